@@ -0,0 +1,39 @@
+// 插件页面运行在主应用的 iframe 中, 通过主应用注入的 bridge 调用后端命令
+interface Bridge {
+  send<T>(command: string, payload: any | undefined): Promise<T>;
+}
+
+function bridge(): Bridge {
+  const win = window as any;
+  const found = win.bridge ?? win.parent?.bridge;
+  if (!found) throw new Error('bridge not found');
+  return found;
+}
+
+export function send<T>(command: string, payload?: any): Promise<T> {
+  return bridge().send<T>(command, payload);
+}
+
+export interface PluginMetrics {
+  id: string;
+  name: string;
+  method: string;
+  calls: number;
+  errors: number;
+  in_flight: number;
+  p50: number | null;
+  p90: number | null;
+  p99: number | null;
+}
+
+export const pluginMetrics = () => send<PluginMetrics[]>('plugin_metrics');
+
+export interface Recording {
+  recording: boolean;
+  path: string | null;
+}
+
+export const startRecording = (path: string | null) =>
+  send<Recording>('start_recording', { path });
+export const stopRecording = () => send<Recording>('stop_recording');
+export const recordingState = () => send<Recording>('recording_state');
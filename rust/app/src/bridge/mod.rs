@@ -2,7 +2,7 @@ mod mode;
 
 use crate::bridge::Plugin;
 pub use mode::*;
-use plugin_manager::manager::PluginManager;
+use plugin_manager::manager::{PluginId, PluginManager};
 use window::WindowState;
 
 /**
@@ -10,7 +10,10 @@ use window::WindowState;
  */
 #[window::bridge]
 pub fn list_plugins(WindowState(pm): WindowState<PluginManager>) -> Vec<Plugin> {
-    pm.list().into_iter().map(Plugin::from).collect()
+    pm.list()
+        .into_iter()
+        .map(|(id, info)| Plugin::from((id, info, plugin_state(&pm, &id))))
+        .collect()
 }
 
 /**
@@ -21,6 +24,29 @@ pub fn call(id: String) -> String {
     format!("called {}", id)
 }
 
+/**
+ * 并发调用多个插件方法, 结果按顺序返回
+ */
+#[window::bridge]
+pub async fn call_batch(
+    p: BatchParam,
+    WindowState(pm): WindowState<PluginManager>,
+) -> std::result::Result<Vec<BatchItem>, String> {
+    let calls = p
+        .calls
+        .into_iter()
+        .map(|c| {
+            let input = plugin::dispatch_input(&c.method, c.params);
+            plugin_id(&c.id).map(|id| (id, input))
+        })
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+    let results = pm
+        .call_batch(calls, p.mode.into())
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(results.into_iter().map(BatchItem::from).collect())
+}
+
 /**
  * 扫描指定位置的插件
  */
@@ -32,3 +58,224 @@ pub fn scan_plugins(
     let scan = crate::plugin::scan_plugins(p.path, p.load_exists, pm).map_err(|e| e.to_string())?;
     Ok(ScanResult::from(scan))
 }
+
+/**
+ * 从安装包安装插件并加载
+ */
+#[window::bridge]
+pub fn install_plugin(
+    path: String,
+    WindowState(pm): WindowState<PluginManager>,
+) -> std::result::Result<Plugin, String> {
+    let path = crate::plugin::resolve(&path);
+    let id = pm.install(path).map_err(|e| e.to_string())?;
+    plugin_of(&pm, id)
+}
+
+/**
+ * 卸载并删除通过安装包安装的插件
+ */
+#[window::bridge]
+pub fn uninstall_plugin(
+    id: String,
+    WindowState(pm): WindowState<PluginManager>,
+) -> std::result::Result<Plugin, String> {
+    let id = plugin_id(&id)?;
+    let info = pm.uninstall(&id).map_err(|e| e.to_string())?;
+    Ok(Plugin::from((id, info, PluginState::Disabled)))
+}
+
+/**
+ * 使用新的安装包更新插件
+ */
+#[window::bridge]
+pub fn update_plugin(
+    id: String,
+    path: String,
+    WindowState(pm): WindowState<PluginManager>,
+) -> std::result::Result<Plugin, String> {
+    let id = plugin_id(&id)?;
+    let path = crate::plugin::resolve(&path);
+    let id = pm.update(&id, path).map_err(|e| e.to_string())?;
+    plugin_of(&pm, id)
+}
+
+/**
+ * 列出插件仓库中的插件及其安装状态
+ */
+#[window::bridge]
+pub fn store_plugins(
+    index: String,
+    WindowState(pm): WindowState<PluginManager>,
+) -> std::result::Result<Vec<StorePlugin>, String> {
+    let registry = crate::plugin::registry(&index).map_err(|e| e.to_string())?;
+    Ok(registry
+        .available(&pm)
+        .into_iter()
+        .map(StorePlugin::from)
+        .collect())
+}
+
+/**
+ * 从插件仓库安装插件, 未指定版本时安装最高版本
+ */
+#[window::bridge]
+pub fn store_install(
+    index: String,
+    name: String,
+    version: Option<String>,
+    WindowState(pm): WindowState<PluginManager>,
+) -> std::result::Result<Plugin, String> {
+    let registry = crate::plugin::registry(&index).map_err(|e| e.to_string())?;
+    let id = registry
+        .install(&pm, &name, version.as_deref())
+        .map_err(|e| e.to_string())?;
+    plugin_of(&pm, id)
+}
+
+/**
+ * 将已安装的插件更新到插件仓库中的最高版本
+ */
+#[window::bridge]
+pub fn store_update(
+    index: String,
+    name: String,
+    WindowState(pm): WindowState<PluginManager>,
+) -> std::result::Result<Plugin, String> {
+    let registry = crate::plugin::registry(&index).map_err(|e| e.to_string())?;
+    let id = registry.update(&pm, &name).map_err(|e| e.to_string())?;
+    plugin_of(&pm, id)
+}
+
+/**
+ * 返回各插件方法的调用指标
+ */
+#[window::bridge]
+pub fn plugin_metrics(WindowState(pm): WindowState<PluginManager>) -> Vec<PluginMetrics> {
+    pm.metrics()
+        .snapshot()
+        .into_iter()
+        .map(|s| {
+            let info = pm.get(&s.id);
+            PluginMetrics::from((s, info))
+        })
+        .collect()
+}
+
+/**
+ * 开始录制插件调用, 未指定文件时自动生成
+ */
+#[window::bridge]
+pub fn start_recording(
+    path: Option<String>,
+    WindowState(pm): WindowState<PluginManager>,
+) -> std::result::Result<Recording, String> {
+    let path = crate::plugin::record_path(path);
+    pm.recorder().start(&path).map_err(|e| e.to_string())?;
+    Ok(Recording::from(pm.recorder().recording()))
+}
+
+/**
+ * 停止录制插件调用, 返回录制的文件
+ */
+#[window::bridge]
+pub fn stop_recording(
+    WindowState(pm): WindowState<PluginManager>,
+) -> std::result::Result<Recording, String> {
+    let path = pm.recorder().stop().map_err(|e| e.to_string())?;
+    Ok(Recording {
+        recording: false,
+        path: path.map(|p| p.to_string_lossy().to_string()),
+    })
+}
+
+/**
+ * 返回插件调用的录制状态
+ */
+#[window::bridge]
+pub fn recording_state(WindowState(pm): WindowState<PluginManager>) -> Recording {
+    Recording::from(pm.recorder().recording())
+}
+
+/**
+ * 清除插件方法的缓存结果, 未指定插件时清除全部, 返回清除的数量
+ */
+#[window::bridge]
+pub fn clear_cache(
+    id: Option<String>,
+    WindowState(pm): WindowState<PluginManager>,
+) -> std::result::Result<usize, String> {
+    match id {
+        Some(id) => Ok(pm.cache().invalidate(&plugin_id(&id)?)),
+        None => Ok(pm.cache().clear()),
+    }
+}
+
+/**
+ * 启用并加载插件, 重启后保持启用
+ */
+#[window::bridge]
+pub fn enable_plugin(
+    id: String,
+    WindowState(pm): WindowState<PluginManager>,
+) -> std::result::Result<Plugin, String> {
+    let id = plugin_id(&id)?;
+    pm.enable(&id).map_err(|e| e.to_string())?;
+    plugin_of(&pm, id)
+}
+
+/**
+ * 禁用并卸载插件, 插件仍会被列出, 重启后保持禁用
+ */
+#[window::bridge]
+pub fn disable_plugin(
+    id: String,
+    WindowState(pm): WindowState<PluginManager>,
+) -> std::result::Result<Plugin, String> {
+    let id = plugin_id(&id)?;
+    pm.disable(&id).map_err(|e| e.to_string())?;
+    plugin_of(&pm, id)
+}
+
+/**
+ * 返回插件声明的定时任务及其执行状态
+ */
+#[window::bridge]
+pub fn list_jobs(WindowState(pm): WindowState<PluginManager>) -> Vec<PluginJob> {
+    pm.scheduler()
+        .list()
+        .into_iter()
+        .map(|job| {
+            let info = pm.get(&job.id);
+            PluginJob::from((job, info))
+        })
+        .collect()
+}
+
+/**
+ * 立即执行插件的定时任务
+ */
+#[window::bridge]
+pub async fn run_job(
+    id: String,
+    name: String,
+    WindowState(pm): WindowState<PluginManager>,
+) -> std::result::Result<BatchItem, String> {
+    let id = plugin_id(&id)?;
+    let result = pm.run_job(&id, &name).await.map_err(|e| e.to_string())?;
+    Ok(BatchItem::from(result))
+}
+
+fn plugin_id(id: &str) -> std::result::Result<PluginId, String> {
+    id.parse().map_err(|_| format!("invalid plugin id: {id}"))
+}
+
+fn plugin_of(pm: &PluginManager, id: PluginId) -> std::result::Result<Plugin, String> {
+    pm.get(&id)
+        .map(|info| Plugin::from((id, info, plugin_state(pm, &id))))
+        .ok_or(format!("plugin not found: {id}"))
+}
+
+fn plugin_state(pm: &PluginManager, id: &PluginId) -> PluginState {
+    pm.state(id).map(PluginState::from).unwrap_or_default()
+}
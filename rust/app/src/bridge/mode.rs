@@ -1,5 +1,16 @@
-use plugin_manager::manager::{PluginId, PluginInfo};
+use plugin::Value;
+use plugin_manager::{
+    batch::{self, BatchResult},
+    manager::{self, PluginId, PluginInfo},
+    metrics::MethodSnapshot,
+    registry::Available,
+    schedule::JobInfo,
+};
 use serde::{Deserialize, Serialize};
+use std::{
+    path::PathBuf,
+    time::{SystemTime, UNIX_EPOCH},
+};
 
 #[derive(Debug, Serialize, Deserialize, Default)]
 pub struct Plugin {
@@ -7,15 +18,42 @@ pub struct Plugin {
     name: String,
     version: String,
     url: String,
+    /// 被禁用的插件不会被加载
+    enabled: bool,
+    state: PluginState,
 }
 
-impl From<(PluginId, PluginInfo)> for Plugin {
-    fn from(value: (PluginId, PluginInfo)) -> Self {
+#[derive(Debug, Serialize, Deserialize, Default, Clone, Copy, PartialEq)]
+pub enum PluginState {
+    /// 已注册, 首次调用时加载
+    #[default]
+    Registered,
+    Loaded,
+    /// 空闲超时被卸载, 再次调用时加载
+    IdleUnloaded,
+    Disabled,
+}
+
+impl From<manager::PluginState> for PluginState {
+    fn from(value: manager::PluginState) -> Self {
+        match value {
+            manager::PluginState::Registered => Self::Registered,
+            manager::PluginState::Loaded => Self::Loaded,
+            manager::PluginState::IdleUnloaded => Self::IdleUnloaded,
+            manager::PluginState::Disabled => Self::Disabled,
+        }
+    }
+}
+
+impl From<(PluginId, PluginInfo, PluginState)> for Plugin {
+    fn from(value: (PluginId, PluginInfo, PluginState)) -> Self {
         Self {
             id: value.0.to_string(),
             name: value.1.name,
             version: value.1.version,
             url: value.1.url,
+            enabled: value.2 != PluginState::Disabled,
+            state: value.2,
         }
     }
 }
@@ -31,6 +69,8 @@ pub struct ScanResult {
     pub loaded: Vec<String>,
     pub failds: Vec<ScanFailItem>,
     pub ignores: Vec<String>,
+    /// 被禁用而未加载的插件
+    pub disabled: Vec<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Default)]
@@ -40,8 +80,15 @@ pub struct ScanFailItem {
     pub reason: String,
 }
 
-impl From<(Vec<PluginId>, Vec<(String, String, String)>, Vec<String>)> for ScanResult {
-    fn from(value: (Vec<PluginId>, Vec<(String, String, String)>, Vec<String>)) -> Self {
+type Scanned = (
+    Vec<PluginId>,
+    Vec<(String, String, String)>,
+    Vec<String>,
+    Vec<PluginId>,
+);
+
+impl From<Scanned> for ScanResult {
+    fn from(value: Scanned) -> Self {
         let loaded = value.0.into_iter().map(|id| id.to_string()).collect();
         let failds = value
             .1
@@ -49,10 +96,196 @@ impl From<(Vec<PluginId>, Vec<(String, String, String)>, Vec<String>)> for ScanR
             .map(|(url, path, reason)| ScanFailItem { url, path, reason })
             .collect();
         let ignores = value.2;
+        let disabled = value.3.into_iter().map(|id| id.to_string()).collect();
         Self {
             loaded,
             failds,
             ignores,
+            disabled,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Default)]
+pub struct StorePlugin {
+    pub name: String,
+    pub description: String,
+    pub versions: Vec<String>,
+    pub latest: Option<String>,
+    /// 已安装的插件id
+    pub id: Option<String>,
+    /// 已安装的版本
+    pub installed: Option<String>,
+    pub has_update: bool,
+}
+
+impl From<Available> for StorePlugin {
+    fn from(value: Available) -> Self {
+        let has_update = value.has_update();
+        let (id, installed) = match value.installed {
+            Some((id, version)) => (Some(id.to_string()), Some(version)),
+            None => (None, None),
+        };
+        Self {
+            name: value.name,
+            description: value.description,
+            versions: value.versions,
+            latest: value.latest,
+            id,
+            installed,
+            has_update,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Default)]
+pub struct PluginMetrics {
+    pub id: String,
+    pub name: String,
+    pub method: String,
+    pub calls: u64,
+    pub errors: u64,
+    pub in_flight: u64,
+    /// 时间窗口内的延迟(毫秒)
+    pub p50: Option<f64>,
+    pub p90: Option<f64>,
+    pub p99: Option<f64>,
+}
+
+impl From<(MethodSnapshot, Option<PluginInfo>)> for PluginMetrics {
+    fn from(value: (MethodSnapshot, Option<PluginInfo>)) -> Self {
+        let (snapshot, info) = value;
+        let quantile = |q: f64| {
+            snapshot
+                .quantiles
+                .iter()
+                .find(|(quantile, _)| *quantile == q)
+                .map(|(_, d)| d.as_secs_f64() * 1000.0)
+        };
+        Self {
+            id: snapshot.id.to_string(),
+            name: info.map(|i| i.name).unwrap_or_default(),
+            p50: quantile(0.5),
+            p90: quantile(0.9),
+            p99: quantile(0.99),
+            method: snapshot.method,
+            calls: snapshot.calls,
+            errors: snapshot.errors,
+            in_flight: snapshot.in_flight,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Default)]
+pub struct Recording {
+    pub recording: bool,
+    /// 正在录制或刚停止录制的文件
+    pub path: Option<String>,
+}
+
+impl From<Option<PathBuf>> for Recording {
+    fn from(value: Option<PathBuf>) -> Self {
+        Self {
+            recording: value.is_some(),
+            path: value.map(|p| p.to_string_lossy().to_string()),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Default)]
+pub struct BatchParam {
+    pub calls: Vec<BatchCall>,
+    #[serde(default)]
+    pub mode: BatchMode,
+}
+
+/// 任一项失败时整体失败, 或各项独立返回
+#[derive(Debug, Serialize, Deserialize, Default, Clone, Copy)]
+pub enum BatchMode {
+    AllOrNothing,
+    #[default]
+    ContinueOnError,
+}
+
+impl From<BatchMode> for batch::BatchMode {
+    fn from(value: BatchMode) -> Self {
+        match value {
+            BatchMode::AllOrNothing => Self::AllOrNothing,
+            BatchMode::ContinueOnError => Self::ContinueOnError,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Default)]
+pub struct BatchCall {
+    pub id: String,
+    pub method: String,
+    #[serde(default)]
+    pub params: Value,
+}
+
+#[derive(Debug, Serialize, Deserialize, Default)]
+pub struct BatchItem {
+    pub ok: bool,
+    pub result: Option<Value>,
+    pub error: Option<String>,
+}
+
+impl From<BatchResult> for BatchItem {
+    fn from(value: BatchResult) -> Self {
+        match value {
+            Ok(result) => Self {
+                ok: true,
+                result: Some(result),
+                error: None,
+            },
+            Err(error) => Self {
+                ok: false,
+                result: None,
+                error: Some(error),
+            },
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Default)]
+pub struct PluginJob {
+    pub id: String,
+    pub plugin: String,
+    pub name: String,
+    pub method: String,
+    pub schedule: String,
+    /// 插件被禁用时暂停
+    pub paused: bool,
+    pub running: bool,
+    /// 时间戳(毫秒)
+    pub next_run: Option<u64>,
+    pub last_run: Option<u64>,
+    /// 上次执行耗时(毫秒)
+    pub last_duration: Option<f64>,
+    pub last_result: Option<BatchItem>,
+}
+
+impl From<(JobInfo, Option<PluginInfo>)> for PluginJob {
+    fn from(value: (JobInfo, Option<PluginInfo>)) -> Self {
+        let (job, info) = value;
+        let millis = |time: SystemTime| {
+            time.duration_since(UNIX_EPOCH)
+                .map(|d| d.as_millis() as u64)
+                .unwrap_or_default()
+        };
+        Self {
+            id: job.id.to_string(),
+            plugin: info.map(|i| i.name).unwrap_or_default(),
+            name: job.name,
+            method: job.method,
+            schedule: job.schedule,
+            paused: job.paused,
+            running: job.running,
+            next_run: job.next_run.map(millis),
+            last_run: job.last_run.map(millis),
+            last_duration: job.last_duration.map(|d| d.as_secs_f64() * 1000.0),
+            last_result: job.last_result.map(BatchItem::from),
         }
     }
 }
@@ -1,9 +1,10 @@
 mod bridge;
 mod plugin;
 
+use bus::Bus;
 use libcommon::{logsetup, prelude::*};
-use plugin_manager::manager::PluginManager;
-use std::sync::Arc;
+use plugin_manager::{intercept::TimingLog, manager::PluginManager};
+use std::{sync::Arc, time::Duration};
 use window::{WindowManager, generate};
 
 use crate::bridge::*;
@@ -11,10 +12,41 @@ use crate::bridge::*;
 #[tokio::main]
 #[logsetup]
 async fn main() -> Result<()> {
-    let pm = Arc::new(PluginManager::default());
-    let wm = WindowManager::with_state(pm.clone());
+    let bus = Arc::new(Bus::with_policy(crate::plugin::bus_policy()));
+    let pm = PluginManager::default()
+        .with_bus(bus.clone())
+        .with_states(crate::plugin::states()?)
+        .with_lazy_load()
+        .with_idle_unload(crate::plugin::IDLE_UNLOAD)
+        .with_verifier(crate::plugin::verifier()?)
+        .with_install_dir(crate::plugin::resolve(crate::plugin::PLUGIN_DIR))
+        .with_interceptor(TimingLog::slow(Duration::from_millis(500)));
+    let pm = Arc::new(pm);
+    pm.spawn_scheduler();
+    pm.spawn_idle_unload();
+    let wm = WindowManager::with_state(pm.clone()).with_bus(bus);
 
     wm.create_window("Start", "http://localhost:3000/app/")?;
-    wm.register(generate!(call, list_plugins, scan_plugins));
+    wm.register(generate!(
+        call,
+        call_batch,
+        list_plugins,
+        scan_plugins,
+        install_plugin,
+        uninstall_plugin,
+        update_plugin,
+        store_plugins,
+        store_install,
+        store_update,
+        plugin_metrics,
+        start_recording,
+        stop_recording,
+        recording_state,
+        clear_cache,
+        enable_plugin,
+        disable_plugin,
+        list_jobs,
+        run_job
+    ));
     wm.run()
 }
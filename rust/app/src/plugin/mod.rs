@@ -1,37 +1,114 @@
-use crate::plugin::scan::Config;
+use bus::{Access, Acl};
 use libcommon::{curr_dir, newerr, prelude::*};
-use plugin_manager::manager::{PluginId, PluginManager};
+use plugin_manager::{
+    err::PluginManagerError,
+    manager::{PluginId, PluginManager},
+    manifest::Manifest,
+    query::Query,
+    registry::Registry,
+    state::PluginStates,
+    verify::{Verifier, VerifyPolicy},
+};
 use std::{
-    fs,
+    env, fs,
     path::{Path, PathBuf},
     sync::Arc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
 };
 mod scan;
 
+/// 受信任的插件发布者公钥(ed25519, hex)
+const TRUSTED_KEYS: &[&str] = &[];
+/// 校验策略的环境变量, 可选 off/warn/enforce, 默认 warn
+const VERIFY_POLICY_ENV: &str = "PLUGIN_VERIFY";
+/// 插件目录, 启动时扫描, 安装包也安装在此
+pub const PLUGIN_DIR: &str = "../plugins/.dir";
+/// 空闲超过该时间的插件被卸载, 再次调用时重新加载
+pub const IDLE_UNLOAD: Duration = Duration::from_secs(600);
+/// 插件的启用状态
+const STATE_FILE: &str = "../plugins/.data/plugins.json";
+/// 未指定文件时插件调用录制的保存目录
+const RECORD_DIR: &str = "../plugins/.records";
+
+///
+/// 消息总线的权限: `app/` 下的主题只允许应用本身发布
+pub fn bus_policy() -> Acl {
+    Acl::default()
+        .deny("plugin/+", "app/#", Access::Publish)
+        .deny("window/+", "app/#", Access::Publish)
+}
+
+///
+/// 创建加载插件库前使用的校验器
+pub fn verifier() -> Result<Verifier> {
+    let policy = match env::var(VERIFY_POLICY_ENV) {
+        Ok(policy) => policy.parse()?,
+        Err(_) => VerifyPolicy::Warn,
+    };
+    let mut verifier = Verifier::new(policy);
+    for key in TRUSTED_KEYS {
+        verifier = verifier.trust(key)?;
+    }
+    Ok(verifier)
+}
+
+///
+/// 读取保存的插件启用状态
+pub fn states() -> Result<PluginStates> {
+    Ok(PluginStates::open(resolve(STATE_FILE))?)
+}
+
+///
+/// 录制文件路径, 未指定时在 [`RECORD_DIR`] 下按时间生成
+pub fn record_path(path: Option<String>) -> PathBuf {
+    match path {
+        Some(path) => resolve(&path),
+        None => {
+            let secs = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or_default();
+            resolve(RECORD_DIR).join(format!("record-{secs}.jsonl"))
+        }
+    }
+}
+
 pub fn scan_plugins(
     path: String,
     load_exist: bool,
     pm: Arc<PluginManager>,
-) -> Result<(Vec<PluginId>, Vec<(String, String, String)>, Vec<String>)> {
-    let mut new_path = PathBuf::from(&path);
-    if new_path.is_relative()
-        && let Ok(curr) = curr_dir!(&path)
-    {
-        new_path = curr;
-    }
+) -> Result<(
+    Vec<PluginId>,
+    Vec<(String, String, String)>,
+    Vec<String>,
+    Vec<PluginId>,
+)> {
+    let new_path = resolve(&path);
 
     debug!("start scan plugins from {new_path:?}");
     let mut ids = Vec::new();
     let mut fails = Vec::new();
     let mut ignores = Vec::new();
+    let mut disabled = Vec::new();
     let plugins = scan_path(new_path)?;
-    for UrlAndLib(url, lib) in plugins {
-        if !load_exist && let Some(id) = pm.find((Some(url.clone()), Some(lib.clone()))) {
-            ignores.push(id.to_string());
-            debug!("plugin ({url},{lib}) already loaded before scan, ignore.");
-            continue;
+    for UrlAndLib(url, lib, manifest) in plugins {
+        if !load_exist {
+            match loaded_before(&pm, &url, &lib) {
+                Ok(Some(id)) => {
+                    ignores.push(id.to_string());
+                    debug!("plugin ({url},{lib}) already loaded before scan, ignore.");
+                    continue;
+                }
+                Ok(None) => {}
+                Err(e) => {
+                    // 无法确定对应哪个已加载的插件, 不注册以免重复加载
+                    warn!("plugin ({url},{lib}) is ambiguous: {e}");
+                    fails.push((url, lib, e.to_string()));
+                    continue;
+                }
+            }
         }
-        let plugin_id = match pm.load(lib.clone(), url.clone()) {
+        let plugin_id = match pm.register(&manifest) {
             Ok(id) => id,
             Err(e) => {
                 let reason = e.to_string();
@@ -40,15 +117,76 @@ pub fn scan_plugins(
                 continue;
             }
         };
-        ids.push(plugin_id);
+        if pm.is_enabled(&plugin_id) {
+            ids.push(plugin_id);
+        } else {
+            disabled.push(plugin_id);
+        }
     }
     debug!(
-        "scan plugins done, {} loaded, {} failed, {} ignored",
+        "scan plugins done, {} registered, {} failed, {} ignored, {} disabled",
         ids.len(),
         fails.len(),
-        ignores.len()
+        ignores.len(),
+        disabled.len()
     );
-    Ok((ids, fails, ignores))
+    Ok((ids, fails, ignores, disabled))
+}
+
+///
+/// 页面地址或库文件相同的插件
+///
+/// 匹配多个, 或页面地址和库文件分别匹配到不同的插件时返回 [`PluginManagerError::Ambiguous`]
+fn loaded_before(
+    pm: &PluginManager,
+    url: &str,
+    lib: &str,
+) -> Result<Option<PluginId>, PluginManagerError> {
+    let by_url = matched(pm.query_one(&Query::default().with_url(url)))?;
+    let by_lib = matched(pm.query_one(&Query::default().with_lib(lib)))?;
+    match (by_url, by_lib) {
+        (Some(a), Some(b)) if a != b => Err(PluginManagerError::Ambiguous(
+            [a, b]
+                .into_iter()
+                .filter_map(|id| pm.get(&id).map(|info| (id, info)))
+                .map(|(id, info)| format!("{}@{} ({id})", info.name, info.version))
+                .collect(),
+        )),
+        (a, b) => Ok(a.or(b)),
+    }
+}
+
+///
+/// 没有匹配的插件时为 `None`
+fn matched(
+    result: Result<PluginId, PluginManagerError>,
+) -> Result<Option<PluginId>, PluginManagerError> {
+    match result {
+        Ok(id) => Ok(Some(id)),
+        Err(PluginManagerError::NoMatch) => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+///
+/// 打开插件仓库, 相对路径基于当前目录
+pub fn registry(index: &str) -> Result<Registry> {
+    if index.contains("://") {
+        return Ok(Registry::open(index)?);
+    }
+    Ok(Registry::open(&resolve(index).to_string_lossy())?)
+}
+
+///
+/// 将相对路径解析为基于当前目录的路径
+pub fn resolve(path: &str) -> PathBuf {
+    let mut new_path = PathBuf::from(path);
+    if new_path.is_relative()
+        && let Ok(curr) = curr_dir!(path)
+    {
+        new_path = curr;
+    }
+    new_path
 }
 
 fn scan_path(dir: impl AsRef<Path>) -> Result<Vec<UrlAndLib>> {
@@ -67,68 +205,19 @@ fn scan_path(dir: impl AsRef<Path>) -> Result<Vec<UrlAndLib>> {
     Ok(result)
 }
 
-pub struct UrlAndLib(pub String, pub String);
-impl TryFrom<&Config> for UrlAndLib {
+pub struct UrlAndLib(pub String, pub String, pub Manifest);
+impl TryFrom<&Manifest> for UrlAndLib {
     type Error = libcommon::prelude::Err;
 
-    fn try_from(config: &Config) -> Result<Self, Self::Error> {
-        let file = config.files();
-        let url = if let Some(file) = file
-            && let Some(url) = &file.url
-        {
-            url.clone()
-        } else {
-            let dir = config
-                .dir
-                .as_ref()
-                .ok_or(newerr!("no url and read dir fail"))?;
-            format!("{dir}/index.html")
-        };
-
-        let lib = if let Some(file) = file
-            && let Some(lib) = &file.lib
-        {
-            let path = Path::new(lib);
-            if path.is_relative()
-                && let Some(dir) = &config.dir
-            {
-                Path::new(dir).join(path).to_string_lossy().to_string()
-            } else {
-                lib.clone()
-            }
-        } else {
-            let dir = config
-                .dir
-                .as_ref()
-                .ok_or(newerr!("no lib and read dir fail"))?;
-            let name = config.name.as_ref().ok_or(newerr!("no lib and no name"))?;
-            let version = config
-                .version
-                .as_ref()
-                .ok_or(newerr!("no lib and no version"))?;
-            format!("{dir}/{name}-v{version}.{}", ext())
-        };
+    fn try_from(manifest: &Manifest) -> Result<Self, Self::Error> {
+        let url = manifest.url()?;
+        let lib = manifest.lib()?;
 
         if !fs::exists(&lib)? {
             return Err(newerr!("not exist: {}", lib));
         }
 
-        Ok(UrlAndLib(url, lib))
-    }
-}
-
-fn ext() -> &'static str {
-    #[cfg(target_os = "windows")]
-    {
-        "dll"
-    }
-    #[cfg(target_os = "linux")]
-    {
-        "so"
-    }
-    #[cfg(target_os = "macos")]
-    {
-        "dylib"
+        Ok(UrlAndLib(url, lib, manifest.clone()))
     }
 }
 
@@ -142,7 +231,7 @@ mod tests {
     fn test() -> libcommon::prelude::Result<()> {
         let dir = curr_dir!("../../plugins/.dir")?;
         let fs = scan_path(dir)?;
-        for UrlAndLib(url, lib) in fs {
+        for UrlAndLib(url, lib, ..) in fs {
             println!("{url}");
             println!("{lib}")
         }
@@ -0,0 +1,118 @@
+use serde::{Deserialize, Serialize};
+use std::fmt::Display;
+
+use crate::topic::matches;
+
+/// 消息的来源或订阅者
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Source {
+    /// 应用本身(Rust 代码)
+    Host,
+    /// 插件, 值为插件名
+    Plugin(String),
+    /// 窗口, 值为窗口 id
+    Window(String),
+}
+
+impl Display for Source {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Source::Host => write!(f, "host"),
+            Source::Plugin(name) => write!(f, "plugin/{name}"),
+            Source::Window(id) => write!(f, "window/{id}"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Access {
+    Publish,
+    Subscribe,
+}
+
+impl Display for Access {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Access::Publish => write!(f, "publish"),
+            Access::Subscribe => write!(f, "subscribe"),
+        }
+    }
+}
+
+///
+/// 发布/订阅的权限检查
+///
+/// 订阅的权限在投递时按消息的主题检查, 订阅者只会收到有权限的消息
+pub trait Policy: Send + Sync {
+    fn check(&self, source: &Source, access: Access, topic: &str) -> bool;
+}
+
+///
+/// 按规则检查权限, 按添加顺序使用第一条匹配的规则, 没有匹配的规则时使用默认值
+///
+/// 规则中的来源和主题都按主题规则匹配, 来源的形式见 [`Source`] 的显示, 如 `plugin/+`。
+/// [`Source::Host`] 不受限制
+#[derive(Debug, Clone)]
+pub struct Acl {
+    rules: Vec<Rule>,
+    default: bool,
+}
+
+#[derive(Debug, Clone)]
+struct Rule {
+    source: String,
+    topic: String,
+    access: Access,
+    allow: bool,
+}
+
+impl Default for Acl {
+    fn default() -> Self {
+        Self {
+            rules: Vec::new(),
+            default: true,
+        }
+    }
+}
+
+impl Acl {
+    ///
+    /// 没有匹配规则时拒绝
+    pub fn deny_by_default() -> Self {
+        Self {
+            rules: Vec::new(),
+            default: false,
+        }
+    }
+
+    pub fn allow(self, source: &str, topic: &str, access: Access) -> Self {
+        self.rule(source, topic, access, true)
+    }
+
+    pub fn deny(self, source: &str, topic: &str, access: Access) -> Self {
+        self.rule(source, topic, access, false)
+    }
+
+    fn rule(mut self, source: &str, topic: &str, access: Access, allow: bool) -> Self {
+        self.rules.push(Rule {
+            source: source.to_string(),
+            topic: topic.to_string(),
+            access,
+            allow,
+        });
+        self
+    }
+}
+
+impl Policy for Acl {
+    fn check(&self, source: &Source, access: Access, topic: &str) -> bool {
+        if *source == Source::Host {
+            return true;
+        }
+        let source = source.to_string();
+        self.rules
+            .iter()
+            .find(|r| r.access == access && matches(&r.source, &source) && matches(&r.topic, topic))
+            .map_or(self.default, |r| r.allow)
+    }
+}
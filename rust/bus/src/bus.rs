@@ -0,0 +1,274 @@
+use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::sync::{
+    Arc, RwLock,
+    atomic::{AtomicU64, Ordering},
+};
+
+use crate::{
+    Access, Acl, BusError, Policy, Source,
+    topic::{matches, validate_pattern, validate_topic},
+};
+
+pub type SubscriptionId = u64;
+
+/// 订阅者收到的消息
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Message {
+    pub topic: String,
+    pub payload: Value,
+    pub source: Source,
+    /// 是否为订阅时补发的保留消息
+    pub retained: bool,
+}
+
+/// 订阅的回调, 在发布者的线程中调用, 不应阻塞
+pub type Handler = Box<dyn Fn(SubscriptionId, &Message) + Send + Sync>;
+
+/// 主题的保留消息, `seq` 在每次保留时递增, 用于判断补发的消息是否已被替换
+struct Retained {
+    seq: u64,
+    message: Message,
+}
+
+struct Subscription {
+    source: Source,
+    pattern: String,
+    handler: Arc<Handler>,
+}
+
+///
+/// 主题发布/订阅
+pub struct Bus {
+    subscriptions: DashMap<SubscriptionId, Subscription>,
+    retained: DashMap<String, Retained>,
+    policy: RwLock<Box<dyn Policy>>,
+    next: AtomicU64,
+    seq: AtomicU64,
+}
+
+impl Default for Bus {
+    fn default() -> Self {
+        Self::with_policy(Acl::default())
+    }
+}
+
+impl Bus {
+    pub fn with_policy(policy: impl Policy + 'static) -> Self {
+        Self {
+            subscriptions: DashMap::new(),
+            retained: DashMap::new(),
+            seq: AtomicU64::new(0),
+            policy: RwLock::new(Box::new(policy)),
+            next: AtomicU64::new(1),
+        }
+    }
+
+    ///
+    /// 替换权限检查, 对之后的发布和投递生效
+    pub fn set_policy(&self, policy: impl Policy + 'static) {
+        *self.policy.write().unwrap_or_else(|e| e.into_inner()) = Box::new(policy);
+    }
+
+    ///
+    /// 发布消息, `retain` 时保留为该主题的最新消息, 返回收到消息的订阅数
+    pub fn publish(
+        &self,
+        source: Source,
+        topic: &str,
+        payload: Value,
+        retain: bool,
+    ) -> Result<usize, BusError> {
+        validate_topic(topic)?;
+        if !self.check(&source, Access::Publish, topic) {
+            return Err(BusError::Denied {
+                by: source,
+                access: Access::Publish,
+                topic: topic.to_string(),
+            });
+        }
+        let message = Message {
+            topic: topic.to_string(),
+            payload,
+            source,
+            retained: false,
+        };
+        if retain {
+            let retained = Message {
+                retained: true,
+                ..message.clone()
+            };
+            let seq = self.seq.fetch_add(1, Ordering::Relaxed);
+            self.retained.insert(
+                topic.to_string(),
+                Retained {
+                    seq,
+                    message: retained,
+                },
+            );
+        }
+        // 先取出回调再调用, 回调中可以再发布或订阅
+        let targets: Vec<_> = self
+            .subscriptions
+            .iter()
+            .filter(|s| matches(&s.pattern, topic))
+            .map(|s| (*s.key(), s.source.clone(), s.handler.clone()))
+            .collect();
+        let mut delivered = 0;
+        for (id, subscriber, handler) in targets {
+            if self.check(&subscriber, Access::Subscribe, topic) {
+                handler(id, &message);
+                delivered += 1;
+            }
+        }
+        Ok(delivered)
+    }
+
+    ///
+    /// 订阅主题, 订阅后立即按主题顺序收到匹配主题的保留消息
+    pub fn subscribe(
+        &self,
+        source: Source,
+        pattern: &str,
+        handler: impl Fn(SubscriptionId, &Message) + Send + Sync + 'static,
+    ) -> Result<SubscriptionId, BusError> {
+        validate_pattern(pattern)?;
+        let id = self.next.fetch_add(1, Ordering::Relaxed);
+        let handler: Arc<Handler> = Arc::new(Box::new(handler));
+        // 先订阅再取保留消息, 之后发布的消息都会直接投递给新订阅
+        self.subscriptions.insert(
+            id,
+            Subscription {
+                source: source.clone(),
+                pattern: pattern.to_string(),
+                handler: handler.clone(),
+            },
+        );
+        let mut retained: Vec<_> = self
+            .retained
+            .iter()
+            .filter(|m| {
+                matches(pattern, m.key()) && self.check(&source, Access::Subscribe, m.key())
+            })
+            .map(|m| (m.seq, m.message.clone()))
+            .collect();
+        retained.sort_by(|a, b| a.1.topic.cmp(&b.1.topic));
+        for (seq, message) in &retained {
+            // 补发前主题已有新的保留消息时, 新消息已直接投递, 不再补发旧消息
+            if self
+                .retained
+                .get(&message.topic)
+                .is_some_and(|m| m.seq == *seq)
+            {
+                handler(id, message);
+            }
+        }
+        Ok(id)
+    }
+
+    pub fn unsubscribe(&self, id: SubscriptionId) -> bool {
+        self.subscriptions.remove(&id).is_some()
+    }
+
+    ///
+    /// 取消来源的所有订阅, 如插件卸载或窗口关闭时
+    pub fn unsubscribe_all(&self, source: &Source) -> usize {
+        let before = self.subscriptions.len();
+        self.subscriptions.retain(|_, s| s.source != *source);
+        before - self.subscriptions.len()
+    }
+
+    pub fn retained(&self, topic: &str) -> Option<Message> {
+        self.retained.get(topic).map(|m| m.message.clone())
+    }
+
+    ///
+    /// 清除主题的保留消息
+    pub fn clear_retained(&self, topic: &str) -> Option<Message> {
+        self.retained.remove(topic).map(|(_, m)| m.message)
+    }
+
+    fn check(&self, source: &Source, access: Access, topic: &str) -> bool {
+        self.policy
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .check(source, access, topic)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::sync::Mutex;
+
+    #[test]
+    fn test_bus() -> Result<(), BusError> {
+        let bus = Bus::with_policy(
+            Acl::default()
+                .deny("plugin/+", "app/#", Access::Publish)
+                .deny("window/+", "secret/#", Access::Subscribe),
+        );
+        let plugin = Source::Plugin("demo".into());
+        let window = Source::Window("w".into());
+        bus.publish(Source::Host, "app/state", json!(1), true)?;
+        bus.publish(Source::Host, "secret/key", json!("k"), true)?;
+
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let sink = received.clone();
+        let id = bus.subscribe(window.clone(), "#", move |_, m| {
+            sink.lock().unwrap().push((m.topic.clone(), m.retained));
+        })?;
+        assert_eq!(*received.lock().unwrap(), [("app/state".to_string(), true)]);
+
+        assert_eq!(
+            bus.publish(plugin.clone(), "plugin/demo/x", json!(2), false)?,
+            1
+        );
+        assert!(matches!(
+            bus.publish(plugin.clone(), "app/state", json!(3), false),
+            Err(BusError::Denied { .. })
+        ));
+        assert_eq!(bus.publish(Source::Host, "secret/key", json!(4), false)?, 0);
+        assert!(bus.publish(plugin, "a/+", json!(5), false).is_err());
+        assert_eq!(received.lock().unwrap().len(), 2);
+
+        assert_eq!(bus.retained("app/state").map(|m| m.payload), Some(json!(1)));
+        assert_eq!(bus.unsubscribe_all(&window), 1);
+        assert!(!bus.unsubscribe(id));
+        Ok(())
+    }
+
+    #[test]
+    fn test_retained_order() -> Result<(), BusError> {
+        let bus = Arc::new(Bus::default());
+        bus.publish(Source::Host, "state/a", json!(1), true)?;
+        bus.publish(Source::Host, "state/b", json!("old"), true)?;
+
+        // 补发 state/a 时发布新的 state/b, 新订阅不应再收到旧的 state/b
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let sink = received.clone();
+        let publisher = Arc::downgrade(&bus);
+        bus.subscribe(Source::Host, "state/+", move |_, m| {
+            sink.lock()
+                .unwrap()
+                .push((m.topic.clone(), m.payload.clone()));
+            if m.topic == "state/a" {
+                publisher
+                    .upgrade()
+                    .unwrap()
+                    .publish(Source::Host, "state/b", json!("new"), true)
+                    .unwrap();
+            }
+        })?;
+        assert_eq!(
+            *received.lock().unwrap(),
+            [
+                ("state/a".to_string(), json!(1)),
+                ("state/b".to_string(), json!("new"))
+            ]
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,17 @@
+use thiserror::Error;
+
+use crate::{Access, Source};
+
+#[derive(Debug, Error, Clone, PartialEq, Eq)]
+pub enum BusError {
+    #[error("Invalid topic: {0}")]
+    InvalidTopic(String),
+    #[error("Invalid topic pattern: {0}")]
+    InvalidPattern(String),
+    #[error("{by} is not allowed to {access} {topic}")]
+    Denied {
+        by: Source,
+        access: Access,
+        topic: String,
+    },
+}
@@ -0,0 +1,14 @@
+//! 应用内的主题发布/订阅
+//!
+//! 主题以 `/` 分隔层级, 如 `plugin/debug_plugin/state`; 订阅时可使用通配符:
+//! `+` 匹配一个层级, `#` 匹配之后的所有层级(只能在最后)。
+//! 发布时可保留消息, 之后订阅匹配主题的订阅者会先收到保留的最新消息
+mod acl;
+mod bus;
+mod err;
+mod topic;
+
+pub use acl::*;
+pub use bus::*;
+pub use err::BusError;
+pub use topic::{matches, validate_pattern, validate_topic};
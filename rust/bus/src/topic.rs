@@ -0,0 +1,69 @@
+use crate::BusError;
+
+pub(crate) const SEPARATOR: char = '/';
+/// 匹配一个层级
+pub(crate) const SINGLE: &str = "+";
+/// 匹配之后的所有层级
+pub(crate) const MULTI: &str = "#";
+
+///
+/// 发布的主题: 非空, 不含通配符
+pub fn validate_topic(topic: &str) -> Result<(), BusError> {
+    if topic.is_empty() || topic.split(SEPARATOR).any(|l| l == SINGLE || l == MULTI) {
+        return Err(BusError::InvalidTopic(topic.to_string()));
+    }
+    Ok(())
+}
+
+///
+/// 订阅的主题: 非空, `#` 只能是最后一个层级
+pub fn validate_pattern(pattern: &str) -> Result<(), BusError> {
+    let levels: Vec<_> = pattern.split(SEPARATOR).collect();
+    let misplaced = levels[..levels.len() - 1].contains(&MULTI);
+    let partial = levels
+        .iter()
+        .any(|l| *l != SINGLE && *l != MULTI && (l.contains('+') || l.contains('#')));
+    if pattern.is_empty() || misplaced || partial {
+        return Err(BusError::InvalidPattern(pattern.to_string()));
+    }
+    Ok(())
+}
+
+///
+/// 主题是否匹配订阅的主题(可含通配符)
+pub fn matches(pattern: &str, topic: &str) -> bool {
+    let mut topic = topic.split(SEPARATOR);
+    for level in pattern.split(SEPARATOR) {
+        if level == MULTI {
+            return true;
+        }
+        match topic.next() {
+            Some(t) if level == SINGLE || level == t => {}
+            _ => return false,
+        }
+    }
+    topic.next().is_none()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_matches() {
+        assert!(matches("a/b", "a/b"));
+        assert!(!matches("a/b", "a/b/c"));
+        assert!(matches("a/+/c", "a/b/c"));
+        assert!(!matches("a/+", "a"));
+        assert!(matches("a/#", "a"));
+        assert!(matches("a/#", "a/b/c"));
+        assert!(matches("#", "a/b"));
+        assert!(!matches("a/#", "b/a"));
+
+        assert!(validate_topic("a/b").is_ok());
+        assert!(validate_topic("a/+").is_err());
+        assert!(validate_pattern("a/+/#").is_ok());
+        assert!(validate_pattern("a/#/b").is_err());
+        assert!(validate_pattern("a/b+").is_err());
+    }
+}
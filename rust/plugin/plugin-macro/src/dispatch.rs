@@ -1,15 +1,42 @@
 use proc_macro::TokenStream;
 use quote::{format_ident, quote};
-use syn::{FnArg, ImplItem, ItemImpl, PathArguments, ReturnType, Signature, Type, parse_macro_input};
+use syn::{Attribute, FnArg, ImplItem, ImplItemFn, ItemImpl, LitInt, PathArguments, ReturnType, Signature, Type, parse_macro_input};
 
 const PLUGIN_START: &str = "call_";
+const CACHE: &str = "cache";
+const ATTACH: &str = "attach";
 
 pub(crate) fn _plugin_dispatch(_args: TokenStream, input: TokenStream) -> TokenStream {
-    let input = parse_macro_input!(input as ItemImpl);
-    let self_ty = &input.self_ty;
+    let mut input = parse_macro_input!(input as ItemImpl);
 
+    // 取出方法上的 #[cache(ttl = 秒)], 该属性只供本宏使用
+    let mut cacheable = Vec::new();
+    for ele in &mut input.items {
+        if let ImplItem::Fn(method) = ele {
+            match take_cache(method) {
+                Ok(Some(ttl)) => cacheable.push((method.sig.ident.to_string(), ttl)),
+                Ok(None) => {}
+                Err(e) => return e.to_compile_error().into(),
+            }
+        }
+    }
+    let cacheable = cacheable.iter().map(|(name, ttl)| quote! { (#name, ::std::time::Duration::from_secs(#ttl)) });
+
+    let self_ty = &input.self_ty;
     let mut methods = Vec::new();
 
+    // impl 中有 fn attach(&self, host: ::plugin::Host) 时转发 Plugin::attach
+    let has_attach = input.items.iter().any(|ele| matches!(ele, ImplItem::Fn(method) if method.sig.ident == ATTACH && method.sig.asyncness.is_none()));
+    let attach = if has_attach {
+        quote! {
+            fn attach(&self, host: ::plugin::Host) {
+                <#self_ty>::attach(self, host)
+            }
+        }
+    } else {
+        quote! {}
+    };
+
     for ele in &input.items {
         if let ImplItem::Fn(method) = ele {
             let name = method.sig.ident.to_string();
@@ -25,40 +52,50 @@ pub(crate) fn _plugin_dispatch(_args: TokenStream, input: TokenStream) -> TokenS
     }
 
     let mut match_arms = Vec::new();
+    let names: Vec<_> = methods.iter().map(|method| method.sig.ident.to_string()).collect();
 
     for method in methods {
         let method_name = method.sig.ident.to_string();
         let method_ident = &method.sig.ident;
 
-        let params:Vec<_> = method.sig.inputs.iter().skip(1).collect();
-        let params_len = params.len();
+        let mut arg_tys = Vec::new();
+        for arg in method.sig.inputs.iter().skip(1) {
+            match arg {
+                FnArg::Typed(pat_type) => arg_tys.push(&pat_type.ty),
+                _ => return newerror(arg, "expected typed parameter"),
+            }
+        }
+        let params_len = arg_tys.len();
+        // 参数转换失败时返回 ValueParseError, 路径为调用参数中的 /params 或 /params/序号
         let (param_extraction,call) = if params_len==0 {
             (quote! {},
             
              quote! { self.#method_ident().await })
         } else if params_len == 1 {
-            let arg_ty = match params[0] {
-                FnArg::Typed(pat_type) => &pat_type.ty,
-                _ => return newerror(params[0], "expected typed parameter")
-            };
+            let arg_ty = arg_tys[0];
             (quote! {
-                let arg: #arg_ty = ::plugin::from_value(params.clone())?;
+                let arg: #arg_ty = ::plugin::try_from_value(params).map_err(|e| e.at("params"))?;
             },
             quote! { self.#method_ident(arg).await })
         } else {
-            let tuple_types = params.iter().map(|arg| {
-                 match arg {
-                    FnArg::Typed(pat_type) => &pat_type.ty,
-                    _ => panic!("expected typed parameter")
-                }
-            });
-            let tuple_tys = quote! { (#(#tuple_types),*) };
-            let tuple_vars = (0..params_len).map(|i| format_ident!("arg{i}") );
-            let tuple_vars2 = (0..params_len).map(|i| format_ident!("arg{i}") );
+            let tuple_vars: Vec<_> = (0..params_len).map(|i| format_ident!("arg{i}")).collect();
+            let indexes = 0..params_len;
             (quote! {
-                let (#(#tuple_vars,)*): #tuple_tys = ::plugin::from_value(params.clone())?; 
+                let args = match params {
+                    ::plugin::Value::Array(args) if args.len() == #params_len => args,
+                    ::plugin::Value::Array(args) => {
+                        let message = format!("expected {} arguments, found {}", #params_len, args.len());
+                        return Err(::plugin::ValueParseError::new("array", "array").with_message(message).at("params").into());
+                    }
+                    other => return Err(::plugin::ValueParseError::new("array", other.type_name()).at("params").into()),
+                };
+                let mut args = args.into_iter();
+                #(
+                    let #tuple_vars: #arg_tys = ::plugin::try_from_value(args.next().unwrap_or_default())
+                        .map_err(|e| e.at_index(#indexes).at("params"))?;
+                )*
             },
-            quote! { self.#method_ident(#(#tuple_vars2),*).await })
+            quote! { self.#method_ident(#(#tuple_vars),*).await })
         };
 
         let is_result = match is_result(&method.sig) {
@@ -91,18 +128,28 @@ pub(crate) fn _plugin_dispatch(_args: TokenStream, input: TokenStream) -> TokenS
             async fn call(&self, input: ::plugin::Value) -> ::std::result::Result<::plugin::Value, Box<dyn std::error::Error + Send + Sync>> {
                 let err = |str: &str| Box::<dyn std::error::Error + Send + Sync>::from(str);
                 let (method, params) = match input {
-                    ::plugin::Value::Object(mut map) => {
-                        let method = map.remove("method").ok_or_else(|| err("no method"))?;
-                        let params = map.remove("params").ok_or_else(|| err("no params"))?;
+                    ::plugin::Value::Map(mut map) => {
+                        let method = map.swap_remove("method").ok_or_else(|| err("no method"))?;
+                        let params = map.swap_remove("params").ok_or_else(|| err("no params"))?;
                         (method, params)
                     }
                     _ => return Err(err("input mut be object with method and params")),
                 };
                 match method.as_str().ok_or_else(|| err("method not string"))? {
                     #(#match_arms,)*
-                    _ => Err(err(format!("unknown method: {}", method).as_str())),
+                    method => Err(err(format!("unknown method: {}", method).as_str())),
                 }
             }
+
+            fn cacheable(&self) -> ::std::vec::Vec<(&'static str, ::std::time::Duration)> {
+                ::std::vec![#(#cacheable),*]
+            }
+
+            fn methods(&self) -> ::std::vec::Vec<&'static str> {
+                ::std::vec![#(#names),*]
+            }
+
+            #attach
         }
     };
 
@@ -112,6 +159,24 @@ pub(crate) fn _plugin_dispatch(_args: TokenStream, input: TokenStream) -> TokenS
     }.into()
 }
 
+/// 移除方法上的 `#[cache(ttl = 秒)]` 并返回缓存时间
+fn take_cache(method: &mut ImplItemFn) -> Result<Option<u64>, syn::Error> {
+    let Some(index) = method.attrs.iter().position(|a| a.path().is_ident(CACHE)) else {
+        return Ok(None);
+    };
+    let attr: Attribute = method.attrs.remove(index);
+    let mut ttl = None;
+    attr.parse_nested_meta(|meta| {
+        if meta.path.is_ident("ttl") {
+            ttl = Some(meta.value()?.parse::<LitInt>()?.base10_parse::<u64>()?);
+            Ok(())
+        } else {
+            Err(meta.error("expected `ttl = <seconds>`"))
+        }
+    })?;
+    ttl.map(Some).ok_or_else(|| syn::Error::new_spanned(attr, "expected `#[cache(ttl = <seconds>)]`"))
+}
+
 fn newerror<T: quote::ToTokens, U: std::fmt::Display>(tokens:T,message:U)->TokenStream{
     syn::Error::new_spanned(tokens, message).to_compile_error().into()
 }
@@ -1,18 +1,23 @@
 // file: plugin-macro/src/lib.rs
 use proc_macro::TokenStream;
 use quote::quote;
-use syn::{ItemStruct, parse_macro_input};
+use syn::{Fields, ItemStruct, parse_macro_input};
 
 pub(crate) fn _plugin_export(_args: TokenStream, input: TokenStream) -> TokenStream {
     let input = parse_macro_input!(input as ItemStruct);
     let struct_name = &input.ident;
+    // 有字段的结构体(如保存宿主上下文)通过 Default 创建
+    let create = match input.fields {
+        Fields::Unit => quote! { #struct_name },
+        _ => quote! { <#struct_name as ::std::default::Default>::default() },
+    };
 
     let expanded = quote! {
         #input
 
         #[unsafe(no_mangle)]
         pub fn plugin() -> Box<dyn ::plugin::Plugin> {
-            Box::new(#struct_name)
+            Box::new(#create)
         }
     };
     TokenStream::from(expanded)
@@ -1,44 +1,13 @@
 use proc_macro::TokenStream;
 use quote::quote;
-use syn::{parse_macro_input, Data, DeriveInput, Fields, Ident};
+use syn::{parse_macro_input, Data, DeriveInput, Fields, Ident, Type};
 
 pub(crate)  fn _derive_value(input: TokenStream) -> TokenStream {
     let input = parse_macro_input!(input as DeriveInput);
     let name = &input.ident;
     let generics = &input.generics;
-
-    // 只处理结构体，且必须为命名字段
-    let fields = match &input.data {
-        Data::Struct(data) => match &data.fields {
-            Fields::Named(fields) => &fields.named,
-            _ => panic!("Value derive only supports structs with named fields"),
-        },
-        _ => panic!("Value derive only supports structs"),
-    };
-
-    // 收集需要转换的字段（跳过标记了 `#[value(skip)]` 的字段）
-    let field_infos: Vec<_> = fields
-        .iter()
-        .filter_map(|f| {
-            // 检查是否有 `#[value(skip)]` 属性
-            let skip = f.attrs.iter().any(|attr| {
-                attr.path()
-                    .is_ident("value")
-                    && attr
-                        .parse_args::<Ident>()
-                        .map(|ident| ident == "skip")
-                        .unwrap_or(false)
-            });
-            if skip {
-                None
-            } else {
-                // 获取字段名和类型
-                let name = f.ident.as_ref().unwrap();
-                let ty = &f.ty;
-                Some((name, ty))
-            }
-        })
-        .collect();
+    let type_name = name.to_string();
+    let field_infos = field_infos(&input);
 
     // 生成 `From` 实现
     let from_impl = {
@@ -46,13 +15,13 @@ pub(crate)  fn _derive_value(input: TokenStream) -> TokenStream {
         let field_keys: Vec<String> = field_names.iter().map(|name| name.to_string()).collect();
 
         quote! {
-            impl #generics From<#name #generics> for Value {
+            impl #generics From<#name #generics> for ::plugin::Value {
                 fn from(val: #name #generics) -> Self {
-                    let mut map = std::collections::HashMap::new();
+                    let mut map = ::plugin::Map::new();
                     #(
-                        map.insert(#field_keys.to_string(), Value::from(val.#field_names));
+                        map.insert(#field_keys.to_string(), ::plugin::Value::from(val.#field_names));
                     )*
-                    Value::Map(map)
+                    ::plugin::Value::Map(map)
                 }
             }
         }
@@ -65,22 +34,24 @@ pub(crate)  fn _derive_value(input: TokenStream) -> TokenStream {
         let field_keys: Vec<String> = field_names.iter().map(|name| name.to_string()).collect();
 
         quote! {
-            impl #generics TryFrom<Value> for #name #generics {
-                type Error = plugin::ValueParseError;
+            impl #generics TryFrom<::plugin::Value> for #name #generics {
+                type Error = ::plugin::ValueParseError;
 
-                fn try_from(value: Value) -> std::result::Result<Self, Self::Error> {
+                fn try_from(value: ::plugin::Value) -> std::result::Result<Self, Self::Error> {
                     match value {
-                        Value::Map(mut map) => {
+                        ::plugin::Value::Map(mut map) => {
                             Ok(#name {
                                 #(
                                     #field_names: {
-                                        let field_value = map.remove(#field_keys).ok_or(plugin::ValueParseError)?;
-                                        <#field_types>::try_from(field_value)?
+                                        // 缺少的键按 null 转换, 使 Option 字段可以省略
+                                        let field_value = map.swap_remove(#field_keys).unwrap_or_default();
+                                        <#field_types>::try_from(field_value)
+                                            .map_err(|e| ::plugin::ValueParseError::from(e).at(#field_keys))?
                                     },
                                 )*
                             })
                         }
-                        _ => Err(plugin::ValueParseError),
+                        other => Err(::plugin::ValueParseError::new(#type_name, other.type_name())),
                     }
                 }
             }
@@ -94,24 +65,24 @@ pub(crate)  fn _derive_value(input: TokenStream) -> TokenStream {
         let field_keys: Vec<String> = field_names.iter().map(|name| name.to_string()).collect();
 
         quote! {
-            impl #generics TryFrom<&Value> for #name #generics {
-                type Error = plugin::ValueParseError;
+            impl #generics TryFrom<&::plugin::Value> for #name #generics {
+                type Error = ::plugin::ValueParseError;
 
-                fn try_from(value: &Value) -> std::result::Result<Self, Self::Error> {
+                fn try_from(value: &::plugin::Value) -> std::result::Result<Self, Self::Error> {
                     match value {
-                        Value::Map(map) => {
+                        ::plugin::Value::Map(map) => {
                             Ok(#name {
                                 #(
                                     #field_names: {
-                                        let field_value = map.get(#field_keys)
-                                            .ok_or(plugin::ValueParseError)?;
                                         // 克隆字段值再转换，避免消耗原值
-                                        <#field_types>::try_from(field_value.clone())?
+                                        let field_value = map.get(#field_keys).cloned().unwrap_or_default();
+                                        <#field_types>::try_from(field_value)
+                                            .map_err(|e| ::plugin::ValueParseError::from(e).at(#field_keys))?
                                     },
                                 )*
                             })
                         }
-                        _ => Err(plugin::ValueParseError),
+                        other => Err(::plugin::ValueParseError::new(#type_name, other.type_name())),
                     }
                 }
             }
@@ -126,4 +97,70 @@ pub(crate)  fn _derive_value(input: TokenStream) -> TokenStream {
     };
 
     TokenStream::from(expanded)
-}
\ No newline at end of file
+}
+
+///
+/// 生成 `Describe` 实现, 缺少时也能转换的字段为可选的键
+pub(crate) fn _derive_describe(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    let name = &input.ident;
+    let generics = &input.generics;
+    let field_infos = field_infos(&input);
+    let field_types: Vec<_> = field_infos.iter().map(|(_, ty)| ty).collect();
+    let field_keys: Vec<String> = field_infos.iter().map(|(name, _)| name.to_string()).collect();
+
+    let expanded = quote! {
+        impl #generics ::plugin::Describe for #name #generics {
+            fn schema() -> ::plugin::Schema {
+                let schema = ::plugin::Schema::map();
+                #(
+                    let field = <#field_types as ::plugin::Describe>::schema();
+                    let schema = if <#field_types as ::plugin::Describe>::optional() {
+                        schema.with_optional(#field_keys, field)
+                    } else {
+                        schema.with_field(#field_keys, field)
+                    };
+                )*
+                schema
+            }
+        }
+    };
+
+    TokenStream::from(expanded)
+}
+
+/// 结构体中参与转换的字段名和类型
+fn field_infos(input: &DeriveInput) -> Vec<(&Ident, &Type)> {
+    // 只处理结构体，且必须为命名字段
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => panic!("Value derive only supports structs with named fields"),
+        },
+        _ => panic!("Value derive only supports structs"),
+    };
+
+    // 收集需要转换的字段（跳过标记了 `#[value(skip)]` 的字段）
+    fields
+        .iter()
+        .filter_map(|f| {
+            // 检查是否有 `#[value(skip)]` 属性
+            let skip = f.attrs.iter().any(|attr| {
+                attr.path()
+                    .is_ident("value")
+                    && attr
+                        .parse_args::<Ident>()
+                        .map(|ident| ident == "skip")
+                        .unwrap_or(false)
+            });
+            if skip {
+                None
+            } else {
+                // 获取字段名和类型
+                let name = f.ident.as_ref().unwrap();
+                let ty = &f.ty;
+                Some((name, ty))
+            }
+        })
+        .collect()
+}
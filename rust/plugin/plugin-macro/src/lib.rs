@@ -11,6 +11,8 @@ mod fromvalue;
 /// - `TryFrom<Value> for Struct`：从 `Value::Map` 还原结构体（消耗所有权）
 /// - `TryFrom<&Value> for Struct`：从 `&Value` 还原结构体（克隆字段值，不消耗原值）
 ///
+/// 缺少的键按 `Value::Null` 转换, 因此 `Option` 字段可以省略
+///
 /// 支持字段属性：
 /// - `#[value(skip)]`：跳过该字段，不参与转换
 ///
@@ -20,8 +22,28 @@ pub fn derive_value(input: TokenStream) -> TokenStream {
     fromvalue::_derive_value(input)
 }
 
+/// 为结构体派生 `Describe`, 得到对应的 `Schema`, 所有字段类型都需实现 `Describe`
+///
+/// 与 `FromValue` 一致: 跳过 `#[value(skip)]` 的字段, `Option` 和 `Value` 字段为可选的键
+///
+/// # 用法
+/// ```ignore
+/// #[derive(FromValue, Describe)]
+/// struct Params {
+///     name: String,
+///     size: Option<u32>,
+/// }
+/// let schema = Params::schema();
+/// ```
+#[proc_macro_derive(Describe, attributes(value))]
+pub fn derive_describe(input: TokenStream) -> TokenStream {
+    fromvalue::_derive_describe(input)
+}
+
 /// 为插件结构体生成 `plugin` 导出函数
 ///
+/// 有字段的结构体需实现 `Default`
+///
 /// # 用法
 /// ```ignore
 /// #[plugin_export]
@@ -40,7 +62,13 @@ pub fn plugin_export(args: TokenStream, input: TokenStream) -> TokenStream {
     export::_plugin_export(args, input)
 }
 
-/// 为结构体的方法实现自动分发（仅处理以 `call_` 开头且第一个参数是&self的异步方法）
+/// 为结构体的方法实现自动分发（仅处理以 `call_` 开头且第一个参数是&self的异步方法）, 这些方法名由 `Plugin::methods` 返回
+///
+/// 方法上的 `#[cache(ttl = 秒)]` 标记该方法的结果可以缓存, 见 `Plugin::cacheable`;
+/// 定义了 `fn attach(&self, host: ::plugin::Host)` 时用于实现 `Plugin::attach`
+///
+/// 参数通过 serde 从 `params` 转换(多个参数时 `params` 为数组), 失败时返回 `ValueParseError`,
+/// 路径为 `/params` 或 `/params/序号`
 ///
 /// # 用法
 /// ```ignore
@@ -52,6 +80,10 @@ pub fn plugin_export(args: TokenStream, input: TokenStream) -> TokenStream {
 ///     async fn call_delete(&self, id: String) -> Result<(), Box<dyn std::error::Error>> {
 ///         Ok(())
 ///     }
+///     #[cache(ttl = 60)]
+///     async fn call_hash(&self, text: String) -> String {
+///         text
+///     }
 /// }
 /// ```
 /// 展开后额外生成：
@@ -0,0 +1,156 @@
+//! 批量调用插件
+//!
+//! 一次调用多个插件(或同一插件的多个方法), 各项并发执行, 结果按输入顺序返回
+use futures::future::{join_all, try_join_all};
+use plugin::Value;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    err::PluginManagerError,
+    manager::{PluginId, PluginManager},
+};
+
+/// 批量调用中一项的结果, 失败时为错误信息
+pub type BatchResult = std::result::Result<Value, String>;
+
+///
+/// 批量调用的失败处理方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum BatchMode {
+    /// 任一项失败则整体失败, 未完成的调用被取消
+    AllOrNothing,
+    /// 各项独立返回结果或错误
+    #[default]
+    ContinueOnError,
+}
+
+impl PluginManager {
+    ///
+    /// 并发执行多个调用, `input` 格式同 [`PluginManager::call`]
+    ///
+    /// [`BatchMode::AllOrNothing`] 时返回最先完成的失败项(不一定是序号最小的)的序号和错误
+    pub async fn call_batch(
+        &self,
+        calls: Vec<(PluginId, Value)>,
+        mode: BatchMode,
+    ) -> Result<Vec<BatchResult>, PluginManagerError> {
+        match mode {
+            BatchMode::ContinueOnError => {
+                let calls = calls.into_iter().map(|(id, input)| async move {
+                    self.call(&id, input).await.map_err(|e| e.to_string())
+                });
+                Ok(join_all(calls).await)
+            }
+            BatchMode::AllOrNothing => {
+                let calls = calls
+                    .into_iter()
+                    .enumerate()
+                    .map(|(index, (id, input))| async move {
+                        self.call(&id, input)
+                            .await
+                            .map_err(|e| PluginManagerError::Batch {
+                                index,
+                                error: e.to_string(),
+                            })
+                    });
+                let results = try_join_all(calls).await?;
+                Ok(results.into_iter().map(Ok).collect())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use plugin::{json, plugin_dispatch};
+    use std::{
+        sync::{
+            Arc,
+            atomic::{AtomicUsize, Ordering},
+        },
+        time::Duration,
+    };
+
+    /// 等待 `ms` 毫秒后返回或失败
+    #[derive(Default)]
+    struct Stub {
+        done: Arc<AtomicUsize>,
+    }
+
+    #[plugin_dispatch]
+    impl Stub {
+        async fn call_ok(&self, ms: u64) -> u64 {
+            tokio::time::sleep(Duration::from_millis(ms)).await;
+            self.done.fetch_add(1, Ordering::SeqCst);
+            ms
+        }
+
+        async fn call_fail(&self, ms: u64) -> Result<u64, String> {
+            tokio::time::sleep(Duration::from_millis(ms)).await;
+            Err(format!("fail after {ms}"))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_batch() -> Result<(), PluginManagerError> {
+        let pm = PluginManager::default();
+        let input = json!({"method": "a", "params": null});
+        let calls = vec![(PluginId(1), input.clone()), (PluginId(2), input)];
+
+        let results = pm
+            .call_batch(calls.clone(), BatchMode::ContinueOnError)
+            .await?;
+        assert_eq!(results.len(), 2);
+        assert!(results.iter().all(|r| r.is_err()));
+
+        let result = pm.call_batch(calls, BatchMode::AllOrNothing).await;
+        assert!(matches!(result, Err(PluginManagerError::Batch { .. })));
+        assert!(
+            pm.call_batch(Vec::new(), BatchMode::AllOrNothing)
+                .await?
+                .is_empty()
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_batch_order() -> Result<(), PluginManagerError> {
+        let pm = PluginManager::default();
+        let stub = Stub::default();
+        let done = stub.done.clone();
+        let id = pm.insert_stub("stub", Box::new(stub));
+        let call = |method: &str, ms: u64| (id, plugin::dispatch_input(method, json!(ms)));
+
+        // 先完成的项不影响结果顺序
+        let calls = vec![
+            call("call_ok", 40),
+            call("call_fail", 0),
+            call("call_ok", 0),
+        ];
+        let results = pm.call_batch(calls, BatchMode::ContinueOnError).await?;
+        assert_eq!(results[0], Ok(json!(40)));
+        assert!(
+            results[1]
+                .as_ref()
+                .is_err_and(|e| e.contains("fail after 0"))
+        );
+        assert_eq!(results[2], Ok(json!(0)));
+
+        let calls = vec![call("call_ok", 20), call("call_ok", 0)];
+        let results = pm.call_batch(calls, BatchMode::AllOrNothing).await?;
+        assert_eq!(results, [Ok(json!(20)), Ok(json!(0))]);
+
+        // 失败时取消未完成的调用
+        let before = done.load(Ordering::SeqCst);
+        let calls = vec![call("call_ok", 100), call("call_fail", 10)];
+        let result = pm.call_batch(calls, BatchMode::AllOrNothing).await;
+        assert!(matches!(
+            result,
+            Err(PluginManagerError::Batch { index: 1, .. })
+        ));
+        tokio::time::sleep(Duration::from_millis(150)).await;
+        assert_eq!(done.load(Ordering::SeqCst), before);
+        Ok(())
+    }
+}
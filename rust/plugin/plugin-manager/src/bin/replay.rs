@@ -0,0 +1,28 @@
+//! 回放录制的插件调用
+//!
+//! ```sh
+//! replay <插件库路径> <录制文件.jsonl>
+//! ```
+//! 加载插件后将录制文件中属于该插件的调用依次回放, 以 json 输出差异, 有差异时退出码为 1
+use libcommon::prelude::Result;
+use plugin_manager::{manager::PluginManager, record};
+use std::process::ExitCode;
+
+#[tokio::main(flavor = "current_thread")]
+async fn main() -> Result<ExitCode> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let [lib, records] = args.as_slice() else {
+        eprintln!("usage: replay <plugin lib> <records.jsonl>");
+        return Ok(ExitCode::from(2));
+    };
+    let pm = PluginManager::default();
+    let id = pm.load(lib, String::new())?;
+    let records = record::read(records)?;
+    let report = record::replay(&pm, &id, &records).await;
+    println!("{}", serde_json::to_string_pretty(&report)?);
+    Ok(if report.is_ok() {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    })
+}
@@ -0,0 +1,184 @@
+//! 插件安装包
+//!
+//! 安装包为 `tar.gz` 格式, 根目录下必须包含 [`MANIFEST`], 例如:
+//! ```text
+//! manifest.json
+//! lib/linux-x86_64/debug_plugin-v0.1.0.so
+//! lib/windows-x86_64/debug_plugin-v0.1.0.dll
+//! ui/index.html
+//! ui/static/...
+//! ```
+//! 清单的 `targets` 声明各平台的库文件(相对安装包根目录), `files` 中只需声明 `url`:
+//! ```json
+//! {
+//!   "name": "debug_plugin",
+//!   "version": "0.1.0",
+//!   "files": { "release": { "url": "ui/index.html" } },
+//!   "targets": {
+//!     "linux-x86_64": { "lib": "lib/linux-x86_64/debug_plugin-v0.1.0.so", "sha256": "..." }
+//!   }
+//! }
+//! ```
+use flate2::read::GzDecoder;
+use std::{
+    fs,
+    path::{Component, Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::{
+    err::PluginManagerError,
+    manager::PluginInfo,
+    manifest::{File, MANIFEST, Manifest, target},
+};
+
+/// 解压中的安装包所在的文件夹(位于安装目录下)
+const STAGING: &str = ".staging";
+
+/// 已解压并校验过结构的安装包
+pub(crate) struct Staged {
+    pub dir: PathBuf,
+    pub name: String,
+}
+
+impl Staged {
+    ///
+    /// 将安装包解压到安装目录下的临时文件夹, 校验内容, 并改写清单为当前平台可直接扫描加载的形式
+    ///
+    /// 失败时会删除临时文件夹
+    pub fn unpack(bundle: &Path, root: &Path) -> Result<Self, PluginManagerError> {
+        if !bundle.is_file() {
+            return Err(PluginManagerError::FileNotExists(bundle.to_path_buf()));
+        }
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_nanos())
+            .unwrap_or_default();
+        let dir = root
+            .join(STAGING)
+            .join(format!("{}-{nanos}", std::process::id()));
+        fs::create_dir_all(&dir)?;
+
+        let result = Self::prepare(bundle, &dir);
+        if result.is_err() {
+            remove_dir(&dir);
+        }
+        let name = result?;
+        Ok(Self { dir, name })
+    }
+
+    fn prepare(bundle: &Path, dir: &Path) -> Result<String, PluginManagerError> {
+        // tar 会拒绝解压到目标文件夹之外的条目
+        tar::Archive::new(GzDecoder::new(fs::File::open(bundle)?)).unpack(dir)?;
+
+        let path = dir.join(MANIFEST);
+        if !path.is_file() {
+            return Err(invalid(format!("no {MANIFEST} in bundle")));
+        }
+        let mut manifest = Manifest::read(&path)?;
+        let name = manifest
+            .name
+            .clone()
+            .filter(|n| !n.is_empty() && n.chars().all(|c| c.is_ascii_alphanumeric() || c == '_'))
+            .ok_or(invalid("bundle name must be [a-zA-Z0-9_]+".to_string()))?;
+        let version = manifest
+            .version
+            .clone()
+            .ok_or(invalid("no version in bundle".to_string()))?;
+
+        let file = manifest
+            .target()
+            .cloned()
+            .ok_or(invalid(format!("no library for target {}", target())))?;
+        let lib = file
+            .lib
+            .as_ref()
+            .ok_or(invalid(format!("no lib for target {}", target())))?;
+        let inside = Path::new(lib)
+            .components()
+            .all(|c| matches!(c, Component::Normal(_) | Component::CurDir));
+        if !inside || !dir.join(lib).is_file() {
+            return Err(invalid(format!("lib not in bundle: {lib}")));
+        }
+        // 插件 id 由库文件名中的名称得到, 与清单不一致时会覆盖其他插件, 且安装后无法找到
+        let info = PluginInfo::try_from((dir.join(lib).as_path(), String::new()))
+            .map_err(|_| invalid(format!("invalid lib file name: {lib}")))?;
+        if info.name != name || info.version != version {
+            return Err(invalid(format!(
+                "lib {lib} does not match bundle {name}@{version}"
+            )));
+        }
+
+        let url = manifest.files().and_then(|f| f.url.clone());
+        manifest.set_files(File { url, ..file });
+        manifest.targets = None;
+        manifest.write(&path)?;
+        Ok(name)
+    }
+}
+
+pub(crate) fn remove_dir(dir: &Path) {
+    if dir.exists()
+        && let Err(e) = fs::remove_dir_all(dir)
+    {
+        libcommon::prelude::warn!("failed to remove {dir:?}: {e}");
+    }
+}
+
+fn invalid(reason: String) -> PluginManagerError {
+    PluginManagerError::Bundle(reason)
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use flate2::{Compression, write::GzEncoder};
+
+    pub(crate) fn bundle(name: &str, manifest: &str, files: &[&str]) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("{name}.tar.gz"));
+        let mut tar = tar::Builder::new(GzEncoder::new(
+            fs::File::create(&path).unwrap(),
+            Compression::default(),
+        ));
+        let mut append = |file: &str, data: &[u8]| {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(data.len() as u64);
+            header.set_mode(0o644);
+            header.set_cksum();
+            tar.append_data(&mut header, file, data).unwrap();
+        };
+        append(MANIFEST, manifest.as_bytes());
+        for file in files {
+            append(file, b"lib");
+        }
+        tar.into_inner().unwrap().finish().unwrap();
+        path
+    }
+
+    #[test]
+    fn test_unpack() -> Result<(), PluginManagerError> {
+        let root = std::env::temp_dir().join("bundle_unpack");
+        let lib = format!("lib/{}/demo-v0.1.0.so", target());
+        let manifest = format!(
+            r#"{{"name":"demo","version":"0.1.0","files":{{"dev":{{"url":"ui/index.html"}}}},"targets":{{"{}":{{"lib":"{lib}","sha256":"00"}}}}}}"#,
+            target()
+        );
+
+        let staged = Staged::unpack(&bundle("demo", &manifest, &[&lib]), &root)?;
+        assert_eq!(staged.name, "demo");
+        let installed = Manifest::read(staged.dir.join(MANIFEST))?;
+        assert!(installed.targets.is_none());
+        assert!(installed.lib()?.ends_with(&lib));
+        assert_eq!(installed.integrity().sha256.as_deref(), Some("00"));
+        remove_dir(&staged.dir);
+
+        let missing = Staged::unpack(&bundle("missing", &manifest, &[]), &root);
+        assert!(matches!(missing, Err(PluginManagerError::Bundle(_))));
+        let other = format!("lib/{}/other-v0.1.0.so", target());
+        let renamed = manifest.replace(&lib, &other);
+        let renamed = Staged::unpack(&bundle("renamed", &renamed, &[&other]), &root);
+        assert!(matches!(renamed, Err(PluginManagerError::Bundle(_))));
+        assert_eq!(fs::read_dir(root.join(STAGING))?.count(), 0);
+        Ok(())
+    }
+}
@@ -0,0 +1,266 @@
+//! 插件方法的结果缓存
+//!
+//! 方法的缓存时间来自插件(`#[cache(ttl = 秒)]`)或清单的 `cache`, 清单优先。
+//! 缓存按插件、方法和规范化后的参数区分, 按最近最少使用淘汰
+use dashmap::DashMap;
+use lru::LruCache;
+use plugin::{Value, async_trait};
+use std::{
+    collections::HashMap,
+    num::NonZeroUsize,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    intercept::{Call, CallResult, Interceptor, Next},
+    manager::PluginId,
+};
+
+/// 默认最多缓存的结果数
+const MAX_ENTRIES: usize = 1024;
+/// 默认缓存结果(json)的总大小上限
+const MAX_BYTES: usize = 16 * 1024 * 1024;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct Key {
+    id: PluginId,
+    method: String,
+    params: String,
+}
+
+struct Entry {
+    value: Value,
+    size: usize,
+    expires: Instant,
+}
+
+struct Entries {
+    lru: LruCache<Key, Entry>,
+    bytes: usize,
+}
+
+///
+/// 插件调用的结果缓存, 在其他拦截器之后、并发限制([`crate::limit::Limits`])之前运行, 缓存命中的调用不必排队
+pub struct CallCache {
+    ttls: DashMap<PluginId, HashMap<String, Duration>>,
+    entries: Mutex<Entries>,
+    max_bytes: usize,
+}
+
+impl Default for CallCache {
+    fn default() -> Self {
+        Self::new(MAX_ENTRIES, MAX_BYTES)
+    }
+}
+
+impl CallCache {
+    ///
+    /// `max_entries`: 最多缓存的结果数; `max_bytes`: 缓存结果的总大小上限
+    pub fn new(max_entries: usize, max_bytes: usize) -> Self {
+        let cap = NonZeroUsize::new(max_entries).unwrap_or(NonZeroUsize::MIN);
+        Self {
+            ttls: DashMap::new(),
+            entries: Mutex::new(Entries {
+                lru: LruCache::new(cap),
+                bytes: 0,
+            }),
+            max_bytes,
+        }
+    }
+
+    ///
+    /// 设置插件方法的缓存时间, `ttl` 为 0 时不缓存该方法
+    pub fn set_ttl(&self, id: PluginId, method: &str, ttl: Duration) {
+        let mut ttls = self.ttls.entry(id).or_default();
+        if ttl.is_zero() {
+            ttls.remove(method);
+        } else {
+            ttls.insert(method.to_string(), ttl);
+        }
+    }
+
+    pub fn ttl(&self, id: &PluginId, method: &str) -> Option<Duration> {
+        self.ttls.get(id).and_then(|t| t.get(method).copied())
+    }
+
+    ///
+    /// 清除插件的缓存结果, 返回清除的数量
+    pub fn invalidate(&self, id: &PluginId) -> usize {
+        let mut entries = self.lock();
+        let keys: Vec<_> = entries
+            .lru
+            .iter()
+            .filter(|(k, _)| k.id == *id)
+            .map(|(k, _)| k.clone())
+            .collect();
+        for key in &keys {
+            entries.remove(key);
+        }
+        keys.len()
+    }
+
+    ///
+    /// 清除所有缓存结果, 返回清除的数量
+    pub fn clear(&self) -> usize {
+        let mut entries = self.lock();
+        let len = entries.lru.len();
+        entries.lru.clear();
+        entries.bytes = 0;
+        len
+    }
+
+    pub fn len(&self) -> usize {
+        self.lock().lru.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    ///
+    /// 插件卸载时清除其缓存结果和缓存时间
+    pub(crate) fn remove(&self, id: &PluginId) {
+        self.ttls.remove(id);
+        self.invalidate(id);
+    }
+
+    fn get(&self, key: &Key) -> Option<Value> {
+        let mut entries = self.lock();
+        match entries.lru.get(key) {
+            Some(entry) if entry.expires > Instant::now() => Some(entry.value.clone()),
+            Some(_) => {
+                entries.remove(key);
+                None
+            }
+            None => None,
+        }
+    }
+
+    fn insert(&self, key: Key, value: Value, ttl: Duration) {
+        let size = key.params.len() + serde_json::to_vec(&value).map_or(0, |v| v.len());
+        if size > self.max_bytes {
+            return;
+        }
+        let mut entries = self.lock();
+        entries.remove(&key);
+        while entries.bytes + size > self.max_bytes {
+            match entries.lru.pop_lru() {
+                Some((_, old)) => entries.bytes -= old.size,
+                None => break,
+            }
+        }
+        let entry = Entry {
+            value,
+            size,
+            expires: Instant::now() + ttl,
+        };
+        entries.bytes += size;
+        if let Some((_, old)) = entries.lru.push(key, entry) {
+            entries.bytes -= old.size;
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Entries> {
+        self.entries.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl Entries {
+    fn remove(&mut self, key: &Key) {
+        if let Some(old) = self.lru.pop(key) {
+            self.bytes -= old.size;
+        }
+    }
+}
+
+#[async_trait]
+impl Interceptor for CallCache {
+    async fn intercept(&self, call: Call, next: Next<'_>) -> CallResult {
+        let Some(ttl) = self.ttl(&call.id, call.method()) else {
+            return next.run(call).await;
+        };
+        let key = Key {
+            id: call.id,
+            method: call.method().to_string(),
+            params: canonical(call.params().unwrap_or(&Value::Null)),
+        };
+        if let Some(value) = self.get(&key) {
+            return Ok(value);
+        }
+        let value = next.run(call).await?;
+        self.insert(key, value.clone(), ttl);
+        Ok(value)
+    }
+}
+
+///
+/// 转为 json 后的文本, json 对象的键有序, 使键顺序不同的参数得到相同的缓存键
+fn canonical(value: &Value) -> String {
+    serde_json::Value::from(value.clone()).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use plugin::{Plugin, json};
+    use std::sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    };
+
+    #[derive(Default)]
+    struct Counter(AtomicUsize);
+
+    #[async_trait]
+    impl Plugin for Counter {
+        async fn call(&self, _input: Value) -> CallResult {
+            Ok(json!(self.0.fetch_add(1, Ordering::Relaxed)))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_cache() {
+        let (a, b) = (PluginId(1), PluginId(2));
+        let cache = Arc::new(CallCache::new(2, MAX_BYTES));
+        cache.set_ttl(a, "m", Duration::from_secs(60));
+        let chain: Vec<Arc<dyn Interceptor>> = vec![cache.clone()];
+        let plugin = Counter::default();
+        let call = |id, method: &str, params| {
+            let call = Call {
+                id,
+                input: json!({ "method": method, "params": params }),
+            };
+            Next::new(&chain, &plugin).run(call)
+        };
+
+        assert_eq!(
+            call(a, "m", json!({"x": 1, "y": 2})).await.unwrap(),
+            json!(0)
+        );
+        assert_eq!(
+            call(a, "m", json!({"y": 2, "x": 1})).await.unwrap(),
+            json!(0)
+        );
+        assert_eq!(call(a, "other", json!(null)).await.unwrap(), json!(1));
+        assert_eq!(
+            call(b, "m", json!({"x": 1, "y": 2})).await.unwrap(),
+            json!(2)
+        );
+        assert_eq!(cache.len(), 1);
+
+        // 超过数量上限时淘汰最久未使用的结果
+        call(a, "m", json!(1)).await.unwrap();
+        call(a, "m", json!(2)).await.unwrap();
+        assert_eq!(cache.len(), 2);
+        assert_eq!(
+            call(a, "m", json!({"x": 1, "y": 2})).await.unwrap(),
+            json!(5)
+        );
+
+        assert_eq!(cache.invalidate(&a), 2);
+        cache.set_ttl(a, "m", Duration::ZERO);
+        assert_eq!(call(a, "m", json!(2)).await.unwrap(), json!(6));
+        assert!(cache.is_empty());
+    }
+}
@@ -2,7 +2,7 @@ use std::path::PathBuf;
 
 use thiserror::Error;
 
-use crate::manager::PluginId;
+use crate::{manager::PluginId, verify::VerifyError};
 
 #[derive(Debug, Error)]
 pub enum PluginManagerError {
@@ -16,4 +16,44 @@ pub enum PluginManagerError {
     Library(#[from] libloading::Error),
     #[error("Plugin not found: {0}")]
     PluginNotFound(PluginId),
+    #[error("Verify fail: {0}")]
+    Verify(#[from] VerifyError),
+    #[error("Io error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Json error: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("Invalid manifest: {0}")]
+    Manifest(String),
+    #[error("Invalid bundle: {0}")]
+    Bundle(String),
+    #[error("No install dir configured")]
+    NoInstallDir,
+    #[error("Plugin already installed: {0}")]
+    AlreadyInstalled(String),
+    #[error("Plugin not installed from bundle: {0}")]
+    NotInstalled(PluginId),
+    #[error("Registry error: {0}")]
+    Registry(String),
+    #[error("Payload too large: {size} > {limit} bytes")]
+    PayloadTooLarge { size: usize, limit: usize },
+    #[error("Method not allowed: {0}")]
+    MethodDenied(String),
+    #[error("Plugin busy: {0} {1}")]
+    Busy(PluginId, String),
+    #[error("Plugin disabled: {0}")]
+    Disabled(PluginId),
+    #[error("Invalid query: {0}")]
+    Query(String),
+    #[error("No plugin matches query")]
+    NoMatch,
+    #[error("Ambiguous query, candidates: {}", .0.join(", "))]
+    Ambiguous(Vec<String>),
+    #[error("Batch call {index} failed: {error}")]
+    Batch { index: usize, error: String },
+    #[error("Invalid job {0}: {1}")]
+    Schedule(String, String),
+    #[error("Job not found: {0} {1}")]
+    JobNotFound(PluginId, String),
+    #[error("Job is running: {0} {1}")]
+    JobRunning(PluginId, String),
 }
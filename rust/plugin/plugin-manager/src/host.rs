@@ -0,0 +1,63 @@
+use bus::{Bus, Source};
+use libloading::Library;
+use plugin::{EventHandler, HostContext, Value};
+use std::sync::Arc;
+
+///
+/// 提供给插件的宿主上下文, 以插件名作为消息来源
+pub(crate) struct PluginHost {
+    bus: Arc<Bus>,
+    source: Source,
+    lib: Arc<Library>,
+}
+
+impl PluginHost {
+    pub(crate) fn new(bus: Arc<Bus>, name: &str, lib: Arc<Library>) -> Self {
+        Self {
+            bus,
+            source: Source::Plugin(name.to_string()),
+            lib,
+        }
+    }
+}
+
+///
+/// 插件注册的回调, 持有插件库的引用
+///
+/// 总线在发布时先取出回调再调用, 插件卸载后回调可能仍在执行或被持有,
+/// 库在最后一个回调释放后才真正卸载; 字段按声明顺序释放, `handler` 必须在 `_lib` 之前释放
+struct LibHandler {
+    handler: EventHandler,
+    _lib: Arc<Library>,
+}
+
+impl LibHandler {
+    fn call(&self, topic: &str, payload: &Value) {
+        (self.handler)(topic, payload)
+    }
+}
+
+impl HostContext for PluginHost {
+    fn publish(&self, topic: &str, payload: Value, retain: bool) -> Result<(), String> {
+        self.bus
+            .publish(self.source.clone(), topic, payload.into(), retain)
+            .map(|_| ())
+            .map_err(|e| e.to_string())
+    }
+
+    fn subscribe(&self, pattern: &str, handler: EventHandler) -> Result<u64, String> {
+        let handler = LibHandler {
+            handler,
+            _lib: self.lib.clone(),
+        };
+        self.bus
+            .subscribe(self.source.clone(), pattern, move |_, message| {
+                handler.call(&message.topic, &Value::from(message.payload.clone()))
+            })
+            .map_err(|e| e.to_string())
+    }
+
+    fn unsubscribe(&self, id: u64) {
+        self.bus.unsubscribe(id);
+    }
+}
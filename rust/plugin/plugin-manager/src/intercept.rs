@@ -0,0 +1,286 @@
+//! 插件调用的拦截器
+//!
+//! 拦截器按注册顺序组成调用链, 先执行全局拦截器, 再执行插件的拦截器, 最后调用插件。
+//! 拦截器可以修改输入、修改输出, 或不调用 [`Next::run`] 直接返回结果
+use dashmap::DashMap;
+use libcommon::prelude::{debug, warn};
+use plugin::{Plugin, Value, async_trait};
+use std::{
+    collections::HashSet,
+    sync::{Arc, RwLock},
+    time::{Duration, Instant},
+};
+
+use crate::{err::PluginManagerError, manager::PluginId};
+
+/// 与 [`Plugin::call`] 一致的错误类型
+pub type CallError = Box<dyn std::error::Error + Send + Sync>;
+pub type CallResult = std::result::Result<Value, CallError>;
+
+#[async_trait]
+pub trait Interceptor: Send + Sync {
+    async fn intercept(&self, call: Call, next: Next<'_>) -> CallResult;
+}
+
+/// 一次插件调用, `input` 为 `{ "method": .., "params": .. }`
+#[derive(Debug, Clone)]
+pub struct Call {
+    pub id: PluginId,
+    pub input: Value,
+}
+
+impl Call {
+    pub fn method(&self) -> &str {
+        self.input
+            .as_map()
+            .and_then(|map| map.get("method"))
+            .and_then(Value::as_str)
+            .unwrap_or_default()
+    }
+
+    pub fn params(&self) -> Option<&Value> {
+        self.input.as_map().and_then(|map| map.get("params"))
+    }
+
+    pub fn params_mut(&mut self) -> Option<&mut Value> {
+        self.input
+            .as_map_mut()
+            .and_then(|map| map.get_mut("params"))
+    }
+}
+
+/// 调用链中剩余的拦截器和插件
+pub struct Next<'a> {
+    chain: &'a [Arc<dyn Interceptor>],
+    plugin: &'a dyn Plugin,
+}
+
+impl<'a> Next<'a> {
+    pub(crate) fn new(chain: &'a [Arc<dyn Interceptor>], plugin: &'a dyn Plugin) -> Self {
+        Self { chain, plugin }
+    }
+
+    ///
+    /// 执行下一个拦截器, 没有拦截器时调用插件
+    pub async fn run(self, call: Call) -> CallResult {
+        match self.chain.split_first() {
+            Some((first, chain)) => {
+                let next = Next {
+                    chain,
+                    plugin: self.plugin,
+                };
+                first.intercept(call, next).await
+            }
+            None => self.plugin.call(call.input).await,
+        }
+    }
+}
+
+///
+/// 已注册的拦截器, 可在运行时添加
+#[derive(Default)]
+pub struct Interceptors {
+    global: RwLock<Vec<Arc<dyn Interceptor>>>,
+    plugins: DashMap<PluginId, Vec<Arc<dyn Interceptor>>>,
+}
+
+impl Interceptors {
+    ///
+    /// 添加作用于所有插件的拦截器
+    pub fn add(&self, interceptor: impl Interceptor + 'static) {
+        self.global
+            .write()
+            .unwrap_or_else(|e| e.into_inner())
+            .push(Arc::new(interceptor));
+    }
+
+    ///
+    /// 添加只作用于插件 `id` 的拦截器, 插件重新加载后仍然有效
+    pub fn add_for(&self, id: PluginId, interceptor: impl Interceptor + 'static) {
+        self.plugins
+            .entry(id)
+            .or_default()
+            .push(Arc::new(interceptor));
+    }
+
+    ///
+    /// 移除插件 `id` 的所有拦截器
+    pub fn clear_for(&self, id: &PluginId) {
+        self.plugins.remove(id);
+    }
+
+    pub(crate) fn chain(&self, id: &PluginId) -> Vec<Arc<dyn Interceptor>> {
+        let mut chain = self
+            .global
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone();
+        if let Some(plugin) = self.plugins.get(id) {
+            chain.extend(plugin.iter().cloned());
+        }
+        chain
+    }
+}
+
+///
+/// 记录每次调用的耗时, 超过 `slow` 时以 warn 输出
+#[derive(Debug, Default)]
+pub struct TimingLog {
+    slow: Option<Duration>,
+}
+
+impl TimingLog {
+    pub fn slow(threshold: Duration) -> Self {
+        Self {
+            slow: Some(threshold),
+        }
+    }
+}
+
+#[async_trait]
+impl Interceptor for TimingLog {
+    async fn intercept(&self, call: Call, next: Next<'_>) -> CallResult {
+        let (id, method) = (call.id, call.method().to_string());
+        let start = Instant::now();
+        let result = next.run(call).await;
+        let elapsed = start.elapsed();
+        match self.slow {
+            Some(slow) if elapsed > slow => warn!("slow call {id}.{method}: {elapsed:?}"),
+            _ => debug!("call {id}.{method}: {elapsed:?}"),
+        }
+        result
+    }
+}
+
+///
+/// 限制输入和输出序列化为 json 后的大小
+#[derive(Debug)]
+pub struct PayloadLimit {
+    input: usize,
+    output: Option<usize>,
+}
+
+impl PayloadLimit {
+    pub fn new(input: usize) -> Self {
+        Self {
+            input,
+            output: None,
+        }
+    }
+
+    pub fn with_output(mut self, output: usize) -> Self {
+        self.output = Some(output);
+        self
+    }
+}
+
+fn check_size(value: &Value, limit: usize) -> std::result::Result<(), PluginManagerError> {
+    let size = serde_json::to_vec(value)?.len();
+    if size > limit {
+        return Err(PluginManagerError::PayloadTooLarge { size, limit });
+    }
+    Ok(())
+}
+
+#[async_trait]
+impl Interceptor for PayloadLimit {
+    async fn intercept(&self, call: Call, next: Next<'_>) -> CallResult {
+        check_size(&call.input, self.input)?;
+        let output = next.run(call).await?;
+        if let Some(limit) = self.output {
+            check_size(&output, limit)?;
+        }
+        Ok(output)
+    }
+}
+
+///
+/// 按方法名放行或拒绝调用
+#[derive(Debug)]
+pub enum MethodFilter {
+    /// 只允许列出的方法
+    Allow(HashSet<String>),
+    /// 拒绝列出的方法
+    Deny(HashSet<String>),
+}
+
+impl MethodFilter {
+    pub fn allow<S: Into<String>>(methods: impl IntoIterator<Item = S>) -> Self {
+        Self::Allow(methods.into_iter().map(Into::into).collect())
+    }
+
+    pub fn deny<S: Into<String>>(methods: impl IntoIterator<Item = S>) -> Self {
+        Self::Deny(methods.into_iter().map(Into::into).collect())
+    }
+
+    pub fn permits(&self, method: &str) -> bool {
+        match self {
+            Self::Allow(methods) => methods.contains(method),
+            Self::Deny(methods) => !methods.contains(method),
+        }
+    }
+}
+
+#[async_trait]
+impl Interceptor for MethodFilter {
+    async fn intercept(&self, call: Call, next: Next<'_>) -> CallResult {
+        if !self.permits(call.method()) {
+            return Err(PluginManagerError::MethodDenied(call.method().to_string()).into());
+        }
+        next.run(call).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use plugin::json;
+
+    struct Echo;
+
+    #[async_trait]
+    impl Plugin for Echo {
+        async fn call(&self, input: Value) -> CallResult {
+            Ok(input)
+        }
+    }
+
+    /// 将参数加 1
+    struct Increment;
+
+    #[async_trait]
+    impl Interceptor for Increment {
+        async fn intercept(&self, mut call: Call, next: Next<'_>) -> CallResult {
+            if let Some(params) = call.params_mut() {
+                *params = json!(params.as_i64().unwrap_or_default() + 1);
+            }
+            next.run(call).await
+        }
+    }
+
+    #[tokio::test]
+    async fn test_chain() {
+        let id = PluginId(1);
+        let interceptors = Interceptors::default();
+        interceptors.add(MethodFilter::deny(["secret"]));
+        interceptors.add(TimingLog::default());
+        interceptors.add_for(id, Increment);
+        interceptors.add_for(id, Increment);
+        interceptors.add_for(PluginId(2), PayloadLimit::new(0));
+
+        let call = |method: &str| Call {
+            id,
+            input: json!({ "method": method, "params": 1 }),
+        };
+        let chain = interceptors.chain(&id);
+        let output = Next::new(&chain, &Echo).run(call("a")).await.unwrap();
+        assert_eq!(output, json!({ "method": "a", "params": 3 }));
+        let denied = Next::new(&chain, &Echo).run(call("secret")).await;
+        assert!(denied.is_err());
+
+        let chain = interceptors.chain(&PluginId(2));
+        assert!(Next::new(&chain, &Echo).run(call("a")).await.is_err());
+        interceptors.clear_for(&PluginId(2));
+        assert_eq!(interceptors.chain(&PluginId(2)).len(), 2);
+    }
+}
@@ -1,2 +1,16 @@
+pub mod batch;
+pub mod bundle;
+pub mod cache;
 pub mod err;
+mod host;
+pub mod intercept;
+pub mod limit;
 pub mod manager;
+pub mod manifest;
+pub mod metrics;
+pub mod query;
+pub mod record;
+pub mod registry;
+pub mod schedule;
+pub mod state;
+pub mod verify;
@@ -0,0 +1,270 @@
+//! 插件调用的并发限制
+//!
+//! 可按插件和按方法限制同时执行的调用数, 超出的调用排队等待, 队列已满时返回
+//! [`PluginManagerError::Busy`]。未设置限制时不限制并发
+use dashmap::DashMap;
+use plugin::async_trait;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    },
+};
+use tokio::sync::{Semaphore, SemaphorePermit};
+
+use crate::{
+    err::PluginManagerError,
+    intercept::{Call, CallResult, Interceptor, Next},
+    manager::PluginId,
+};
+
+/// 未指定时的等待队列长度
+const QUEUE: usize = 64;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Limit {
+    /// 同时执行的调用数, 至少为 1
+    pub concurrency: usize,
+    /// 等待执行的调用数上限
+    #[serde(default = "default_queue")]
+    pub queue: usize,
+}
+
+fn default_queue() -> usize {
+    QUEUE
+}
+
+impl Limit {
+    pub fn new(concurrency: usize, queue: usize) -> Self {
+        Self {
+            concurrency: concurrency.max(1),
+            queue,
+        }
+    }
+
+    ///
+    /// 逐个执行调用
+    pub fn serial(queue: usize) -> Self {
+        Self::new(1, queue)
+    }
+}
+
+/// 清单中的并发限制
+///
+/// ```json
+/// "limit": { "serial": true, "methods": { "call_a": { "concurrency": 2, "queue": 8 } } }
+/// ```
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LimitConfig {
+    /// 逐个执行插件的调用, 队列长度取 `plugin.queue`
+    #[serde(default)]
+    pub serial: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub plugin: Option<Limit>,
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub methods: HashMap<String, Limit>,
+}
+
+impl LimitConfig {
+    ///
+    /// 插件整体的限制, `serial` 时并发数为 1
+    pub fn plugin(&self) -> Option<Limit> {
+        match (self.serial, self.plugin) {
+            (true, plugin) => Some(Limit::serial(plugin.map_or(QUEUE, |l| l.queue))),
+            (false, plugin) => plugin,
+        }
+    }
+}
+
+struct Gate {
+    limit: Limit,
+    /// 运行时设置的限制, 重新应用清单时保留
+    runtime: bool,
+    semaphore: Semaphore,
+    waiting: AtomicUsize,
+}
+
+impl Gate {
+    fn new(limit: Limit, runtime: bool) -> Self {
+        Self {
+            limit,
+            runtime,
+            semaphore: Semaphore::new(limit.concurrency.max(1)),
+            waiting: AtomicUsize::new(0),
+        }
+    }
+
+    /// 队列已满时返回 `None`
+    async fn acquire(&self) -> Option<SemaphorePermit<'_>> {
+        if let Ok(permit) = self.semaphore.try_acquire() {
+            return Some(permit);
+        }
+        if self.waiting.fetch_add(1, Ordering::AcqRel) >= self.limit.queue {
+            self.waiting.fetch_sub(1, Ordering::AcqRel);
+            return None;
+        }
+        // 等待被取消时也要离开队列
+        let _waiting = Waiting(&self.waiting);
+        self.semaphore.acquire().await.ok()
+    }
+}
+
+struct Waiting<'a>(&'a AtomicUsize);
+
+impl Drop for Waiting<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::AcqRel);
+    }
+}
+
+///
+/// 已设置的并发限制, 作为调用链中最后一个拦截器运行
+#[derive(Default)]
+pub struct Limits {
+    plugins: DashMap<PluginId, Arc<Gate>>,
+    methods: DashMap<(PluginId, String), Arc<Gate>>,
+}
+
+impl Limits {
+    ///
+    /// 限制插件所有方法的并发, 已在执行和排队的调用不受影响
+    ///
+    /// 优先于清单中的配置, 插件重新加载或更新后仍然生效, 直到 [`Limits::remove`]
+    pub fn set(&self, id: PluginId, limit: Limit) {
+        self.plugins.insert(id, Arc::new(Gate::new(limit, true)));
+    }
+
+    ///
+    /// 限制插件某个方法的并发, 与插件的限制同时生效; 与 [`Limits::set`] 一样优先于清单中的配置
+    pub fn set_method(&self, id: PluginId, method: &str, limit: Limit) {
+        self.methods
+            .insert((id, method.to_string()), Arc::new(Gate::new(limit, true)));
+    }
+
+    ///
+    /// 使用清单中的配置替换插件来自清单的限制, 运行时设置的限制保留
+    pub fn configure(&self, id: PluginId, config: &LimitConfig) {
+        self.plugins.remove_if(&id, |_, gate| !gate.runtime);
+        self.methods
+            .retain(|(key, _), gate| *key != id || gate.runtime);
+        if let Some(limit) = config.plugin() {
+            self.plugins
+                .entry(id)
+                .or_insert_with(|| Arc::new(Gate::new(limit, false)));
+        }
+        for (method, limit) in &config.methods {
+            self.methods
+                .entry((id, method.clone()))
+                .or_insert_with(|| Arc::new(Gate::new(*limit, false)));
+        }
+    }
+
+    pub fn get(&self, id: &PluginId) -> Option<Limit> {
+        self.plugins.get(id).map(|g| g.limit)
+    }
+
+    pub fn get_method(&self, id: &PluginId, method: &str) -> Option<Limit> {
+        self.methods
+            .get(&(*id, method.to_string()))
+            .map(|g| g.limit)
+    }
+
+    ///
+    /// 移除插件的所有限制
+    pub fn remove(&self, id: &PluginId) {
+        self.plugins.remove(id);
+        self.methods.retain(|(key, _), _| key != id);
+    }
+}
+
+#[async_trait]
+impl Interceptor for Limits {
+    async fn intercept(&self, call: Call, next: Next<'_>) -> CallResult {
+        let plugin = self.plugins.get(&call.id).map(|g| g.clone());
+        let method = self
+            .methods
+            .get(&(call.id, call.method().to_string()))
+            .map(|g| g.clone());
+        if plugin.is_none() && method.is_none() {
+            return next.run(call).await;
+        }
+        let busy = || PluginManagerError::Busy(call.id, call.method().to_string());
+        // 先取方法的许可, 避免等待方法时占用插件的并发数
+        let _method = match &method {
+            Some(gate) => Some(gate.acquire().await.ok_or_else(busy)?),
+            None => None,
+        };
+        let _plugin = match &plugin {
+            Some(gate) => Some(gate.acquire().await.ok_or_else(busy)?),
+            None => None,
+        };
+        next.run(call).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use plugin::{Plugin, Value, json};
+    use std::time::Duration;
+
+    /// 记录同时执行的最大调用数
+    #[derive(Default)]
+    struct Slow {
+        running: AtomicUsize,
+        max: AtomicUsize,
+    }
+
+    #[async_trait]
+    impl Plugin for Slow {
+        async fn call(&self, _input: Value) -> CallResult {
+            let running = self.running.fetch_add(1, Ordering::SeqCst) + 1;
+            self.max.fetch_max(running, Ordering::SeqCst);
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            self.running.fetch_sub(1, Ordering::SeqCst);
+            Ok(Value::Null)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_limits() {
+        let id = PluginId(1);
+        let limits = Arc::new(Limits::default());
+        limits.configure(
+            id,
+            &serde_json::from_value(
+                serde_json::json!({ "serial": true, "plugin": { "concurrency": 4, "queue": 2 } }),
+            )
+            .unwrap(),
+        );
+        assert_eq!(limits.get(&id), Some(Limit::serial(2)));
+
+        let chain: Vec<Arc<dyn Interceptor>> = vec![limits.clone()];
+        let plugin = Slow::default();
+        let call = || {
+            let call = Call {
+                id,
+                input: json!({ "method": "a", "params": null }),
+            };
+            Next::new(&chain, &plugin).run(call)
+        };
+        // 1 个执行, 2 个排队, 1 个因队列已满失败
+        let results = tokio::join!(call(), call(), call(), call());
+        let results = [results.0, results.1, results.2, results.3];
+        assert_eq!(results.iter().filter(|r| r.is_ok()).count(), 3);
+        assert_eq!(plugin.max.load(Ordering::SeqCst), 1);
+
+        // 运行时设置的限制在重新应用清单后保留
+        limits.set_method(id, "a", Limit::new(2, 0));
+        limits.configure(id, &LimitConfig::default());
+        assert_eq!(limits.get(&id), None);
+        assert_eq!(limits.get_method(&id, "a"), Some(Limit::new(2, 0)));
+
+        limits.remove(&id);
+        let results = tokio::join!(call(), call(), call(), call());
+        assert!(results.0.is_ok() && results.3.is_ok());
+        assert_eq!(plugin.max.load(Ordering::SeqCst), 4);
+    }
+}
@@ -1,21 +1,77 @@
+use bus::{Bus, Source};
 use dashmap::DashMap;
 use libcommon::{
     hash, newerr,
-    prelude::{Result, debug, info},
+    prelude::{Result, debug, info, warn},
 };
 use libloading::{Library, Symbol};
 use plugin::{Plugin, Value};
-use std::{fs, path::Path, sync::Arc};
+use std::{
+    fs,
+    num::ParseIntError,
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
 
-use crate::err::PluginManagerError;
+use crate::{
+    bundle::{Staged, remove_dir},
+    cache::CallCache,
+    err::PluginManagerError,
+    host::PluginHost,
+    intercept::{Call, Interceptor, Interceptors, Next},
+    limit::Limits,
+    manifest::{MANIFEST, Manifest},
+    metrics::{Metrics, OTHER_METHOD},
+    query::{self, Candidate, Health, Query},
+    record::{Record, Recorder},
+    schedule::Scheduler,
+    state::PluginStates,
+    verify::{Integrity, Verified, Verifier},
+};
 
 type PluginRefFn<'a> = Symbol<'a, unsafe fn() -> Box<dyn Plugin>>;
 const PLUGIN: &str = "plugin";
+/// 插件加载后发布的主题, 内容为插件的 id、name 和 version
+pub const LOADED: &str = "app/plugin/loaded";
+/// 插件卸载后发布的主题
+pub const UNLOADED: &str = "app/plugin/unloaded";
 const PATTERN: &str = r"^(?P<name>[a-zA-Z0-9_]+)-v(?P<version>\d+\.\d+\.\d+(?:-[0-9A-Za-z-]+(?:\.[0-9A-Za-z-]+)*)?(?:\+[0-9A-Za-z-]+(?:\.[0-9A-Za-z-]+)*)?)\.(?P<ext>[a-zA-Z0-9]+)$";
 
 #[derive(Default)]
 pub struct PluginManager {
-    plugins: DashMap<PluginId, (PluginInfo, LoadPlugin)>,
+    plugins: DashMap<PluginId, (PluginInfo, Arc<LoadPlugin>)>,
+    verifier: Verifier,
+    install_dir: Option<PathBuf>,
+    metrics: Metrics,
+    recorder: Recorder,
+    interceptors: Interceptors,
+    cache: Arc<CallCache>,
+    limits: Arc<Limits>,
+    bus: Option<Arc<Bus>>,
+    /// 已加载和被禁用的插件
+    entries: DashMap<PluginId, Entry>,
+    states: PluginStates,
+    scheduler: Scheduler,
+    /// 为 true 时注册插件不加载, 首次调用时加载
+    lazy: bool,
+    /// 插件空闲超过该时间后卸载, 见 [`PluginManager::spawn_idle_unload`]
+    idle: Option<Duration>,
+    /// 首次调用时加载插件的锁, 避免并发调用重复加载
+    loading: Mutex<()>,
+}
+
+///
+/// 插件的状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PluginState {
+    /// 已注册, 首次调用时加载
+    Registered,
+    Loaded,
+    /// 空闲超时被卸载, 再次调用时加载
+    IdleUnloaded,
+    Disabled,
 }
 
 #[derive(Debug, Clone)]
@@ -26,76 +82,628 @@ pub struct PluginInfo {
     pub lib: String,
 }
 
+/// 插件的来源, 被禁用的插件启用时从此加载
+#[derive(Debug, Clone)]
+struct Entry {
+    info: PluginInfo,
+    integrity: Integrity,
+    manifest: Option<Manifest>,
+    /// 最近一次加载时插件公开的方法
+    methods: Vec<String>,
+    /// 因空闲被卸载
+    idle: bool,
+}
+
+/// 字段按声明顺序释放, `plugin` 必须在 `lib` 之前释放
 struct LoadPlugin {
-    _lib: Arc<Library>,
     plugin: Box<dyn Plugin>,
+    lib: Arc<Library>,
+    /// 实际加载的文件; 总线上的回调可能让库在此之后才卸载, 已映射的库不受删除文件影响
+    _file: Verified,
+    /// 最近一次调用开始或结束的时间
+    used: Mutex<Instant>,
+}
+
+impl LoadPlugin {
+    fn touch(&self) {
+        *self.used.lock().unwrap_or_else(|e| e.into_inner()) = Instant::now();
+    }
+
+    fn idle(&self) -> Duration {
+        self.used
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .elapsed()
+    }
 }
 
 impl PluginManager {
+    ///
+    /// 设置加载插件库前使用的校验器
+    pub fn with_verifier(mut self, verifier: Verifier) -> Self {
+        self.verifier = verifier;
+        self
+    }
+
+    ///
+    /// 设置安装包的安装目录, 每个插件安装在以其名称命名的子文件夹中
+    pub fn with_install_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.install_dir = Some(dir.into());
+        self
+    }
+
+    ///
+    /// 设置消息总线, 之后加载的插件可通过 [`plugin::Plugin::attach`] 得到的上下文发布和订阅消息
+    pub fn with_bus(mut self, bus: Arc<Bus>) -> Self {
+        self.bus = Some(bus);
+        self
+    }
+
+    ///
+    /// 设置插件的启用状态, 被禁用的插件不会被加载
+    pub fn with_states(mut self, states: PluginStates) -> Self {
+        self.states = states;
+        self
+    }
+
+    ///
+    /// 注册插件时只记录清单信息, 首次调用时才加载插件库
+    pub fn with_lazy_load(mut self) -> Self {
+        self.lazy = true;
+        self
+    }
+
+    ///
+    /// 卸载空闲超过 `idle` 的插件, 需调用 [`PluginManager::spawn_idle_unload`]
+    pub fn with_idle_unload(mut self, idle: Duration) -> Self {
+        self.idle = Some(idle);
+        self
+    }
+
+    ///
+    /// 添加作用于所有插件的拦截器, 见 [`crate::intercept`]
+    pub fn with_interceptor(self, interceptor: impl Interceptor + 'static) -> Self {
+        self.interceptors.add(interceptor);
+        self
+    }
+
+    ///
+    /// 不带完整性信息加载插件库, 校验策略为 [`crate::verify::VerifyPolicy::Enforce`] 时总是失败,
+    /// 此时应使用 [`PluginManager::load_with`]
     pub fn load(&self, path: impl AsRef<Path>, url: String) -> Result<PluginId> {
-        let path = path.as_ref();
+        self.load_with(path, url, &Integrity::default())
+    }
+
+    ///
+    /// 按 [`Verifier`] 的策略校验插件库后再加载
+    ///
+    /// 插件被禁用时只记录而不加载
+    pub fn load_with(
+        &self,
+        path: impl AsRef<Path>,
+        url: String,
+        integrity: &Integrity,
+    ) -> Result<PluginId> {
+        let entry = Entry {
+            info: PluginInfo::try_from((path.as_ref(), url))?,
+            integrity: integrity.clone(),
+            manifest: None,
+            methods: Vec::new(),
+            idle: false,
+        };
+        self.add(entry, false)
+    }
+
+    ///
+    /// 按清单加载插件并应用清单中的配置, 插件被禁用时只记录而不加载
+    pub fn register(&self, manifest: &Manifest) -> Result<PluginId> {
+        self.register_with(manifest, false)
+    }
+
+    ///
+    /// `eager` 时不论是否延迟加载都立即校验并加载, 用于安装和更新, 使有问题的安装包在安装时就失败
+    fn register_with(&self, manifest: &Manifest, eager: bool) -> Result<PluginId> {
+        let lib = manifest.lib()?;
+        let entry = Entry {
+            info: PluginInfo::try_from((Path::new(&lib), manifest.url()?))?,
+            integrity: manifest.integrity(),
+            manifest: Some(manifest.clone()),
+            methods: Vec::new(),
+            idle: false,
+        };
+        self.add(entry, eager)
+    }
+
+    ///
+    /// 启用并加载插件(延迟加载时只注册), 保存启用状态
+    pub fn enable(&self, id: &PluginId) -> Result<PluginInfo> {
+        let mut entry = self.entry(id)?;
+        self.states.set_enabled(&entry.info.name, true)?;
+        if self.lazy {
+            self.scheduler.set_paused(id, false);
+        } else if !self.plugins.contains_key(id) {
+            self.open(&mut entry)?;
+            self.entries.insert(*id, entry.clone());
+        }
+        Ok(entry.info)
+    }
+
+    ///
+    /// 禁用并卸载插件, 保存启用状态, 插件仍会出现在 [`PluginManager::list`] 中
+    pub fn disable(&self, id: &PluginId) -> Result<PluginInfo> {
+        let entry = self.entry(id)?;
+        self.states.set_enabled(&entry.info.name, false)?;
+        self.scheduler.set_paused(id, true);
+        self.close(id);
+        Ok(entry.info)
+    }
+
+    pub fn is_enabled(&self, id: &PluginId) -> bool {
+        self.entries
+            .get(id)
+            .is_some_and(|e| self.states.is_enabled(&e.info.name))
+    }
+
+    pub fn is_loaded(&self, id: &PluginId) -> bool {
+        self.plugins.contains_key(id)
+    }
+
+    pub fn state(&self, id: &PluginId) -> Option<PluginState> {
+        let entry = self.entries.get(id)?;
+        Some(if !self.states.is_enabled(&entry.info.name) {
+            PluginState::Disabled
+        } else if self.plugins.contains_key(id) {
+            PluginState::Loaded
+        } else if entry.idle {
+            PluginState::IdleUnloaded
+        } else {
+            PluginState::Registered
+        })
+    }
+
+    fn entry(&self, id: &PluginId) -> Result<Entry, PluginManagerError> {
+        self.entries
+            .get(id)
+            .map(|e| e.clone())
+            .ok_or(PluginManagerError::PluginNotFound(*id))
+    }
+
+    ///
+    /// 记录插件的来源, 启用时加载; `eager` 见 [`PluginManager::register_with`]
+    fn add(&self, mut entry: Entry, eager: bool) -> Result<PluginId> {
+        let id = PluginId::from(&entry.info);
+        let enabled = self.states.is_enabled(&entry.info.name);
+        let load = enabled && (!self.lazy || eager);
+        if !load && (enabled || eager) {
+            // 不立即加载时也先校验, 使扫描或安装时就能报告校验失败; 加载时会再校验实际加载的副本
+            self.verifier
+                .verify(Path::new(&entry.info.lib), &entry.integrity)
+                .map_err(PluginManagerError::from)?;
+        }
+        if load {
+            self.open(&mut entry)?;
+        } else if enabled {
+            debug!("registered plugin {id}: {:?}", entry.info);
+            // 重新注册(如更新)时卸载旧版本, 下次调用时加载新版本
+            self.close(&id);
+        } else {
+            info!("plugin {id} is disabled, skip loading: {:?}", entry.info);
+            self.close(&id);
+        }
+        let jobs = entry.manifest.as_ref().and_then(|m| m.jobs.as_deref());
+        self.scheduler.set(id, jobs.unwrap_or_default(), !enabled);
+        self.entries.insert(id, entry);
+        Ok(id)
+    }
+
+    fn open(&self, entry: &mut Entry) -> Result<PluginId> {
+        let info = entry.info.clone();
+        let path = Path::new(&info.lib);
         debug!("loading plugin from path: {path:?}");
-        let plugin = LoadPlugin::try_from(path)?;
-        let info = PluginInfo::try_from((path, url))?;
+        let file = self
+            .verifier
+            .prepare(path, &entry.integrity)
+            .map_err(PluginManagerError::from)?;
+        let plugin = LoadPlugin::try_from(file)?;
         let id = PluginId::from(&info);
         info!("loaded plugin: {id}: {info:?}");
-        self.plugins.insert(id, (info, plugin));
+        // 重新加载时旧的缓存结果和订阅不再有效
+        self.cache.remove(&id);
+        self.detach(&info.name);
+        if let Some(bus) = &self.bus {
+            plugin.plugin.attach(Arc::new(PluginHost::new(
+                bus.clone(),
+                &info.name,
+                plugin.lib.clone(),
+            )));
+        }
+        for (method, ttl) in plugin.plugin.cacheable() {
+            self.cache.set_ttl(id, method, ttl);
+        }
+        if let Some(manifest) = &entry.manifest {
+            self.configure(id, manifest);
+        }
+        entry.idle = false;
+        entry.methods = plugin
+            .plugin
+            .methods()
+            .into_iter()
+            .map(String::from)
+            .collect();
+        self.notify(LOADED, id, &info);
+        self.plugins.insert(id, (info, Arc::new(plugin)));
+        self.scheduler.set_paused(&id, false);
         Ok(id)
     }
 
-    pub fn find(&self, find: (Option<String>, Option<String>)) -> Option<PluginId> {
-        if find.0.is_none() && find.1.is_none() {
-            return None;
+    ///
+    /// 从安装包(见[`crate::bundle`])安装插件到安装目录并加载
+    ///
+    /// 延迟加载时也会立即校验并加载, 任一步失败都会删除已解压的文件
+    pub fn install(&self, bundle: impl AsRef<Path>) -> Result<PluginId> {
+        let root = self.install_root()?;
+        let staged = Staged::unpack(bundle.as_ref(), root)?;
+        let dest = root.join(&staged.name);
+        if dest.exists()
+            || self
+                .entries
+                .contains_key(&PluginId::from(staged.name.as_str()))
+        {
+            remove_dir(&staged.dir);
+            return Err(PluginManagerError::AlreadyInstalled(staged.name).into());
         }
-        let finder = self
-            .plugins
-            .iter()
-            .filter_map(|v| {
-                let info = &v.0;
-                if find.0.as_ref() == Some(&info.url) || find.1.as_ref() == Some(&info.lib) {
-                    Some(v.key().clone())
-                } else {
-                    None
-                }
-            })
-            .collect::<Vec<_>>();
-        if finder.len() > 1 {
-            None
+        if let Err(e) = fs::rename(&staged.dir, &dest) {
+            remove_dir(&staged.dir);
+            return Err(e.into());
+        }
+        self.load_installed(&dest)
+            .inspect_err(|_| remove_dir(&dest))
+    }
+
+    ///
+    /// 卸载并删除通过安装包安装的插件
+    pub fn uninstall(&self, id: &PluginId) -> Result<PluginInfo> {
+        let dir = self.installed_dir(id)?;
+        let info = self
+            .unload(id)
+            .ok_or(PluginManagerError::PluginNotFound(*id))?;
+        // 运行时设置的并发限制在重新加载和更新后保留, 卸载安装包时才清除
+        self.limits.remove(id);
+        fs::remove_dir_all(&dir)?;
+        info!("uninstalled plugin: {id}: {dir:?}");
+        Ok(info)
+    }
+
+    ///
+    /// 使用新的安装包替换已安装的插件
+    ///
+    /// 与 [`PluginManager::install`] 相同, 新版本立即校验并加载, 失败时恢复并重新加载旧版本
+    pub fn update(&self, id: &PluginId, bundle: impl AsRef<Path>) -> Result<PluginId> {
+        let dir = self.installed_dir(id)?;
+        let root = self.install_root()?;
+        let staged = Staged::unpack(bundle.as_ref(), root)?;
+        if dir.file_name() != Some(staged.name.as_ref()) {
+            remove_dir(&staged.dir);
+            let reason = format!("bundle is {}, not {dir:?}", staged.name);
+            return Err(PluginManagerError::Bundle(reason).into());
+        }
+        let backup = staged.dir.with_extension("backup");
+
+        self.unload(id);
+        if let Err(e) = fs::rename(&dir, &backup) {
+            remove_dir(&staged.dir);
+            self.load_installed(&dir)?;
+            return Err(e.into());
+        }
+        let result = fs::rename(&staged.dir, &dir)
+            .map_err(Into::into)
+            .and_then(|_| self.load_installed(&dir));
+        match result {
+            Ok(id) => {
+                remove_dir(&backup);
+                Ok(id)
+            }
+            Err(e) => {
+                warn!("update plugin {id} failed, rollback: {e}");
+                remove_dir(&staged.dir);
+                remove_dir(&dir);
+                fs::rename(&backup, &dir)?;
+                self.load_installed(&dir)?;
+                Err(e)
+            }
+        }
+    }
+
+    ///
+    /// 发布插件加载/卸载的消息
+    fn notify(&self, topic: &str, id: PluginId, info: &PluginInfo) {
+        if let Some(bus) = &self.bus {
+            let payload = serde_json::json!({
+                "id": id.to_string(),
+                "name": info.name,
+                "version": info.version,
+            });
+            if let Err(e) = bus.publish(Source::Host, topic, payload, false) {
+                warn!("failed to publish {topic}: {e}");
+            }
+        }
+    }
+
+    ///
+    /// 取消插件的所有订阅, 订阅的回调在插件库中, 必须在释放插件库前取消
+    fn detach(&self, name: &str) {
+        if let Some(bus) = &self.bus {
+            bus.unsubscribe_all(&Source::Plugin(name.to_string()));
+        }
+    }
+
+    fn load_installed(&self, dir: &Path) -> Result<PluginId> {
+        let manifest = Manifest::read(dir.join(MANIFEST))?;
+        self.register_with(&manifest, true)
+    }
+
+    fn install_root(&self) -> Result<&Path, PluginManagerError> {
+        self.install_dir
+            .as_deref()
+            .ok_or(PluginManagerError::NoInstallDir)
+    }
+
+    ///
+    /// 是否为通过安装包安装(位于安装目录下)的插件, 只有这类插件可以更新和卸载
+    pub fn is_installed(&self, id: &PluginId) -> bool {
+        self.installed_dir(id).is_ok()
+    }
+
+    fn installed_dir(&self, id: &PluginId) -> Result<PathBuf, PluginManagerError> {
+        let info = self
+            .get(id)
+            .ok_or(PluginManagerError::PluginNotFound(*id))?;
+        let dir = self.install_root()?.join(&info.name);
+        if Path::new(&info.lib).starts_with(&dir) {
+            Ok(dir)
         } else {
-            finder.get(0).cloned()
+            Err(PluginManagerError::NotInstalled(*id))
         }
     }
 
+    ///
+    /// 查询满足条件的所有插件(包括被禁用的), 按名称排序
+    pub fn query(&self, query: &Query) -> Vec<(PluginId, PluginInfo)> {
+        let mut found: Vec<_> = self
+            .entries
+            .iter()
+            .filter(|v| {
+                let id = *v.key();
+                let (calls, errors) = self.metrics.totals(&id);
+                let candidate = Candidate {
+                    id,
+                    info: &v.info,
+                    capabilities: v
+                        .manifest
+                        .as_ref()
+                        .and_then(|m| m.capabilities.as_deref())
+                        .unwrap_or_default(),
+                    methods: &v.methods,
+                    health: Health::of(self.plugins.contains_key(&id), calls, errors),
+                };
+                query.matches(&candidate)
+            })
+            .map(|v| (*v.key(), v.info.clone()))
+            .collect();
+        found.sort_by(|a, b| a.1.name.cmp(&b.1.name));
+        found
+    }
+
+    ///
+    /// 查询唯一的插件, 匹配多个时返回 [`PluginManagerError::Ambiguous`] 及所有候选
+    pub fn query_one(&self, query: &Query) -> Result<PluginId, PluginManagerError> {
+        query::one(self.query(query))
+    }
+
+    ///
+    /// 卸载插件, 被禁用的插件也会被移除
     pub fn unload(&self, id: &PluginId) -> Option<PluginInfo> {
-        self.plugins.remove(id).map(|(_, (info, _))| info)
+        let loaded = self.close(id);
+        self.scheduler.remove(id);
+        self.metrics.remove(id);
+        self.entries.remove(id).map(|(_, e)| e.info).or(loaded)
     }
 
-    pub fn list(&self) -> Vec<(PluginId, PluginInfo)> {
+    fn close(&self, id: &PluginId) -> Option<PluginInfo> {
+        self.cache.remove(id);
+        let (_, (info, _)) = self.plugins.remove(id)?;
+        self.detach(&info.name);
+        self.notify(UNLOADED, *id, &info);
+        Some(info)
+    }
+
+    ///
+    /// 首次调用(或空闲卸载后再次调用)时加载插件
+    fn load_lazy(&self, id: &PluginId) -> Result<Arc<LoadPlugin>> {
+        let mut entry = self.entry(id)?;
+        if !self.states.is_enabled(&entry.info.name) {
+            return Err(PluginManagerError::Disabled(*id).into());
+        }
+        let _loading = self.loading.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(plugin) = self.plugins.get(id) {
+            return Ok(plugin.1.clone());
+        }
+        self.open(&mut entry)?;
+        self.entries.insert(*id, entry);
         self.plugins
+            .get(id)
+            .map(|plugin| plugin.1.clone())
+            .ok_or_else(|| PluginManagerError::PluginNotFound(*id).into())
+    }
+
+    ///
+    /// 卸载空闲超过 `idle` 且没有进行中调用的插件, 返回卸载的插件
+    pub fn unload_idle(&self, idle: Duration) -> Vec<PluginId> {
+        let ids: Vec<_> = self.plugins.iter().map(|v| *v.key()).collect();
+        let mut unloaded = Vec::new();
+        for id in ids {
+            // 进行中的调用持有插件的引用
+            let removed = self.plugins.remove_if(&id, |_, (_, plugin)| {
+                Arc::strong_count(plugin) == 1 && plugin.idle() >= idle
+            });
+            let Some((_, (info, plugin))) = removed else {
+                continue;
+            };
+            self.cache.remove(&id);
+            self.detach(&info.name);
+            drop(plugin);
+            if let Some(mut entry) = self.entries.get_mut(&id) {
+                entry.idle = true;
+            }
+            info!("unloaded idle plugin: {id}: {info:?}");
+            self.notify(UNLOADED, id, &info);
+            unloaded.push(id);
+        }
+        unloaded
+    }
+
+    ///
+    /// 在当前 tokio 运行时中定期卸载空闲的插件, 未设置 [`PluginManager::with_idle_unload`] 时不执行
+    pub fn spawn_idle_unload(self: &Arc<Self>) -> Option<tokio::task::JoinHandle<()>> {
+        let idle = self.idle?;
+        let period = (idle / 4).clamp(Duration::from_secs(1), Duration::from_secs(60));
+        let pm = Arc::downgrade(self);
+        Some(tokio::spawn(async move {
+            let mut interval = tokio::time::interval(period);
+            loop {
+                interval.tick().await;
+                let Some(pm) = pm.upgrade() else {
+                    break;
+                };
+                pm.unload_idle(idle);
+            }
+        }))
+    }
+
+    ///
+    /// 已加载和被禁用的插件
+    pub fn list(&self) -> Vec<(PluginId, PluginInfo)> {
+        self.entries
             .iter()
-            .map(|v| (*v.key(), v.0.clone()))
+            .map(|v| (*v.key(), v.info.clone()))
             .collect()
     }
 
     pub fn get(&self, id: &PluginId) -> Option<PluginInfo> {
-        self.plugins.get(id).map(|v| v.0.clone())
+        self.entries.get(id).map(|v| v.info.clone())
+    }
+
+    ///
+    /// 插件调用指标
+    pub fn metrics(&self) -> &Metrics {
+        &self.metrics
+    }
+
+    ///
+    /// 调用拦截器, 可在运行时添加全局或插件的拦截器
+    pub fn interceptors(&self) -> &Interceptors {
+        &self.interceptors
+    }
+
+    ///
+    /// 应用清单中的缓存时间和并发限制, 加载插件后调用
+    pub fn configure(&self, id: PluginId, manifest: &Manifest) {
+        for (method, ttl) in manifest.cache() {
+            self.cache.set_ttl(id, &method, ttl);
+        }
+        self.limits
+            .configure(id, &manifest.limit.clone().unwrap_or_default());
+    }
+
+    ///
+    /// 并发限制, 可在运行时修改
+    pub fn limits(&self) -> &Limits {
+        &self.limits
+    }
+
+    ///
+    /// 消息总线
+    pub fn bus(&self) -> Option<&Arc<Bus>> {
+        self.bus.as_ref()
+    }
+
+    ///
+    /// 定时任务
+    pub fn scheduler(&self) -> &Scheduler {
+        &self.scheduler
+    }
+
+    ///
+    /// 方法结果缓存
+    pub fn cache(&self) -> &CallCache {
+        &self.cache
+    }
+
+    ///
+    /// 调用录制器, 可在运行时开始/停止录制
+    pub fn recorder(&self) -> &Recorder {
+        &self.recorder
     }
 
     pub async fn call(&self, id: &PluginId, input: Value) -> Result<Value> {
-        match self.plugins.get(id) {
-            Some(value) => value.1.plugin.call(input).await.map_err(|e| newerr!(e)),
-            None => Err(PluginManagerError::PluginNotFound(*id).into()),
+        // 不在持有 DashMap 的锁时 await, 避免阻塞同一分片上的 load/unload
+        let plugin = self.plugins.get(id).map(|value| value.1.clone());
+        let plugin = match plugin {
+            Some(plugin) => plugin,
+            None => self.load_lazy(id)?,
+        };
+        plugin.touch();
+        let method = method_of(&input);
+        let method = if plugin.plugin.methods().contains(&method) {
+            method
+        } else {
+            OTHER_METHOD
+        };
+        let timer = self.metrics.start(*id, method);
+        // 仅在录制时保留输入
+        let recorded = self.recorder.is_recording().then(|| input.clone());
+        let start = Instant::now();
+        let mut chain = self.interceptors.chain(id);
+        // 缓存和并发限制在其他拦截器之后, 缓存命中的调用不占用并发数
+        chain.push(self.cache.clone());
+        chain.push(self.limits.clone());
+        let call = Call { id: *id, input };
+        let result = Next::new(&chain, plugin.plugin.as_ref())
+            .run(call)
+            .await
+            .map_err(|e| e.to_string());
+        plugin.touch();
+        timer.finish(result.is_ok());
+        if let Some(input) = recorded {
+            let record = Record::new(id, &input, &result, start.elapsed());
+            self.recorder.record(&record);
         }
+        result.map_err(|e| newerr!(e))
     }
 }
 
-impl TryFrom<&Path> for LoadPlugin {
+///
+/// 调用参数中的方法名, 格式见 [`plugin::plugin_dispatch`]
+fn method_of(input: &Value) -> &str {
+    input
+        .as_map()
+        .and_then(|map| map.get("method"))
+        .and_then(Value::as_str)
+        .unwrap_or_default()
+}
+
+impl TryFrom<Verified> for LoadPlugin {
     type Error = PluginManagerError;
 
-    fn try_from(value: &Path) -> Result<Self, Self::Error> {
-        let _lib: Arc<Library> = unsafe { Library::new(value) }?.into();
-        let plugin = unsafe { _lib.get::<PluginRefFn>(PLUGIN)?() };
-        Ok(Self { _lib, plugin })
+    fn try_from(value: Verified) -> Result<Self, Self::Error> {
+        let lib: Arc<Library> = unsafe { Library::new(value.path()) }?.into();
+        let plugin = unsafe { lib.get::<PluginRefFn>(PLUGIN)?() };
+        Ok(Self {
+            lib,
+            _file: value,
+            plugin,
+            used: Mutex::new(Instant::now()),
+        })
     }
 }
 
@@ -142,6 +750,16 @@ impl std::fmt::Display for PluginId {
     }
 }
 
+///
+/// 从 [`PluginId`] 的显示形式(hex)解析
+impl FromStr for PluginId {
+    type Err = ParseIntError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        u64::from_str_radix(s, 16).map(Self)
+    }
+}
+
 impl From<&str> for PluginId {
     fn from(value: &str) -> Self {
         Self(hash!(value))
@@ -153,3 +771,116 @@ impl From<&PluginInfo> for PluginId {
         Self::from(value.name.as_str())
     }
 }
+
+#[cfg(test)]
+impl PluginManager {
+    ///
+    /// 加入不来自插件库的插件, 用于测试调用流程
+    pub(crate) fn insert_stub(&self, name: &str, plugin: Box<dyn Plugin>) -> PluginId {
+        let info = PluginInfo {
+            name: name.to_string(),
+            version: String::from("0.1.0"),
+            url: String::new(),
+            lib: String::new(),
+        };
+        let id = PluginId::from(&info);
+        #[cfg(unix)]
+        let lib: Library = libloading::os::unix::Library::this().into();
+        #[cfg(windows)]
+        let lib: Library = libloading::os::windows::Library::this()
+            .expect("current module")
+            .into();
+        let file = Verifier::default()
+            .prepare(Path::new(&info.lib), &Integrity::default())
+            .expect("verify is off");
+        let plugin = LoadPlugin {
+            plugin,
+            lib: Arc::new(lib),
+            _file: file,
+            used: Mutex::new(Instant::now()),
+        };
+        self.plugins.insert(id, (info, Arc::new(plugin)));
+        id
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_lazy_state() -> Result<()> {
+        let dir = std::env::temp_dir().join(format!("plugin_lazy_test_{}", std::process::id()));
+        fs::create_dir_all(&dir)?;
+        let result = lazy_state(&dir).await;
+        fs::remove_dir_all(&dir)?;
+        result
+    }
+
+    async fn lazy_state(dir: &Path) -> Result<()> {
+        let lib = dir.join("lazy_plugin-v0.1.0.so");
+        fs::write(&lib, b"not a library")?;
+
+        let pm = PluginManager::default().with_lazy_load();
+        let id = pm.load(&lib, String::from("http://localhost"))?;
+        assert_eq!(pm.state(&id), Some(PluginState::Registered));
+        // 首次调用时才加载, 加载失败的错误返回给调用者
+        assert!(pm.call(&id, Value::Null).await.is_err());
+        assert_eq!(pm.state(&id), Some(PluginState::Registered));
+
+        pm.disable(&id)?;
+        assert_eq!(pm.state(&id), Some(PluginState::Disabled));
+        assert!(pm.call(&id, Value::Null).await.is_err());
+        pm.enable(&id)?;
+        assert_eq!(pm.state(&id), Some(PluginState::Registered));
+        assert!(pm.unload_idle(Duration::ZERO).is_empty());
+
+        // 延迟加载时注册就校验, 扫描时即可报告校验失败
+        let pm = PluginManager::default()
+            .with_lazy_load()
+            .with_verifier(Verifier::new(crate::verify::VerifyPolicy::Enforce));
+        let bad = Integrity {
+            sha256: Some("00".repeat(32)),
+            signature: None,
+        };
+        let err = pm
+            .load_with(&lib, String::from("http://localhost"), &bad)
+            .unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<PluginManagerError>(),
+            Some(PluginManagerError::Verify(_))
+        ));
+        assert!(pm.list().is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_install_verify() -> Result<()> {
+        let root = std::env::temp_dir().join(format!("plugin_install_test_{}", std::process::id()));
+        let lib = format!("lib/{}/bad_sha-v0.1.0.so", crate::manifest::target());
+        let manifest = format!(
+            r#"{{"name":"bad_sha","version":"0.1.0","targets":{{"{}":{{"lib":"{lib}","sha256":"{}"}}}}}}"#,
+            crate::manifest::target(),
+            "00".repeat(32)
+        );
+        let bundle = crate::bundle::tests::bundle("bad_sha", &manifest, &[&lib]);
+
+        // 延迟加载时安装也会校验, 校验失败时不保留安装的文件
+        let pm = PluginManager::default()
+            .with_lazy_load()
+            .with_verifier(Verifier::new(crate::verify::VerifyPolicy::Enforce))
+            .with_install_dir(&root);
+        let err = pm.install(&bundle).unwrap_err();
+        let installed = root.join("bad_sha").exists();
+        let result = fs::remove_dir_all(&root);
+        fs::remove_file(&bundle)?;
+        assert!(!installed);
+        assert!(matches!(
+            err.downcast_ref::<PluginManagerError>(),
+            Some(PluginManagerError::Verify(_))
+        ));
+        assert!(pm.list().is_empty());
+        result?;
+        Ok(())
+    }
+}
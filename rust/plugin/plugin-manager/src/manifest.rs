@@ -0,0 +1,218 @@
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    env::consts,
+    fs,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use crate::{err::PluginManagerError, limit::LimitConfig, schedule::JobSpec, verify::Integrity};
+
+/// 插件清单文件名(安装包及安装目录中)
+pub const MANIFEST: &str = "manifest.json";
+
+/// 插件清单(json)
+///
+/// 扫描时 `dir` 会被设置为清单文件所在的文件夹, 清单中的相对路径都基于该文件夹
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct Manifest {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dir: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    files: Option<Files>,
+    /// 安装包中按目标平台(`{os}-{arch}`, 如 `linux-x86_64`)区分的库文件
+    ///
+    /// 安装时选出当前平台的一项写入 `files`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub targets: Option<HashMap<String, File>>,
+    /// 按方法设置的结果缓存时间(秒), 优先于插件中的 `#[cache(ttl = ..)]`, 为 0 时不缓存
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cache: Option<HashMap<String, u64>>,
+    /// 并发限制, 未设置时不限制
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit: Option<LimitConfig>,
+    /// 插件声明的能力, 用于 [`crate::query::Query::with_capability`]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub capabilities: Option<Vec<String>>,
+    /// 定时任务, 见 [`crate::schedule`]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub jobs: Option<Vec<JobSpec>>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct Files {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    dev: Option<File>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    release: Option<File>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct File {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub lib: Option<String>,
+    /// lib 文件的 SHA-256(hex)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<String>,
+    /// 发布者对 lib 文件 SHA-256 摘要的 ed25519 签名(hex)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub signature: Option<String>,
+}
+
+impl Manifest {
+    ///
+    /// 读取清单文件, 并将 `dir` 设置为其所在文件夹
+    pub fn read(path: impl AsRef<Path>) -> Result<Self, PluginManagerError> {
+        let path = path.as_ref();
+        let mut manifest: Manifest = serde_json::from_reader(fs::File::open(path)?)?;
+        manifest.update_dir(path);
+        Ok(manifest)
+    }
+
+    ///
+    /// 写入清单文件, 不写入 `dir`
+    pub fn write(&self, path: impl AsRef<Path>) -> Result<(), PluginManagerError> {
+        let manifest = Self {
+            dir: None,
+            ..self.clone()
+        };
+        fs::write(path, serde_json::to_vec_pretty(&manifest)?)?;
+        Ok(())
+    }
+
+    pub fn update_dir(&mut self, dir: &Path) {
+        if let Some(dir) = dir.parent()
+            && let Some(dir) = dir.to_str()
+        {
+            self.dir = Some(dir.to_string());
+        }
+    }
+
+    pub fn files(&self) -> Option<&File> {
+        #[cfg(debug_assertions)]
+        {
+            self.files.as_ref()?.dev.as_ref()
+        }
+        #[cfg(not(debug_assertions))]
+        {
+            self.files.as_ref()?.release.as_ref()
+        }
+    }
+
+    ///
+    /// 同时设置 dev 和 release 使用的文件
+    pub fn set_files(&mut self, file: File) {
+        self.files = Some(Files {
+            dev: Some(file.clone()),
+            release: Some(file),
+        });
+    }
+
+    ///
+    /// 当前平台对应的 `targets` 项
+    pub fn target(&self) -> Option<&File> {
+        self.targets.as_ref()?.get(&target())
+    }
+
+    ///
+    /// 插件页面地址
+    ///
+    /// 未声明时为 `{dir}/index.html`; 声明的是相对路径(非 `scheme://` 形式)时基于 `dir`
+    pub fn url(&self) -> Result<String, PluginManagerError> {
+        if let Some(file) = self.files()
+            && let Some(url) = &file.url
+        {
+            return Ok(if url.contains("://") {
+                url.clone()
+            } else {
+                self.join(url)
+            });
+        }
+        let dir = self
+            .dir
+            .as_ref()
+            .ok_or(invalid("no url and read dir fail"))?;
+        Ok(format!("{dir}/index.html"))
+    }
+
+    ///
+    /// 插件库路径
+    ///
+    /// 未声明时为 `{dir}/{name}-v{version}.{ext}`; 声明的是相对路径时基于 `dir`
+    pub fn lib(&self) -> Result<String, PluginManagerError> {
+        if let Some(file) = self.files()
+            && let Some(lib) = &file.lib
+        {
+            return Ok(self.join(lib));
+        }
+        let dir = self
+            .dir
+            .as_ref()
+            .ok_or(invalid("no lib and read dir fail"))?;
+        let name = self.name.as_ref().ok_or(invalid("no lib and no name"))?;
+        let version = self
+            .version
+            .as_ref()
+            .ok_or(invalid("no lib and no version"))?;
+        Ok(format!("{dir}/{name}-v{version}.{}", ext()))
+    }
+
+    pub fn integrity(&self) -> Integrity {
+        let file = self.files();
+        Integrity {
+            sha256: file.and_then(|f| f.sha256.clone()),
+            signature: file.and_then(|f| f.signature.clone()),
+        }
+    }
+
+    pub fn cache(&self) -> Vec<(String, Duration)> {
+        self.cache
+            .iter()
+            .flatten()
+            .map(|(method, ttl)| (method.clone(), Duration::from_secs(*ttl)))
+            .collect()
+    }
+
+    fn join(&self, path: &str) -> String {
+        let path = Path::new(path);
+        if path.is_relative()
+            && let Some(dir) = &self.dir
+        {
+            PathBuf::from(dir).join(path).to_string_lossy().to_string()
+        } else {
+            path.to_string_lossy().to_string()
+        }
+    }
+}
+
+///
+/// 当前平台, 格式为 `{os}-{arch}`
+pub fn target() -> String {
+    format!("{}-{}", consts::OS, consts::ARCH)
+}
+
+fn invalid(reason: &str) -> PluginManagerError {
+    PluginManagerError::Manifest(reason.to_string())
+}
+
+fn ext() -> &'static str {
+    #[cfg(target_os = "windows")]
+    {
+        "dll"
+    }
+    #[cfg(target_os = "linux")]
+    {
+        "so"
+    }
+    #[cfg(target_os = "macos")]
+    {
+        "dylib"
+    }
+}
@@ -0,0 +1,298 @@
+use dashmap::DashMap;
+use std::{
+    collections::VecDeque,
+    fmt::Write,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::{Duration, Instant},
+};
+
+use crate::manager::PluginId;
+
+/// 默认统计延迟的时间窗口
+const WINDOW: Duration = Duration::from_secs(60);
+/// 每个方法在时间窗口内最多保留的延迟样本数
+const MAX_SAMPLES: usize = 1024;
+/// 导出的延迟分位数
+const QUANTILES: [f64; 3] = [0.5, 0.9, 0.99];
+/// 插件未公开的方法(包括未指定方法的调用)统一记录的方法名, 避免调用方传入的方法名使指标无限增长
+pub const OTHER_METHOD: &str = "other";
+
+///
+/// 插件调用指标, 按插件和方法统计
+pub struct Metrics {
+    methods: DashMap<(PluginId, String), Arc<MethodMetrics>>,
+    window: Duration,
+}
+
+#[derive(Default)]
+struct MethodMetrics {
+    calls: AtomicU64,
+    errors: AtomicU64,
+    in_flight: AtomicU64,
+    completed: AtomicU64,
+    /// 完成的调用的总延迟(纳秒)
+    latency: AtomicU64,
+    samples: Mutex<VecDeque<(Instant, Duration)>>,
+}
+
+///
+/// 一次调用的计时, 调用 [`CallTimer::finish`] 记录结果
+///
+/// 未调用 `finish` 就被释放(如调用被取消)时只减少进行中的调用数
+pub struct CallTimer {
+    metrics: Arc<MethodMetrics>,
+    start: Instant,
+    window: Duration,
+}
+
+/// 某个插件方法的指标快照
+#[derive(Debug, Clone)]
+pub struct MethodSnapshot {
+    pub id: PluginId,
+    pub method: String,
+    pub calls: u64,
+    pub errors: u64,
+    pub in_flight: u64,
+    /// 完成(记录了延迟)的调用数
+    pub completed: u64,
+    /// 完成的调用的总延迟
+    pub latency: Duration,
+    /// 时间窗口内的延迟样本数
+    pub samples: usize,
+    /// 时间窗口内的延迟分位数, 与 [`QUANTILES`] 对应, 无样本时为空
+    pub quantiles: Vec<(f64, Duration)>,
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Self::new(WINDOW)
+    }
+}
+
+impl Metrics {
+    ///
+    /// `window`: 统计延迟分位数的滚动时间窗口
+    pub fn new(window: Duration) -> Self {
+        Self {
+            methods: DashMap::new(),
+            window,
+        }
+    }
+
+    pub fn start(&self, id: PluginId, method: &str) -> CallTimer {
+        let metrics = self
+            .methods
+            .entry((id, method.to_string()))
+            .or_default()
+            .clone();
+        metrics.calls.fetch_add(1, Ordering::Relaxed);
+        metrics.in_flight.fetch_add(1, Ordering::Relaxed);
+        CallTimer {
+            metrics,
+            start: Instant::now(),
+            window: self.window,
+        }
+    }
+
+    ///
+    /// 清除某个插件的指标
+    pub fn remove(&self, id: &PluginId) {
+        self.methods.retain(|(key, _), _| key != id);
+    }
+
+    ///
+    /// 某个插件所有方法的调用数和失败数
+    pub fn totals(&self, id: &PluginId) -> (u64, u64) {
+        self.methods
+            .iter()
+            .filter(|entry| entry.key().0 == *id)
+            .fold((0, 0), |(calls, errors), entry| {
+                let metrics = entry.value();
+                (
+                    calls + metrics.calls.load(Ordering::Relaxed),
+                    errors + metrics.errors.load(Ordering::Relaxed),
+                )
+            })
+    }
+
+    pub fn snapshot(&self) -> Vec<MethodSnapshot> {
+        let now = Instant::now();
+        let mut snapshots: Vec<_> = self
+            .methods
+            .iter()
+            .map(|entry| {
+                let (id, method) = entry.key();
+                let metrics = entry.value();
+                let mut latencies: Vec<Duration> = {
+                    let mut samples = metrics.samples.lock().unwrap_or_else(|e| e.into_inner());
+                    expire(&mut samples, now, self.window);
+                    samples.iter().map(|(_, d)| *d).collect()
+                };
+                latencies.sort();
+                MethodSnapshot {
+                    id: *id,
+                    method: method.clone(),
+                    calls: metrics.calls.load(Ordering::Relaxed),
+                    errors: metrics.errors.load(Ordering::Relaxed),
+                    in_flight: metrics.in_flight.load(Ordering::Relaxed),
+                    completed: metrics.completed.load(Ordering::Relaxed),
+                    latency: Duration::from_nanos(metrics.latency.load(Ordering::Relaxed)),
+                    samples: latencies.len(),
+                    quantiles: quantiles(&latencies),
+                }
+            })
+            .collect();
+        snapshots.sort_by(|a, b| (a.id.0, &a.method).cmp(&(b.id.0, &b.method)));
+        snapshots
+    }
+
+    ///
+    /// 导出为 Prometheus 文本格式
+    pub fn prometheus(&self) -> String {
+        let snapshots = self.snapshot();
+        let mut out = String::new();
+        let labels = |s: &MethodSnapshot| {
+            format!(
+                "plugin=\"{}\",method=\"{}\"",
+                s.id,
+                s.method.replace('\\', "\\\\").replace('"', "\\\"")
+            )
+        };
+
+        let counters = [
+            ("plugin_calls_total", "Total plugin calls.", "counter"),
+            (
+                "plugin_errors_total",
+                "Total failed plugin calls.",
+                "counter",
+            ),
+            ("plugin_in_flight", "Plugin calls in progress.", "gauge"),
+        ];
+        for (i, (name, help, ty)) in counters.iter().enumerate() {
+            let _ = writeln!(out, "# HELP {name} {help}\n# TYPE {name} {ty}");
+            for s in &snapshots {
+                let value = [s.calls, s.errors, s.in_flight][i];
+                let _ = writeln!(out, "{name}{{{}}} {value}", labels(s));
+            }
+        }
+
+        let name = "plugin_call_latency_seconds";
+        let _ = writeln!(
+            out,
+            "# HELP {name} Plugin call latency over the last {}s.\n# TYPE {name} summary",
+            self.window.as_secs()
+        );
+        for s in &snapshots {
+            for (q, d) in &s.quantiles {
+                let _ = writeln!(
+                    out,
+                    "{name}{{{},quantile=\"{q}\"}} {}",
+                    labels(s),
+                    d.as_secs_f64()
+                );
+            }
+            let _ = writeln!(
+                out,
+                "{name}_sum{{{}}} {}",
+                labels(s),
+                s.latency.as_secs_f64()
+            );
+            let _ = writeln!(out, "{name}_count{{{}}} {}", labels(s), s.completed);
+        }
+        out
+    }
+}
+
+impl CallTimer {
+    pub fn finish(self, ok: bool) {
+        let elapsed = self.start.elapsed();
+        if !ok {
+            self.metrics.errors.fetch_add(1, Ordering::Relaxed);
+        }
+        self.metrics.completed.fetch_add(1, Ordering::Relaxed);
+        self.metrics
+            .latency
+            .fetch_add(elapsed.as_nanos() as u64, Ordering::Relaxed);
+        let now = Instant::now();
+        let mut samples = self
+            .metrics
+            .samples
+            .lock()
+            .unwrap_or_else(|e| e.into_inner());
+        expire(&mut samples, now, self.window);
+        if samples.len() >= MAX_SAMPLES {
+            samples.pop_front();
+        }
+        samples.push_back((now, elapsed));
+    }
+}
+
+impl Drop for CallTimer {
+    fn drop(&mut self) {
+        self.metrics.in_flight.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+fn expire(samples: &mut VecDeque<(Instant, Duration)>, now: Instant, window: Duration) {
+    while let Some((at, _)) = samples.front()
+        && now.duration_since(*at) > window
+    {
+        samples.pop_front();
+    }
+}
+
+fn quantiles(sorted: &[Duration]) -> Vec<(f64, Duration)> {
+    if sorted.is_empty() {
+        return Vec::new();
+    }
+    QUANTILES
+        .iter()
+        .map(|q| {
+            let index = ((sorted.len() as f64 * q).ceil() as usize).clamp(1, sorted.len()) - 1;
+            (*q, sorted[index])
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_metrics() {
+        let metrics = Metrics::default();
+        let id = PluginId(1);
+        for i in 0..10 {
+            metrics.start(id, "a").finish(i % 5 != 0);
+        }
+        let pending = metrics.start(id, "b");
+
+        let snapshot = metrics.snapshot();
+        assert_eq!(snapshot.len(), 2);
+        assert_eq!((snapshot[0].calls, snapshot[0].errors), (10, 2));
+        assert_eq!(snapshot[0].in_flight, 0);
+        assert_eq!(snapshot[0].samples, 10);
+        assert_eq!(snapshot[0].quantiles.len(), QUANTILES.len());
+        assert_eq!((snapshot[1].in_flight, snapshot[1].samples), (1, 0));
+
+        drop(pending);
+        let text = metrics.prometheus();
+        assert!(text.contains("plugin_calls_total{plugin=\"1\",method=\"a\"} 10"));
+        assert!(text.contains("plugin_in_flight{plugin=\"1\",method=\"b\"} 0"));
+        assert!(text.contains("plugin_call_latency_seconds_count{plugin=\"1\",method=\"a\"} 10"));
+        assert!(text.contains("plugin_call_latency_seconds_sum{plugin=\"1\",method=\"a\"} "));
+
+        metrics.remove(&id);
+        assert!(metrics.snapshot().is_empty());
+
+        // 样本过期后 `_count` 不减少
+        let expired = Metrics::new(Duration::ZERO);
+        expired.start(id, "a").finish(true);
+        std::thread::sleep(Duration::from_millis(1));
+        let snapshot = expired.snapshot();
+        assert_eq!((snapshot[0].samples, snapshot[0].completed), (0, 1));
+    }
+}
@@ -0,0 +1,210 @@
+//! 按条件查询插件
+//!
+//! ```ignore
+//! let ids = pm.query(&Query::default().with_capability("fs").with_version(">=1.0"));
+//! let id = pm.query_one(&Query::default().with_id_prefix("3fa"))?;
+//! ```
+use semver::{Version, VersionReq};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    err::PluginManagerError,
+    manager::{PluginId, PluginInfo},
+};
+
+/// 失败调用占比超过该值时视为 [`Health::Degraded`]
+const DEGRADED_RATIO: f64 = 0.5;
+/// 调用数少于该值时不判断失败占比
+const DEGRADED_MIN_CALLS: u64 = 10;
+
+///
+/// 插件的健康状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Health {
+    Healthy,
+    /// 已加载, 但调用失败的占比过高
+    Degraded,
+    /// 未加载(被禁用或尚未加载)
+    Unloaded,
+}
+
+impl Health {
+    ///
+    /// 根据是否加载和调用指标中的调用数、失败数判断
+    pub fn of(loaded: bool, calls: u64, errors: u64) -> Self {
+        if !loaded {
+            Self::Unloaded
+        } else if calls >= DEGRADED_MIN_CALLS && errors as f64 / calls as f64 > DEGRADED_RATIO {
+            Self::Degraded
+        } else {
+            Self::Healthy
+        }
+    }
+}
+
+///
+/// 查询条件, 未设置的条件不过滤, 设置的条件需全部满足
+#[derive(Debug, Clone, Default)]
+pub struct Query {
+    name: Option<String>,
+    id_prefix: Option<String>,
+    version: Option<VersionReq>,
+    capability: Option<String>,
+    method: Option<String>,
+    health: Option<Health>,
+    url: Option<String>,
+    lib: Option<String>,
+}
+
+///
+/// 查询时插件的信息
+pub(crate) struct Candidate<'a> {
+    pub id: PluginId,
+    pub info: &'a PluginInfo,
+    /// 清单中声明的能力
+    pub capabilities: &'a [String],
+    /// 插件公开的方法, 未加载过的插件为空
+    pub methods: &'a [String],
+    pub health: Health,
+}
+
+impl Query {
+    pub fn with_name(mut self, name: impl Into<String>) -> Self {
+        self.name = Some(name.into());
+        self
+    }
+
+    ///
+    /// [`PluginId`] 显示形式(hex)的前缀
+    pub fn with_id_prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.id_prefix = Some(prefix.into().to_lowercase());
+        self
+    }
+
+    ///
+    /// 版本要求, 如 `>=1.2, <2`
+    pub fn with_version(mut self, req: &str) -> Result<Self, PluginManagerError> {
+        let req = VersionReq::parse(req).map_err(|e| PluginManagerError::Query(e.to_string()))?;
+        self.version = Some(req);
+        Ok(self)
+    }
+
+    ///
+    /// 清单中 `capabilities` 声明的能力
+    pub fn with_capability(mut self, capability: impl Into<String>) -> Self {
+        self.capability = Some(capability.into());
+        self
+    }
+
+    ///
+    /// 插件公开的方法名, 只能匹配加载过的插件
+    pub fn with_method(mut self, method: impl Into<String>) -> Self {
+        self.method = Some(method.into());
+        self
+    }
+
+    pub fn with_health(mut self, health: Health) -> Self {
+        self.health = Some(health);
+        self
+    }
+
+    pub fn with_url(mut self, url: impl Into<String>) -> Self {
+        self.url = Some(url.into());
+        self
+    }
+
+    pub fn with_lib(mut self, lib: impl Into<String>) -> Self {
+        self.lib = Some(lib.into());
+        self
+    }
+
+    pub(crate) fn matches(&self, c: &Candidate) -> bool {
+        let eq = |want: &Option<String>, value: &str| want.as_deref().is_none_or(|w| w == value);
+        eq(&self.name, &c.info.name)
+            && eq(&self.url, &c.info.url)
+            && eq(&self.lib, &c.info.lib)
+            && self
+                .id_prefix
+                .as_ref()
+                .is_none_or(|p| c.id.to_string().starts_with(p.as_str()))
+            && self
+                .version
+                .as_ref()
+                .is_none_or(|req| Version::parse(&c.info.version).is_ok_and(|v| req.matches(&v)))
+            && self
+                .capability
+                .as_ref()
+                .is_none_or(|want| c.capabilities.contains(want))
+            && self
+                .method
+                .as_ref()
+                .is_none_or(|want| c.methods.contains(want))
+            && self.health.is_none_or(|h| h == c.health)
+    }
+}
+
+///
+/// 查询结果只能有一个, 有多个时返回所有候选
+pub(crate) fn one(mut found: Vec<(PluginId, PluginInfo)>) -> Result<PluginId, PluginManagerError> {
+    match found.len() {
+        0 => Err(PluginManagerError::NoMatch),
+        1 => Ok(found.remove(0).0),
+        _ => Err(PluginManagerError::Ambiguous(
+            found
+                .into_iter()
+                .map(|(id, info)| format!("{}@{} ({id})", info.name, info.version))
+                .collect(),
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_matches() -> Result<(), PluginManagerError> {
+        let info = PluginInfo {
+            name: String::from("debug_plugin"),
+            version: String::from("1.2.0"),
+            url: String::from("http://localhost"),
+            lib: String::from("/p/debug_plugin-v1.2.0.so"),
+        };
+        let id = PluginId::from(&info);
+        let candidate = Candidate {
+            id,
+            info: &info,
+            capabilities: &[String::from("fs")],
+            methods: &[String::from("call_list")],
+            health: Health::Healthy,
+        };
+        assert!(Query::default().matches(&candidate));
+        let prefix = id.to_string()[..3].to_string();
+        let query = Query::default()
+            .with_name("debug_plugin")
+            .with_id_prefix(prefix)
+            .with_version(">=1.0, <2")?
+            .with_capability("fs")
+            .with_method("call_list")
+            .with_health(Health::Healthy);
+        assert!(query.matches(&candidate));
+        assert!(!Query::default().with_version("^2")?.matches(&candidate));
+        assert!(!Query::default().with_capability("net").matches(&candidate));
+        assert!(
+            !Query::default()
+                .with_health(Health::Unloaded)
+                .matches(&candidate)
+        );
+        assert!(Query::default().with_version("one").is_err());
+
+        assert_eq!(Health::of(true, 20, 11), Health::Degraded);
+        assert_eq!(Health::of(true, 5, 5), Health::Healthy);
+        assert_eq!(Health::of(false, 0, 0), Health::Unloaded);
+
+        let candidates = vec![(id, info.clone()), (PluginId(1), info)];
+        assert!(matches!(one(candidates), Err(PluginManagerError::Ambiguous(c)) if c.len() == 2));
+        assert!(matches!(one(Vec::new()), Err(PluginManagerError::NoMatch)));
+        Ok(())
+    }
+}
@@ -0,0 +1,327 @@
+//! 插件调用的录制与回放
+//!
+//! 录制时每次 [`PluginManager::call`] 写入一行 json([`Record`]), 回放时将录制的输入
+//! 依次发给新加载的插件, 并与录制的输出比较
+use libcommon::prelude::warn;
+use plugin::{Map, Operation, Value, render_diff};
+use serde::{Deserialize, Serialize};
+use std::{
+    fs,
+    io::{self, BufRead, BufReader, BufWriter, Write},
+    path::{Path, PathBuf},
+    sync::Mutex,
+    time::Duration,
+};
+
+use crate::manager::{PluginId, PluginManager};
+
+/// 一次插件调用的记录
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Record {
+    /// [`PluginId`] 的显示形式
+    pub id: String,
+    pub method: String,
+    pub params: Value,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub result: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// 调用耗时(微秒)
+    pub duration: u64,
+}
+
+impl Record {
+    pub fn new(
+        id: &PluginId,
+        input: &Value,
+        result: &Result<Value, String>,
+        duration: Duration,
+    ) -> Self {
+        let (result, error) = match result {
+            Ok(value) => (Some(value.clone()), None),
+            Err(e) => (None, Some(e.clone())),
+        };
+        Self {
+            id: id.to_string(),
+            method: input
+                .as_map()
+                .and_then(|map| map.get("method"))
+                .and_then(Value::as_str)
+                .unwrap_or_default()
+                .to_string(),
+            params: input
+                .as_map()
+                .and_then(|map| map.get("params"))
+                .cloned()
+                .unwrap_or_default(),
+            result,
+            error,
+            duration: duration.as_micros() as u64,
+        }
+    }
+
+    ///
+    /// 还原为 [`PluginManager::call`] 的输入
+    pub fn input(&self) -> Value {
+        plugin::dispatch_input(&self.method, self.params.clone())
+    }
+}
+
+///
+/// 录制器, 可在运行时开始/停止
+#[derive(Default)]
+pub struct Recorder {
+    file: Mutex<Option<(PathBuf, BufWriter<fs::File>)>>,
+}
+
+impl Recorder {
+    ///
+    /// 开始录制到文件(追加), 正在录制时先停止之前的录制
+    pub fn start(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let path = path.as_ref();
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let file = fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)?;
+        let prev = self
+            .lock()
+            .replace((path.to_path_buf(), BufWriter::new(file)));
+        if let Some((_, mut prev)) = prev {
+            prev.flush()?;
+        }
+        Ok(())
+    }
+
+    ///
+    /// 停止录制, 返回录制的文件
+    pub fn stop(&self) -> io::Result<Option<PathBuf>> {
+        match self.lock().take() {
+            Some((path, mut file)) => {
+                file.flush()?;
+                Ok(Some(path))
+            }
+            None => Ok(None),
+        }
+    }
+
+    ///
+    /// 正在录制的文件
+    pub fn recording(&self) -> Option<PathBuf> {
+        self.lock().as_ref().map(|(path, _)| path.clone())
+    }
+
+    pub(crate) fn is_recording(&self) -> bool {
+        self.lock().is_some()
+    }
+
+    pub(crate) fn record(&self, record: &Record) {
+        let mut file = self.lock();
+        if let Some((path, file)) = file.as_mut() {
+            let result = serde_json::to_writer(&mut *file, record)
+                .map_err(io::Error::from)
+                .and_then(|_| file.write_all(b"\n"))
+                .and_then(|_| file.flush());
+            if let Err(e) = result {
+                warn!("failed to record plugin call to {path:?}: {e}");
+            }
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Option<(PathBuf, BufWriter<fs::File>)>> {
+        self.file.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+///
+/// 读取录制文件, 忽略空行
+pub fn read(path: impl AsRef<Path>) -> io::Result<Vec<Record>> {
+    let mut records = Vec::new();
+    for line in BufReader::new(fs::File::open(path)?).lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        records.push(serde_json::from_str(&line)?);
+    }
+    Ok(records)
+}
+
+/// 回放结果与录制结果的一处差异
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Difference {
+    /// JSON Pointer 形式的路径, 如 `/result/files/0`
+    pub path: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expected: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub actual: Option<Value>,
+}
+
+/// 一条记录的回放结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Mismatch {
+    /// 在录制文件中的序号
+    pub index: usize,
+    pub method: String,
+    pub params: Value,
+    pub differences: Vec<Difference>,
+    /// 逐行列出的差异, 格式见 [`plugin::render_diff`]
+    pub diff: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct ReplayReport {
+    pub total: usize,
+    pub matched: usize,
+    pub mismatches: Vec<Mismatch>,
+}
+
+impl ReplayReport {
+    pub fn is_ok(&self) -> bool {
+        self.mismatches.is_empty()
+    }
+}
+
+///
+/// 将属于插件 `id` 的记录依次回放, 比较输出
+pub async fn replay(pm: &PluginManager, id: &PluginId, records: &[Record]) -> ReplayReport {
+    let mut report = ReplayReport::default();
+    let key = id.to_string();
+    for (index, record) in records.iter().enumerate() {
+        if record.id != key {
+            continue;
+        }
+        report.total += 1;
+        let expected = outcome(&record.result, &record.error);
+        let actual = match pm.call(id, record.input()).await {
+            Ok(value) => outcome(&Some(value), &None),
+            Err(e) => outcome(&None, &Some(e.to_string())),
+        };
+        let (expected, actual) = (normalize(&expected), normalize(&actual));
+        let differences = differences(&expected, &actual);
+        if differences.is_empty() {
+            report.matched += 1;
+        } else {
+            report.mismatches.push(Mismatch {
+                index,
+                method: record.method.clone(),
+                params: record.params.clone(),
+                differences,
+                diff: render_diff(&expected, &actual),
+            });
+        }
+    }
+    report
+}
+
+fn outcome(result: &Option<Value>, error: &Option<String>) -> Value {
+    match (result, error) {
+        (_, Some(error)) => Value::Map(Map::from([("error".to_string(), error.as_str().into())])),
+        (Some(result), None) => Value::Map(Map::from([("result".to_string(), result.clone())])),
+        (None, None) => Value::Null,
+    }
+}
+
+///
+/// 录制文件中的二进制数据为数字数组, 两边都转为 json 再转回, 使比较不受数据来源影响
+fn normalize(value: &Value) -> Value {
+    Value::from(serde_json::Value::from(value.clone()))
+}
+
+///
+/// 从录制的结果到回放结果的差异, 路径和顺序与 [`Value::diff`] 一致
+fn differences(expected: &Value, actual: &Value) -> Vec<Difference> {
+    expected
+        .diff(actual)
+        .0
+        .into_iter()
+        .filter_map(|op| match op {
+            Operation::Add { path, value } => Some(Difference {
+                path,
+                expected: None,
+                actual: Some(value),
+            }),
+            Operation::Remove { path } => Some(Difference {
+                expected: expected.get(&path).cloned(),
+                path,
+                actual: None,
+            }),
+            Operation::Replace { path, value } => Some(Difference {
+                expected: expected.get(&path).cloned(),
+                path,
+                actual: Some(value),
+            }),
+            // Value::diff 只生成以上三种操作
+            _ => None,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use plugin::json;
+
+    #[test]
+    fn test_diff() {
+        let result = |value| normalize(&outcome(&Some(value), &None));
+        let out = differences(
+            &result(json!({"a": 1, "b": [1, 2], "c/d": true})),
+            &result(json!({"a": 1, "b": [1, 3, 4], "e": null})),
+        );
+        let paths: Vec<_> = out.iter().map(|d| d.path.as_str()).collect();
+        assert_eq!(
+            paths,
+            ["/result/b/1", "/result/b/2", "/result/c~1d", "/result/e"]
+        );
+        assert_eq!(out[1].expected, None);
+        assert_eq!(out[1].actual, Some(json!(4)));
+        assert_eq!(out[2].expected, Some(json!(true)));
+
+        // 录制为数字数组的二进制数据与回放得到的二进制数据相同
+        let bytes = Value::Bytes(vec![1, 2].into());
+        assert!(differences(&normalize(&json!([1, 2])), &normalize(&bytes)).is_empty());
+        let error = normalize(&outcome(&None, &Some(String::from("boom"))));
+        let paths: Vec<_> = differences(&result(json!(1)), &error)
+            .into_iter()
+            .map(|d| d.path)
+            .collect();
+        assert_eq!(paths, ["/result", "/error"]);
+    }
+
+    #[test]
+    fn test_record() -> io::Result<()> {
+        let path = std::env::temp_dir().join("record_test.jsonl");
+        let _ = fs::remove_file(&path);
+        let recorder = Recorder::default();
+        let input = json!({"method": "a", "params": 1});
+        recorder.record(&Record::new(
+            &PluginId(1),
+            &input,
+            &Ok(json!(2)),
+            Duration::ZERO,
+        ));
+        assert!(!path.exists());
+
+        recorder.start(&path)?;
+        recorder.record(&Record::new(
+            &PluginId(1),
+            &input,
+            &Ok(json!(2)),
+            Duration::ZERO,
+        ));
+        let error = Err("boom".to_string());
+        recorder.record(&Record::new(&PluginId(1), &input, &error, Duration::ZERO));
+        assert_eq!(recorder.stop()?, Some(path.clone()));
+
+        let records = read(&path)?;
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].input(), input);
+        assert_eq!(records[0].result, Some(json!(2)));
+        assert_eq!(records[1].error.as_deref(), Some("boom"));
+        Ok(())
+    }
+}
@@ -0,0 +1,317 @@
+//! 本地插件仓库
+//!
+//! 仓库是一个包含 [`INDEX`] 和安装包文件的文件夹, 可通过路径或 `file://` 地址访问:
+//! ```json
+//! {
+//!   "plugins": [
+//!     {
+//!       "name": "debug_plugin",
+//!       "description": "调试插件",
+//!       "versions": [
+//!         { "version": "0.1.0", "bundle": "debug_plugin-v0.1.0.tar.gz", "sha256": "..." }
+//!       ]
+//!     }
+//!   ]
+//! }
+//! ```
+//! `bundle` 为相对仓库文件夹的安装包路径, 格式见 [`crate::bundle`]
+use libcommon::prelude::Result;
+use semver::Version;
+use serde::{Deserialize, Serialize};
+use std::{
+    fs,
+    path::{Component, Path, PathBuf},
+};
+
+use crate::{
+    err::PluginManagerError,
+    manager::{PluginId, PluginManager},
+    verify::sha256_file,
+};
+
+/// 仓库索引文件名
+pub const INDEX: &str = "index.json";
+const FILE_SCHEME: &str = "file://";
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct Index {
+    pub plugins: Vec<IndexPlugin>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IndexPlugin {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    pub versions: Vec<IndexVersion>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IndexVersion {
+    pub version: String,
+    pub bundle: String,
+    /// 安装包文件的 SHA-256(hex), 声明时下载后校验
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<String>,
+}
+
+impl IndexPlugin {
+    ///
+    /// 按版本号从高到低排序的版本, 忽略无法解析的版本号
+    pub fn sorted(&self) -> Vec<(Version, &IndexVersion)> {
+        let mut versions: Vec<_> = self
+            .versions
+            .iter()
+            .filter_map(|v| Version::parse(&v.version).ok().map(|ver| (ver, v)))
+            .collect();
+        versions.sort_by(|a, b| b.0.cmp(&a.0));
+        versions
+    }
+
+    pub fn latest(&self) -> Option<&IndexVersion> {
+        self.sorted().first().map(|(_, v)| *v)
+    }
+}
+
+/// 仓库中的插件及其安装状态
+#[derive(Debug, Clone)]
+pub struct Available {
+    pub name: String,
+    pub description: String,
+    /// 从高到低
+    pub versions: Vec<String>,
+    pub latest: Option<String>,
+    /// 通过安装包安装的同名插件及其版本, 扫描加载的插件不算已安装
+    pub installed: Option<(PluginId, String)>,
+}
+
+impl Available {
+    ///
+    /// 仓库中是否有比已安装版本更高的版本
+    pub fn has_update(&self) -> bool {
+        match (&self.installed, &self.latest) {
+            (Some((_, installed)), Some(latest)) => newer(latest, installed),
+            _ => false,
+        }
+    }
+}
+
+pub struct Registry {
+    dir: PathBuf,
+    index: Index,
+}
+
+impl Registry {
+    ///
+    /// 打开仓库, `location` 为仓库文件夹或索引文件的路径, 或对应的 `file://` 地址
+    pub fn open(location: &str) -> Result<Self, PluginManagerError> {
+        let path = match location.strip_prefix(FILE_SCHEME) {
+            Some(path) => path,
+            None if location.contains("://") => {
+                return Err(invalid(format!("unsupported location: {location}")));
+            }
+            None => location,
+        };
+        let path = Path::new(path);
+        let (dir, index) = if path.is_dir() {
+            (path.to_path_buf(), path.join(INDEX))
+        } else {
+            let dir = path.parent().unwrap_or(Path::new("")).to_path_buf();
+            (dir, path.to_path_buf())
+        };
+        if !index.is_file() {
+            return Err(PluginManagerError::FileNotExists(index));
+        }
+        let index = serde_json::from_reader(fs::File::open(index)?)?;
+        Ok(Self { dir, index })
+    }
+
+    pub fn index(&self) -> &Index {
+        &self.index
+    }
+
+    ///
+    /// 列出仓库中的插件, 并与已加载的插件比较版本
+    pub fn available(&self, pm: &PluginManager) -> Vec<Available> {
+        self.index
+            .plugins
+            .iter()
+            .map(|plugin| {
+                let id = PluginId::from(plugin.name.as_str());
+                let sorted = plugin.sorted();
+                Available {
+                    name: plugin.name.clone(),
+                    description: plugin.description.clone(),
+                    latest: sorted.first().map(|(_, v)| v.version.clone()),
+                    versions: sorted.into_iter().map(|(_, v)| v.version.clone()).collect(),
+                    installed: pm
+                        .get(&id)
+                        .filter(|_| pm.is_installed(&id))
+                        .map(|info| (id, info.version)),
+                }
+            })
+            .collect()
+    }
+
+    ///
+    /// 安装仓库中的插件, 未指定版本时安装最高版本
+    pub fn install(
+        &self,
+        pm: &PluginManager,
+        name: &str,
+        version: Option<&str>,
+    ) -> Result<PluginId> {
+        let bundle = self.bundle(name, version)?;
+        pm.install(bundle)
+    }
+
+    ///
+    /// 将已安装的插件更新到仓库中的最高版本
+    pub fn update(&self, pm: &PluginManager, name: &str) -> Result<PluginId> {
+        let id = PluginId::from(name);
+        let info = pm.get(&id).ok_or(PluginManagerError::PluginNotFound(id))?;
+        let latest = self
+            .plugin(name)?
+            .latest()
+            .ok_or(invalid(format!("no valid version of {name}")))?;
+        if !newer(&latest.version, &info.version) {
+            let reason = format!("{name} {} is up to date", info.version);
+            return Err(invalid(reason).into());
+        }
+        let bundle = self.bundle(name, Some(&latest.version))?;
+        pm.update(&id, bundle)
+    }
+
+    fn plugin(&self, name: &str) -> Result<&IndexPlugin, PluginManagerError> {
+        self.index
+            .plugins
+            .iter()
+            .find(|p| p.name == name)
+            .ok_or(invalid(format!("{name} not in registry")))
+    }
+
+    ///
+    /// 安装包路径, 必须位于仓库文件夹内, 声明了 `sha256` 时校验
+    fn bundle(&self, name: &str, version: Option<&str>) -> Result<PathBuf, PluginManagerError> {
+        let plugin = self.plugin(name)?;
+        let entry = match version {
+            Some(version) => plugin.versions.iter().find(|v| v.version == version),
+            None => plugin.latest(),
+        }
+        .ok_or(invalid(format!(
+            "{name} {} not in registry",
+            version.unwrap_or("latest")
+        )))?;
+
+        let inside = Path::new(&entry.bundle)
+            .components()
+            .all(|c| matches!(c, Component::Normal(_)));
+        let outside = || invalid(format!("bundle not in registry: {}", entry.bundle));
+        if !inside {
+            return Err(outside());
+        }
+        // 仓库中的符号链接可能指向仓库之外
+        let bundle = self.dir.join(&entry.bundle).canonicalize()?;
+        if !bundle.starts_with(self.dir.canonicalize()?) {
+            return Err(outside());
+        }
+        if let Some(expected) = &entry.sha256 {
+            let actual = hex::encode(sha256_file(&bundle)?);
+            if !expected.eq_ignore_ascii_case(&actual) {
+                return Err(invalid(format!(
+                    "bundle sha256 mismatch: expected {expected}, actual {actual}"
+                )));
+            }
+        }
+        Ok(bundle)
+    }
+}
+
+fn newer(version: &str, than: &str) -> bool {
+    match (Version::parse(version), Version::parse(than)) {
+        (Ok(version), Ok(than)) => version > than,
+        _ => false,
+    }
+}
+
+fn invalid(reason: String) -> PluginManagerError {
+    PluginManagerError::Registry(reason)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_registry() -> Result<(), PluginManagerError> {
+        let dir = std::env::temp_dir().join("registry_index");
+        fs::create_dir_all(&dir)?;
+        fs::write(dir.join("demo-v0.1.0.tar.gz"), b"bundle")?;
+        fs::write(dir.join("demo-v0.2.0.tar.gz"), b"bundle")?;
+        fs::write(
+            dir.join(INDEX),
+            r#"{"plugins":[{"name":"demo","versions":[
+                {"version":"0.1.0","bundle":"demo-v0.1.0.tar.gz"},
+                {"version":"0.10.0","bundle":"demo-v0.2.0.tar.gz","sha256":"00"},
+                {"version":"latest","bundle":"demo.tar.gz"}
+            ]}]}"#,
+        )?;
+
+        let registry = Registry::open(&format!("{FILE_SCHEME}{}", dir.to_string_lossy()))?;
+        let available = registry.available(&PluginManager::default());
+        assert_eq!(available.len(), 1);
+        assert_eq!(available[0].versions, ["0.10.0", "0.1.0"]);
+        assert_eq!(available[0].latest.as_deref(), Some("0.10.0"));
+        assert!(available[0].installed.is_none());
+        assert!(!available[0].has_update());
+
+        // 扫描加载(不在安装目录下)的同名插件不算已安装
+        let lib = dir.join("demo-v0.0.1.so");
+        fs::write(&lib, b"lib")?;
+        let pm = PluginManager::default()
+            .with_lazy_load()
+            .with_install_dir(dir.join("installed"));
+        assert!(pm.load(&lib, String::new()).is_ok());
+        assert!(registry.available(&pm)[0].installed.is_none());
+
+        assert!(matches!(
+            registry.bundle("demo", None),
+            Err(PluginManagerError::Registry(_))
+        ));
+        assert!(registry.bundle("demo", Some("0.1.0")).is_ok());
+        assert!(registry.bundle("other", None).is_err());
+        assert!(Registry::open("http://localhost/index.json").is_err());
+
+        // 安装包路径不能指向仓库之外
+        let outside = |bundle: &str| {
+            let registry = Registry {
+                dir: dir.clone(),
+                index: Index {
+                    plugins: vec![IndexPlugin {
+                        name: String::from("demo"),
+                        description: String::new(),
+                        versions: vec![IndexVersion {
+                            version: String::from("0.1.0"),
+                            bundle: bundle.to_string(),
+                            sha256: None,
+                        }],
+                    }],
+                },
+            };
+            registry.bundle("demo", None).is_err()
+        };
+        assert!(outside("../registry_index/demo-v0.1.0.tar.gz"));
+        assert!(outside(&dir.join("demo-v0.1.0.tar.gz").to_string_lossy()));
+        assert!(!outside("demo-v0.1.0.tar.gz"));
+        #[cfg(unix)]
+        {
+            let target = std::env::temp_dir().join("registry_outside.tar.gz");
+            fs::write(&target, b"bundle")?;
+            let link = dir.join("link.tar.gz");
+            let _ = fs::remove_file(&link);
+            std::os::unix::fs::symlink(&target, &link)?;
+            assert!(outside("link.tar.gz"));
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,424 @@
+//! 插件的定时任务
+//!
+//! 插件在清单中声明任务, 按固定间隔或 cron 表达式调用插件方法:
+//! ```json
+//! "jobs": [
+//!     { "name": "sync", "every": 300, "method": "call_sync", "params": null },
+//!     { "name": "clean", "cron": "0 3 * * *", "method": "call_clean", "params": [7] }
+//! ]
+//! ```
+//! 同一任务不会重叠执行, 插件被禁用时任务暂停
+use chrono::Local;
+use dashmap::DashMap;
+use libcommon::prelude::{debug, warn};
+use plugin::Value;
+use serde::{Deserialize, Serialize};
+use std::{
+    str::FromStr,
+    sync::{
+        Arc, Mutex, Weak,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::{Duration, Instant, SystemTime},
+};
+use tokio::{sync::Notify, task::JoinHandle};
+
+use crate::{
+    batch::BatchResult,
+    err::PluginManagerError,
+    manager::{PluginId, PluginManager},
+};
+
+/// 没有待执行的任务时调度器的最长等待时间
+const IDLE: Duration = Duration::from_secs(60);
+
+///
+/// 清单中声明的任务, `every` 和 `cron` 需设置其中一个
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+pub struct JobSpec {
+    pub name: String,
+    /// 执行间隔(秒)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub every: Option<u64>,
+    /// cron 表达式, 5 个字段(分 时 日 月 周)或带秒的 6 个字段, 按本地时间
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cron: Option<String>,
+    pub method: String,
+    #[serde(default)]
+    pub params: Value,
+}
+
+#[derive(Debug, Clone)]
+pub enum Schedule {
+    Every(Duration),
+    Cron(Box<cron::Schedule>),
+}
+
+impl JobSpec {
+    pub fn schedule(&self) -> Result<Schedule, PluginManagerError> {
+        let invalid = |reason: String| PluginManagerError::Schedule(self.name.clone(), reason);
+        match (self.every, &self.cron) {
+            (Some(0), None) => Err(invalid(String::from("every must be greater than 0"))),
+            (Some(secs), None) => Ok(Schedule::Every(Duration::from_secs(secs))),
+            (None, Some(expr)) => {
+                let expr = expr.trim();
+                // cron 库的表达式以秒开始
+                let expr = if expr.split_whitespace().count() == 5 {
+                    format!("0 {expr}")
+                } else {
+                    expr.to_string()
+                };
+                let schedule =
+                    cron::Schedule::from_str(&expr).map_err(|e| invalid(e.to_string()))?;
+                Ok(Schedule::Cron(Box::new(schedule)))
+            }
+            _ => Err(invalid(String::from("need exactly one of every and cron"))),
+        }
+    }
+}
+
+impl Schedule {
+    ///
+    /// 从现在起到下次执行的时间, cron 没有下次执行时间时为 [`None`]
+    pub fn next(&self) -> Option<Duration> {
+        match self {
+            Self::Every(every) => Some(*every),
+            Self::Cron(schedule) => {
+                let now = Local::now();
+                let next = schedule.after(&now).next()?;
+                Some((next - now).to_std().unwrap_or_default())
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for Schedule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Every(every) => write!(f, "every {}s", every.as_secs()),
+            Self::Cron(schedule) => write!(f, "cron {schedule}"),
+        }
+    }
+}
+
+///
+/// 任务的状态快照
+#[derive(Debug, Clone)]
+pub struct JobInfo {
+    pub id: PluginId,
+    pub name: String,
+    pub method: String,
+    pub schedule: String,
+    pub paused: bool,
+    pub running: bool,
+    pub next_run: Option<SystemTime>,
+    pub last_run: Option<SystemTime>,
+    pub last_duration: Option<Duration>,
+    pub last_result: Option<BatchResult>,
+}
+
+struct Job {
+    id: PluginId,
+    spec: JobSpec,
+    schedule: Schedule,
+    /// 重新设置任务时与旧任务共用, 避免旧任务执行期间再次执行
+    running: Arc<AtomicBool>,
+    state: Mutex<JobState>,
+}
+
+#[derive(Default, Clone)]
+struct JobState {
+    paused: bool,
+    next: Option<Instant>,
+    last_run: Option<SystemTime>,
+    last_duration: Option<Duration>,
+    last_result: Option<BatchResult>,
+}
+
+impl Job {
+    fn state(&self) -> std::sync::MutexGuard<'_, JobState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn input(&self) -> Value {
+        plugin::dispatch_input(&self.spec.method, self.spec.params.clone())
+    }
+
+    fn info(&self) -> JobInfo {
+        let state = self.state();
+        let now = Instant::now();
+        JobInfo {
+            id: self.id,
+            name: self.spec.name.clone(),
+            method: self.spec.method.clone(),
+            schedule: self.schedule.to_string(),
+            paused: state.paused,
+            running: self.running.load(Ordering::Acquire),
+            next_run: state
+                .next
+                .filter(|_| !state.paused)
+                .map(|next| SystemTime::now() + next.saturating_duration_since(now)),
+            last_run: state.last_run,
+            last_duration: state.last_duration,
+            last_result: state.last_result.clone(),
+        }
+    }
+}
+
+/// 执行结束(包括被取消)时清除执行中标记
+struct Running<'a>(&'a AtomicBool);
+
+impl Drop for Running<'_> {
+    fn drop(&mut self) {
+        self.0.store(false, Ordering::Release);
+    }
+}
+
+///
+/// 任务调度器, 由 [`PluginManager::spawn_scheduler`] 在运行时中驱动
+#[derive(Default)]
+pub struct Scheduler {
+    jobs: DashMap<(PluginId, String), Arc<Job>>,
+    changed: Arc<Notify>,
+}
+
+impl Scheduler {
+    ///
+    /// 替换插件的任务, 同名任务保留上次执行的记录, 无效的任务被忽略
+    pub(crate) fn set(&self, id: PluginId, specs: &[JobSpec], paused: bool) {
+        let mut keep = Vec::new();
+        for spec in specs {
+            let schedule = match spec.schedule() {
+                Ok(schedule) => schedule,
+                Err(e) => {
+                    warn!("ignore job of plugin {id}: {e}");
+                    continue;
+                }
+            };
+            let key = (id, spec.name.clone());
+            let (running, mut state) = self
+                .jobs
+                .get(&key)
+                .map(|job| (job.running.clone(), job.state().clone()))
+                .unwrap_or_default();
+            state.paused = paused;
+            state.next = schedule.next().map(|next| Instant::now() + next);
+            let job = Job {
+                id,
+                spec: spec.clone(),
+                schedule,
+                running,
+                state: Mutex::new(state),
+            };
+            self.jobs.insert(key.clone(), Arc::new(job));
+            keep.push(key);
+        }
+        self.jobs.retain(|key, _| key.0 != id || keep.contains(key));
+        self.changed.notify_one();
+    }
+
+    pub(crate) fn remove(&self, id: &PluginId) {
+        self.jobs.retain(|key, _| key.0 != *id);
+        self.changed.notify_one();
+    }
+
+    ///
+    /// 暂停或恢复插件的所有任务, 恢复时重新计算下次执行时间
+    pub(crate) fn set_paused(&self, id: &PluginId, paused: bool) {
+        for job in self.jobs.iter().filter(|job| job.key().0 == *id) {
+            let mut state = job.state();
+            if state.paused != paused {
+                state.paused = paused;
+                state.next = job.schedule.next().map(|next| Instant::now() + next);
+            }
+        }
+        self.changed.notify_one();
+    }
+
+    pub fn list(&self) -> Vec<JobInfo> {
+        let mut jobs: Vec<_> = self.jobs.iter().map(|job| job.info()).collect();
+        jobs.sort_by(|a, b| (a.id.0, &a.name).cmp(&(b.id.0, &b.name)));
+        jobs
+    }
+
+    fn get(&self, id: &PluginId, name: &str) -> Option<Arc<Job>> {
+        self.jobs
+            .get(&(*id, name.to_string()))
+            .map(|job| job.clone())
+    }
+
+    ///
+    /// 取出到期的任务并计算其下次执行时间, 同时返回最近的下次执行时间
+    fn due(&self, now: Instant) -> (Vec<Arc<Job>>, Option<Instant>) {
+        let mut due = Vec::new();
+        let mut earliest: Option<Instant> = None;
+        for job in self.jobs.iter() {
+            let mut state = job.state();
+            if state.paused {
+                continue;
+            }
+            if let Some(next) = state.next
+                && next <= now
+            {
+                due.push(job.clone());
+                state.next = job.schedule.next().map(|next| now + next);
+            }
+            if let Some(next) = state.next {
+                earliest = Some(earliest.map_or(next, |e| e.min(next)));
+            }
+        }
+        (due, earliest)
+    }
+}
+
+impl PluginManager {
+    ///
+    /// 在当前 tokio 运行时中驱动定时任务, 插件管理器释放后结束
+    pub fn spawn_scheduler(self: &Arc<Self>) -> JoinHandle<()> {
+        let pm = Arc::downgrade(self);
+        let changed = self.scheduler().changed.clone();
+        tokio::spawn(async move {
+            loop {
+                let Some(manager) = pm.upgrade() else {
+                    break;
+                };
+                let (due, next) = manager.scheduler().due(Instant::now());
+                drop(manager);
+                for job in due {
+                    let pm: Weak<PluginManager> = pm.clone();
+                    tokio::spawn(async move {
+                        if let Some(pm) = pm.upgrade() {
+                            pm.execute(&job).await;
+                        }
+                    });
+                }
+                let next = next.unwrap_or_else(|| Instant::now() + IDLE);
+                tokio::select! {
+                    _ = tokio::time::sleep_until(next.min(Instant::now() + IDLE).into()) => {}
+                    _ = changed.notified() => {}
+                }
+            }
+        })
+    }
+
+    ///
+    /// 立即执行任务, 不影响其按计划的执行
+    pub async fn run_job(
+        &self,
+        id: &PluginId,
+        name: &str,
+    ) -> Result<BatchResult, PluginManagerError> {
+        let job = self
+            .scheduler()
+            .get(id, name)
+            .ok_or_else(|| PluginManagerError::JobNotFound(*id, name.to_string()))?;
+        if job.state().paused {
+            return Err(PluginManagerError::Disabled(*id));
+        }
+        self.execute(&job)
+            .await
+            .ok_or_else(|| PluginManagerError::JobRunning(*id, name.to_string()))
+    }
+
+    ///
+    /// 执行任务并记录结果, 任务正在执行时返回 [`None`]
+    async fn execute(&self, job: &Job) -> Option<BatchResult> {
+        if job.running.swap(true, Ordering::AcqRel) {
+            debug!(
+                "job {} of plugin {} is running, skip",
+                job.spec.name, job.id
+            );
+            return None;
+        }
+        let _running = Running(&job.running);
+        let started = SystemTime::now();
+        let start = Instant::now();
+        let result = self
+            .call(&job.id, job.input())
+            .await
+            .map_err(|e| e.to_string());
+        if let Err(e) = &result {
+            warn!("job {} of plugin {} failed: {e}", job.spec.name, job.id);
+        }
+        let mut state = job.state();
+        state.last_run = Some(started);
+        state.last_duration = Some(start.elapsed());
+        state.last_result = Some(result.clone());
+        Some(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spec(name: &str, every: Option<u64>, cron: Option<&str>) -> JobSpec {
+        JobSpec {
+            name: name.to_string(),
+            every,
+            cron: cron.map(String::from),
+            method: String::from("call_a"),
+            params: Value::Null,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_scheduler() {
+        assert!(spec("a", Some(0), None).schedule().is_err());
+        assert!(spec("a", None, None).schedule().is_err());
+        assert!(spec("a", Some(1), Some("* * * * *")).schedule().is_err());
+        assert!(spec("a", None, Some("bad")).schedule().is_err());
+        let cron = spec("a", None, Some("*/5 * * * *")).schedule().unwrap();
+        assert!(
+            cron.next()
+                .is_some_and(|next| next <= Duration::from_secs(300))
+        );
+
+        let pm = PluginManager::default();
+        let id = PluginId(1);
+        let specs = [spec("a", Some(60), None), spec("b", None, Some("bad"))];
+        pm.scheduler().set(id, &specs, false);
+        assert_eq!(pm.scheduler().list().len(), 1);
+
+        let later = Instant::now() + Duration::from_secs(61);
+        let (due, next) = pm.scheduler().due(later);
+        assert_eq!(due.len(), 1);
+        assert!(next.is_some_and(|next| next > later));
+
+        // 执行中的任务被重新设置后仍视为执行中
+        let running = |pm: &PluginManager| pm.scheduler().list()[0].running;
+        let job = pm
+            .scheduler()
+            .jobs
+            .get(&(id, "a".to_string()))
+            .unwrap()
+            .clone();
+        job.running.store(true, Ordering::Release);
+        pm.scheduler().set(id, &specs, false);
+        assert!(running(&pm));
+        drop(Running(&job.running));
+        assert!(!running(&pm));
+
+        pm.scheduler().set_paused(&id, true);
+        assert!(
+            pm.scheduler()
+                .due(later + Duration::from_secs(61))
+                .0
+                .is_empty()
+        );
+        assert!(matches!(
+            pm.run_job(&id, "a").await,
+            Err(PluginManagerError::Disabled(_))
+        ));
+
+        pm.scheduler().set_paused(&id, false);
+        let result = pm.run_job(&id, "a").await;
+        assert!(matches!(result, Ok(Err(_))));
+        let info = &pm.scheduler().list()[0];
+        assert!(info.last_run.is_some() && !info.running);
+        assert!(matches!(info.last_result, Some(Err(_))));
+
+        pm.scheduler().remove(&id);
+        assert!(pm.scheduler().list().is_empty());
+    }
+}
@@ -0,0 +1,117 @@
+//! 插件的启用状态
+//!
+//! 按插件名称保存被禁用的插件, 写入应用数据文件后重启仍然有效
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeSet,
+    fs, io,
+    path::{Path, PathBuf},
+    sync::RwLock,
+};
+
+#[derive(Debug, Serialize, Deserialize, Default)]
+struct StateFile {
+    #[serde(default)]
+    disabled: BTreeSet<String>,
+}
+
+///
+/// 插件的启用状态, 默认所有插件都启用
+///
+/// 未指定文件([`Default`])时只保存在内存中
+#[derive(Debug, Default)]
+pub struct PluginStates {
+    path: Option<PathBuf>,
+    disabled: RwLock<BTreeSet<String>>,
+}
+
+impl PluginStates {
+    ///
+    /// 从文件读取启用状态, 文件不存在时所有插件都启用, 修改时写入该文件
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        let path = path.as_ref();
+        let state = match fs::read(path) {
+            Ok(bytes) => serde_json::from_slice::<StateFile>(&bytes)?,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => StateFile::default(),
+            Err(e) => return Err(e),
+        };
+        Ok(Self {
+            path: Some(path.to_path_buf()),
+            disabled: RwLock::new(state.disabled),
+        })
+    }
+
+    pub fn is_enabled(&self, name: &str) -> bool {
+        !self.read().contains(name)
+    }
+
+    ///
+    /// 设置插件是否启用并保存, 返回状态是否改变
+    pub fn set_enabled(&self, name: &str, enabled: bool) -> io::Result<bool> {
+        let mut disabled = self.disabled.write().unwrap_or_else(|e| e.into_inner());
+        let changed = if enabled {
+            disabled.remove(name)
+        } else {
+            disabled.insert(name.to_string())
+        };
+        if changed && let Err(e) = self.save(&disabled) {
+            // 保存失败时恢复, 内存中的状态与文件保持一致
+            if enabled {
+                disabled.insert(name.to_string());
+            } else {
+                disabled.remove(name);
+            }
+            return Err(e);
+        }
+        Ok(changed)
+    }
+
+    ///
+    /// 被禁用的插件名称
+    pub fn disabled(&self) -> Vec<String> {
+        self.read().iter().cloned().collect()
+    }
+
+    fn save(&self, disabled: &BTreeSet<String>) -> io::Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let state = StateFile {
+            disabled: disabled.clone(),
+        };
+        // 先写入临时文件再替换, 避免写入中断时损坏原文件
+        let tmp = path.with_extension("tmp");
+        fs::write(&tmp, serde_json::to_vec_pretty(&state)?)?;
+        fs::rename(&tmp, path)
+    }
+
+    fn read(&self) -> std::sync::RwLockReadGuard<'_, BTreeSet<String>> {
+        self.disabled.read().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_persist() -> io::Result<()> {
+        let path = std::env::temp_dir().join("plugin_state_test/plugins.json");
+        let _ = fs::remove_file(&path);
+        let states = PluginStates::open(&path)?;
+        assert!(states.is_enabled("a"));
+        assert!(states.set_enabled("a", false)?);
+        assert!(!states.set_enabled("a", false)?);
+        assert!(states.set_enabled("b", false)?);
+        assert!(states.set_enabled("b", true)?);
+
+        let states = PluginStates::open(&path)?;
+        assert!(!states.is_enabled("a"));
+        assert!(states.is_enabled("b"));
+        assert_eq!(states.disabled(), ["a"]);
+        Ok(())
+    }
+}
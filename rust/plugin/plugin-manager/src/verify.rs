@@ -0,0 +1,369 @@
+use ed25519_dalek::{Signature, Verifier as _, VerifyingKey};
+use libcommon::prelude::warn;
+use sha2::{Digest, Sha256};
+use std::{
+    fs,
+    io::{self, Read, Write},
+    path::{Path, PathBuf},
+    str::FromStr,
+    time::{SystemTime, UNIX_EPOCH},
+};
+use thiserror::Error;
+
+/// 加载插件库前的校验策略
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum VerifyPolicy {
+    /// 不校验
+    #[default]
+    Off,
+    /// 校验, 失败时只打印警告, 仍然加载
+    Warn,
+    /// 校验, 失败时拒绝加载
+    Enforce,
+}
+
+impl FromStr for VerifyPolicy {
+    type Err = VerifyError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "off" => Ok(Self::Off),
+            "warn" => Ok(Self::Warn),
+            "enforce" => Ok(Self::Enforce),
+            _ => Err(VerifyError::UnknownPolicy(s.to_string())),
+        }
+    }
+}
+
+/// 清单中声明的插件库完整性信息
+///
+/// - `sha256`: 库文件的 SHA-256, hex 编码
+/// - `signature`: 发布者使用 ed25519 私钥对库文件 SHA-256 摘要(32字节)的签名, hex 编码
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Integrity {
+    pub sha256: Option<String>,
+    pub signature: Option<String>,
+}
+
+/// 插件库校验器
+///
+/// 配置了受信任公钥时, 插件库必须带有其中任一公钥可验证的签名;
+/// 否则至少要提供与文件一致的 `sha256`.
+#[derive(Debug, Clone, Default)]
+pub struct Verifier {
+    policy: VerifyPolicy,
+    trusted: Vec<VerifyingKey>,
+}
+
+impl Verifier {
+    pub fn new(policy: VerifyPolicy) -> Self {
+        Self {
+            policy,
+            trusted: Vec::new(),
+        }
+    }
+
+    /// 添加受信任的发布者公钥(ed25519, 32字节, hex 编码)
+    pub fn trust(mut self, key: &str) -> Result<Self, VerifyError> {
+        let bytes: [u8; 32] = decode_hex(key)?
+            .try_into()
+            .map_err(|_| VerifyError::InvalidKey(key.to_string()))?;
+        let key = VerifyingKey::from_bytes(&bytes)
+            .map_err(|_| VerifyError::InvalidKey(key.to_string()))?;
+        self.trusted.push(key);
+        Ok(self)
+    }
+
+    pub fn policy(&self) -> VerifyPolicy {
+        self.policy
+    }
+
+    ///
+    /// 按策略校验插件库
+    ///
+    /// [`VerifyPolicy::Warn`] 下校验失败只打印警告并返回 `Ok`
+    pub fn verify(&self, path: &Path, integrity: &Integrity) -> Result<(), VerifyError> {
+        match self.policy {
+            VerifyPolicy::Off => Ok(()),
+            VerifyPolicy::Warn => {
+                if let Err(e) = self.check(path, integrity) {
+                    warn!("plugin {path:?} verify failed, load anyway: {e}");
+                }
+                Ok(())
+            }
+            VerifyPolicy::Enforce => self.check(path, integrity),
+        }
+    }
+
+    ///
+    /// 按策略校验插件库, 返回用于加载的文件
+    ///
+    /// 策略不是 [`VerifyPolicy::Off`] 时, 边复制到私有的临时文件边计算摘要, 校验并加载该副本,
+    /// 避免校验后、加载前原文件被替换
+    pub fn prepare(&self, path: &Path, integrity: &Integrity) -> Result<Verified, VerifyError> {
+        if self.policy == VerifyPolicy::Off {
+            return Ok(Verified {
+                path: path.to_path_buf(),
+                copied: false,
+            });
+        }
+        let (copy, digest) = private_copy(path)?;
+        match self.policy {
+            VerifyPolicy::Warn => {
+                if let Err(e) = self.check_digest(digest, integrity) {
+                    warn!("plugin {path:?} verify failed, load anyway: {e}");
+                }
+            }
+            _ => self.check_digest(digest, integrity)?,
+        }
+        Ok(copy)
+    }
+
+    fn check(&self, path: &Path, integrity: &Integrity) -> Result<(), VerifyError> {
+        self.check_digest(sha256_file(path)?, integrity)
+    }
+
+    fn check_digest(&self, digest: [u8; 32], integrity: &Integrity) -> Result<(), VerifyError> {
+        if integrity.sha256.is_none() && integrity.signature.is_none() {
+            return Err(VerifyError::Missing);
+        }
+
+        if let Some(expected) = &integrity.sha256 {
+            let actual = hex::encode(digest);
+            if !expected.eq_ignore_ascii_case(&actual) {
+                return Err(VerifyError::ChecksumMismatch {
+                    expected: expected.clone(),
+                    actual,
+                });
+            }
+        }
+
+        if self.trusted.is_empty() {
+            return match integrity.sha256 {
+                Some(_) => Ok(()),
+                None => Err(VerifyError::Untrusted),
+            };
+        }
+        let signature = integrity.signature.as_ref().ok_or(VerifyError::Unsigned)?;
+        let bytes: [u8; 64] = decode_hex(signature)?
+            .try_into()
+            .map_err(|_| VerifyError::InvalidSignature(signature.clone()))?;
+        let signature = Signature::from_bytes(&bytes);
+        if self
+            .trusted
+            .iter()
+            .any(|key| key.verify(&digest, &signature).is_ok())
+        {
+            Ok(())
+        } else {
+            Err(VerifyError::Untrusted)
+        }
+    }
+}
+
+/// 计算文件的 SHA-256
+pub fn sha256_file(path: &Path) -> Result<[u8; 32], VerifyError> {
+    let mut hasher = Sha256::new();
+    io::copy(&mut fs::File::open(path)?, &mut hasher)?;
+    Ok(hasher.finalize().into())
+}
+
+/// 校验通过(或无需校验)的插件库文件
+///
+/// 若是 [`Verifier::prepare`] 复制出的副本, 释放时删除
+#[derive(Debug)]
+pub struct Verified {
+    path: PathBuf,
+    copied: bool,
+}
+
+impl Verified {
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for Verified {
+    fn drop(&mut self) {
+        if self.copied
+            && let Err(e) = fs::remove_file(&self.path)
+        {
+            warn!("failed to remove {:?}: {e}", self.path);
+        }
+    }
+}
+
+/// 复制到临时文件夹中仅当前用户可读写的新文件, 同时计算读到内容的 SHA-256
+fn private_copy(path: &Path) -> Result<(Verified, [u8; 32]), VerifyError> {
+    let mut src = fs::File::open(path)?;
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos())
+        .unwrap_or_default();
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    let dest = std::env::temp_dir().join(format!("plugin-{}-{nanos}-{name}", std::process::id()));
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    let mut file = options.open(&dest)?;
+    let copy = Verified {
+        path: dest,
+        copied: true,
+    };
+
+    let mut hasher = Sha256::new();
+    let mut buf = vec![0; 64 * 1024];
+    loop {
+        let n = src.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+        file.write_all(&buf[..n])?;
+    }
+    file.sync_all()?;
+    Ok((copy, hasher.finalize().into()))
+}
+
+fn decode_hex(str: &str) -> Result<Vec<u8>, VerifyError> {
+    hex::decode(str.trim()).map_err(|e| VerifyError::InvalidHex(e.to_string()))
+}
+
+#[derive(Debug, Error)]
+pub enum VerifyError {
+    #[error("no sha256 or signature provided")]
+    Missing,
+    #[error("sha256 mismatch: expected {expected}, actual {actual}")]
+    ChecksumMismatch { expected: String, actual: String },
+    #[error("library is not signed")]
+    Unsigned,
+    #[error("signature not trusted by any publisher key")]
+    Untrusted,
+    #[error("invalid signature: {0}")]
+    InvalidSignature(String),
+    #[error("invalid publisher key: {0}")]
+    InvalidKey(String),
+    #[error("invalid hex: {0}")]
+    InvalidHex(String),
+    #[error("unknown verify policy: {0}")]
+    UnknownPolicy(String),
+    #[error("Io error: {0}")]
+    Io(#[from] io::Error),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ed25519_dalek::{Signer, SigningKey};
+
+    /// 测试用的库文件, 每个测试和进程单独一个目录, 结束时删除
+    struct LibFile {
+        dir: PathBuf,
+        path: PathBuf,
+    }
+
+    impl Drop for LibFile {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.dir);
+        }
+    }
+
+    fn lib_file(test: &str) -> LibFile {
+        let dir = std::env::temp_dir().join(format!("plugin_{test}_test_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("libtest.so");
+        fs::write(&path, b"not really a library").unwrap();
+        LibFile { dir, path }
+    }
+
+    #[test]
+    fn test_checksum() {
+        let lib = lib_file("verify_checksum");
+        let path = lib.path.clone();
+        let sha256 = hex::encode(sha256_file(&path).unwrap());
+        let verifier = Verifier::new(VerifyPolicy::Enforce);
+
+        let ok = Integrity {
+            sha256: Some(sha256.to_uppercase()),
+            signature: None,
+        };
+        assert!(verifier.verify(&path, &ok).is_ok());
+
+        let bad = Integrity {
+            sha256: Some("00".repeat(32)),
+            signature: None,
+        };
+        assert!(matches!(
+            verifier.verify(&path, &bad),
+            Err(VerifyError::ChecksumMismatch { .. })
+        ));
+        assert!(matches!(
+            verifier.verify(&path, &Integrity::default()),
+            Err(VerifyError::Missing)
+        ));
+        assert!(
+            Verifier::new(VerifyPolicy::Warn)
+                .verify(&path, &bad)
+                .is_ok()
+        );
+    }
+
+    #[test]
+    fn test_prepare() {
+        let lib = lib_file("verify_prepare");
+        let path = lib.path.clone();
+        let integrity = Integrity {
+            sha256: Some(hex::encode(sha256_file(&path).unwrap())),
+            signature: None,
+        };
+        let verified = Verifier::new(VerifyPolicy::Enforce)
+            .prepare(&path, &integrity)
+            .unwrap();
+        let copy = verified.path().to_path_buf();
+        assert_ne!(copy, path);
+
+        // 校验后替换原文件不影响将要加载的副本
+        fs::write(&path, b"replaced").unwrap();
+        assert_eq!(fs::read(&copy).unwrap(), b"not really a library");
+        drop(verified);
+        assert!(!copy.exists());
+
+        let off = Verifier::default().prepare(&path, &integrity).unwrap();
+        assert_eq!(off.path(), path);
+        drop(off);
+        assert!(path.exists());
+    }
+
+    #[test]
+    fn test_signature() {
+        let lib = lib_file("verify_signature");
+        let path = lib.path.clone();
+        let publisher = SigningKey::from_bytes(&[7; 32]);
+        let other = SigningKey::from_bytes(&[8; 32]);
+        let digest = sha256_file(&path).unwrap();
+
+        let verifier = Verifier::new(VerifyPolicy::Enforce)
+            .trust(&hex::encode(publisher.verifying_key().to_bytes()))
+            .unwrap();
+
+        let signed = |key: &SigningKey| Integrity {
+            sha256: None,
+            signature: Some(hex::encode(key.sign(&digest).to_bytes())),
+        };
+        assert!(verifier.verify(&path, &signed(&publisher)).is_ok());
+        assert!(matches!(
+            verifier.verify(&path, &signed(&other)),
+            Err(VerifyError::Untrusted)
+        ));
+
+        let unsigned = Integrity {
+            sha256: Some(hex::encode(digest)),
+            signature: None,
+        };
+        assert!(matches!(
+            verifier.verify(&path, &unsigned),
+            Err(VerifyError::Unsigned)
+        ));
+    }
+}
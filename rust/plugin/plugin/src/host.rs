@@ -0,0 +1,24 @@
+use crate::Value;
+use std::sync::Arc;
+
+/// 订阅的回调, 参数为消息的主题和内容
+pub type EventHandler = Box<dyn Fn(&str, &Value) + Send + Sync>;
+
+///
+/// 宿主提供给插件的上下文, 加载插件后通过 [`crate::Plugin::attach`] 传入
+///
+/// 主题以 `/` 分隔层级, 订阅时 `+` 匹配一个层级, `#` 匹配之后的所有层级
+pub trait HostContext: Send + Sync {
+    ///
+    /// 发布消息, `retain` 时保留为该主题的最新消息
+    fn publish(&self, topic: &str, payload: Value, retain: bool) -> Result<(), String>;
+
+    ///
+    /// 订阅主题, 返回订阅 id; 插件卸载时自动取消订阅
+    fn subscribe(&self, pattern: &str, handler: EventHandler) -> Result<u64, String>;
+
+    fn unsubscribe(&self, id: u64);
+}
+
+/// 共享的宿主上下文
+pub type Host = Arc<dyn HostContext>;
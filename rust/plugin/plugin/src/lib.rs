@@ -1,10 +1,32 @@
+mod host;
 mod plugin;
 pub mod prelude;
 
+pub use host::*;
 pub use plugin::*;
+pub use plugin_macro::Describe;
 pub use plugin_macro::FromValue;
 pub use plugin_macro::plugin_export;
 pub use plugin_macro::plugin_dispatch;
 
 pub use async_trait::async_trait;
-pub use serde_json::*;
\ No newline at end of file
+pub use value::*;
+pub use serde_json;
+
+///
+/// 以 json 语法构造 [`Value`], 整数为 [`Number::I64`], 小数为 [`Number::F64`]
+#[macro_export]
+macro_rules! json {
+    ($($json:tt)+) => {
+        $crate::Value::from($crate::serde_json::json!($($json)+))
+    };
+}
+
+///
+/// `#[plugin_dispatch]` 分发的调用参数 `{ "method": .., "params": .. }`
+pub fn dispatch_input(method: &str, params: Value) -> Value {
+    Value::Map(Map::from([
+        (String::from("method"), Value::from(method)),
+        (String::from("params"), params),
+    ]))
+}
@@ -1,6 +1,20 @@
-use crate::prelude::*;
+use crate::{Host, prelude::*};
+use std::time::Duration;
 
 #[async_trait]
 pub trait Plugin: Send + Sync {
     async fn call(&self, input: Value) -> Result<Value, Box<dyn std::error::Error + Send + Sync>>;
+
+    /// 可缓存结果的方法及缓存时间, 由 `#[plugin_dispatch]` 根据方法上的 `#[cache(ttl = 秒)]` 生成
+    fn cacheable(&self) -> Vec<(&'static str, Duration)> {
+        Vec::new()
+    }
+
+    /// 可调用的方法名, 由 `#[plugin_dispatch]` 生成
+    fn methods(&self) -> Vec<&'static str> {
+        Vec::new()
+    }
+
+    /// 加载后由宿主调用, 需要发布或订阅消息的插件保存 `host`
+    fn attach(&self, _host: Host) {}
 }
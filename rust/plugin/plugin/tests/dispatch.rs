@@ -0,0 +1,36 @@
+use plugin::{Plugin, Value, ValueParseError, dispatch_input, json, plugin_dispatch};
+
+struct Demo;
+
+#[plugin_dispatch]
+impl Demo {
+    async fn call_add(&self, a: i64, b: i64) -> i64 {
+        a + b
+    }
+
+    async fn call_len(&self, s: String) -> usize {
+        s.len()
+    }
+}
+
+async fn parse_error(method: &str, params: Value) -> ValueParseError {
+    let err = Demo.call(dispatch_input(method, params)).await.unwrap_err();
+    *err.downcast::<ValueParseError>().unwrap()
+}
+
+#[tokio::test]
+async fn test_dispatch_error() {
+    let sum = Demo.call(dispatch_input("call_add", json!([1, 2]))).await;
+    assert_eq!(sum.unwrap(), json!(3));
+
+    // 参数转换失败时给出参数在调用输入中的路径和类型
+    let e = parse_error("call_add", json!([1, "x"])).await;
+    assert_eq!(
+        (e.path.as_str(), e.expected, e.actual),
+        ("/params/1", "i64", "string")
+    );
+    let e = parse_error("call_len", json!(1)).await;
+    assert_eq!((e.path.as_str(), e.actual), ("/params", "number"));
+    let e = parse_error("call_add", json!([1])).await;
+    assert_eq!(e.path, "/params");
+}
@@ -0,0 +1,62 @@
+//! 二进制编码与 serde_json 的对比
+//!
+//! `cargo bench -p value --bench codec`
+use std::{
+    collections::HashMap,
+    hint::black_box,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+use value::{Value, binary};
+
+const ROUNDS: u32 = 200;
+
+fn payload() -> Value {
+    let items = (0..1000)
+        .map(|i| {
+            Value::from(HashMap::from([
+                (String::from("id"), Value::from(i as u64)),
+                (String::from("name"), Value::from(format!("item-{i}"))),
+                (String::from("score"), Value::from(i as f64 / 3.0)),
+                (String::from("tags"), Value::from(vec!["a", "b", "c"])),
+            ]))
+        })
+        .collect::<Vec<_>>();
+    Value::from(HashMap::from([
+        (String::from("items"), Value::Array(items)),
+        (
+            String::from("blob"),
+            Value::Bytes(Arc::from(vec![0x5au8; 1 << 20])),
+        ),
+    ]))
+}
+
+fn bench(name: &str, size: usize, mut f: impl FnMut()) {
+    f();
+    let start = Instant::now();
+    for _ in 0..ROUNDS {
+        f();
+    }
+    let per = start.elapsed() / ROUNDS;
+    let rate = size as f64 / per.max(Duration::from_nanos(1)).as_secs_f64() / 1e6;
+    println!("{name:<24} {per:>12.2?}/iter {size:>10} bytes {rate:>10.1} MB/s");
+}
+
+fn main() {
+    let value = payload();
+    let bin = binary::to_vec(&value).unwrap();
+    let json = serde_json::to_vec(&value).unwrap();
+
+    bench("binary encode", bin.len(), || {
+        black_box(binary::to_vec(black_box(&value)).unwrap());
+    });
+    bench("serde_json encode", json.len(), || {
+        black_box(serde_json::to_vec(black_box(&value)).unwrap());
+    });
+    bench("binary decode", bin.len(), || {
+        black_box(binary::from_slice(black_box(&bin)).unwrap());
+    });
+    bench("serde_json decode", json.len(), || {
+        black_box(serde_json::from_slice::<Value>(black_box(&json)).unwrap());
+    });
+}
@@ -0,0 +1,510 @@
+//! [`Value`] 的二进制编码
+//!
+//! 自描述的紧凑格式, 保留数字类型和二进制数据; 每个值以 1 字节标记开始, 多字节数字均为小端序,
+//! 长度为 LEB128 编码的无符号整数(`len`):
+//!
+//! | 标记 | 值 | 内容 |
+//! | --- | --- | --- |
+//! | `0x00` | `Null` | |
+//! | `0x01`/`0x02` | `Bool(false)`/`Bool(true)` | |
+//! | `0x10`..`0x13` | `I8`/`I16`/`I32`/`I64` | 1/2/4/8 字节 |
+//! | `0x14`..`0x17` | `U8`/`U16`/`U32`/`U64` | 1/2/4/8 字节 |
+//! | `0x18`/`0x19` | `F32`/`F64` | 4/8 字节 |
+//! | `0x20` | `String` | `len` + utf-8 |
+//! | `0x21` | `Bytes` | `len` + 原始数据 |
+//! | `0x30` | `Array` | `len` + `len` 个值 |
+//! | `0x31` | `Map` | `len` + `len` 个(`len` + utf-8 键, 值) |
+//!
+//! 多个值可以依次写入同一个流, 用 [`Decoder::read`] 逐个读出
+//!
+//! ```ignore
+//! let buf = binary::to_vec(&value)?;
+//! let value = binary::from_slice(&buf)?;
+//! ```
+use std::{
+    fmt,
+    io::{self, Read, Write},
+    sync::Arc,
+};
+
+use crate::{Map, Number, Value};
+
+const NULL: u8 = 0x00;
+const FALSE: u8 = 0x01;
+const TRUE: u8 = 0x02;
+const I8: u8 = 0x10;
+const I16: u8 = 0x11;
+const I32: u8 = 0x12;
+const I64: u8 = 0x13;
+const U8: u8 = 0x14;
+const U16: u8 = 0x15;
+const U32: u8 = 0x16;
+const U64: u8 = 0x17;
+const F32: u8 = 0x18;
+const F64: u8 = 0x19;
+const STRING: u8 = 0x20;
+const BYTES: u8 = 0x21;
+const ARRAY: u8 = 0x30;
+const MAP: u8 = 0x31;
+
+///
+/// 编解码的限制, 超出时返回错误; 解码时在分配内存前检查声明的长度
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Limits {
+    /// 数组和映射的最大嵌套层数
+    pub max_depth: usize,
+    /// 单个值编码后的最大字节数
+    pub max_size: usize,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self {
+            max_depth: 128,
+            max_size: 64 * 1024 * 1024,
+        }
+    }
+}
+
+impl Limits {
+    pub fn with_max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    pub fn with_max_size(mut self, max_size: usize) -> Self {
+        self.max_size = max_size;
+        self
+    }
+}
+
+#[derive(Debug)]
+pub enum CodecError {
+    Io(io::Error),
+    /// 未知的类型标记
+    Tag(u8),
+    Utf8,
+    /// 长度的编码超过 64 位
+    Length,
+    /// 嵌套超过 [`Limits::max_depth`]
+    TooDeep(usize),
+    /// 编码后超过 [`Limits::max_size`]
+    TooLarge(usize),
+    /// [`from_slice`] 解码后还有剩余数据
+    Trailing(usize),
+}
+
+impl fmt::Display for CodecError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "io error: {e}"),
+            Self::Tag(tag) => write!(f, "unknown type tag: {tag:#04x}"),
+            Self::Utf8 => write!(f, "invalid utf-8 string"),
+            Self::Length => write!(f, "invalid length"),
+            Self::TooDeep(limit) => write!(f, "nesting deeper than {limit}"),
+            Self::TooLarge(limit) => write!(f, "value larger than {limit} bytes"),
+            Self::Trailing(len) => write!(f, "{len} trailing bytes"),
+        }
+    }
+}
+
+impl std::error::Error for CodecError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for CodecError {
+    fn from(value: io::Error) -> Self {
+        Self::Io(value)
+    }
+}
+
+pub type Result<T> = std::result::Result<T, CodecError>;
+
+///
+/// 编码后的字节数
+pub fn encoded_len(value: &Value) -> usize {
+    1 + match value {
+        Value::Null | Value::Bool(_) => 0,
+        Value::Number(n) => match n {
+            Number::I8(_) | Number::U8(_) => 1,
+            Number::I16(_) | Number::U16(_) => 2,
+            Number::I32(_) | Number::U32(_) | Number::F32(_) => 4,
+            Number::I64(_) | Number::U64(_) | Number::F64(_) => 8,
+        },
+        Value::String(s) => len_size(s.len()) + s.len(),
+        Value::Bytes(b) => len_size(b.len()) + b.len(),
+        Value::Array(vec) => len_size(vec.len()) + vec.iter().map(encoded_len).sum::<usize>(),
+        Value::Map(map) => {
+            len_size(map.len())
+                + map
+                    .iter()
+                    .map(|(k, v)| len_size(k.len()) + k.len() + encoded_len(v))
+                    .sum::<usize>()
+        }
+    }
+}
+
+pub fn to_vec(value: &Value) -> Result<Vec<u8>> {
+    let mut buf = Vec::with_capacity(encoded_len(value));
+    Encoder::new(&mut buf).write(value)?;
+    Ok(buf)
+}
+
+///
+/// 使用默认限制解码一个值, 数据必须恰好是一个值
+pub fn from_slice(buf: &[u8]) -> Result<Value> {
+    let mut reader = buf;
+    let value = Decoder::new(&mut reader).read_value()?;
+    match reader.len() {
+        0 => Ok(value),
+        len => Err(CodecError::Trailing(len)),
+    }
+}
+
+///
+/// 流式编码, 值直接写入 `writer`, 二进制数据不经过中间缓冲
+pub struct Encoder<W> {
+    writer: W,
+    limits: Limits,
+}
+
+impl<W: Write> Encoder<W> {
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer,
+            limits: Limits::default(),
+        }
+    }
+
+    pub fn with_limits(mut self, limits: Limits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    ///
+    /// 写入一个值, 超出限制时不写入任何数据
+    pub fn write(&mut self, value: &Value) -> Result<()> {
+        check_depth(value, self.limits.max_depth)?;
+        if encoded_len(value) > self.limits.max_size {
+            return Err(CodecError::TooLarge(self.limits.max_size));
+        }
+        self.write_value(value)
+    }
+
+    pub fn flush(&mut self) -> Result<()> {
+        Ok(self.writer.flush()?)
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+
+    fn write_value(&mut self, value: &Value) -> Result<()> {
+        let w = &mut self.writer;
+        match value {
+            Value::Null => w.write_all(&[NULL])?,
+            Value::Bool(false) => w.write_all(&[FALSE])?,
+            Value::Bool(true) => w.write_all(&[TRUE])?,
+            Value::Number(n) => match *n {
+                Number::I8(n) => write_tagged(w, I8, &n.to_le_bytes())?,
+                Number::I16(n) => write_tagged(w, I16, &n.to_le_bytes())?,
+                Number::I32(n) => write_tagged(w, I32, &n.to_le_bytes())?,
+                Number::I64(n) => write_tagged(w, I64, &n.to_le_bytes())?,
+                Number::U8(n) => write_tagged(w, U8, &n.to_le_bytes())?,
+                Number::U16(n) => write_tagged(w, U16, &n.to_le_bytes())?,
+                Number::U32(n) => write_tagged(w, U32, &n.to_le_bytes())?,
+                Number::U64(n) => write_tagged(w, U64, &n.to_le_bytes())?,
+                Number::F32(n) => write_tagged(w, F32, &n.to_le_bytes())?,
+                Number::F64(n) => write_tagged(w, F64, &n.to_le_bytes())?,
+            },
+            Value::String(s) => {
+                w.write_all(&[STRING])?;
+                write_bytes(w, s.as_bytes())?;
+            }
+            Value::Bytes(b) => {
+                w.write_all(&[BYTES])?;
+                write_bytes(w, b)?;
+            }
+            Value::Array(vec) => {
+                w.write_all(&[ARRAY])?;
+                write_len(w, vec.len())?;
+                for v in vec {
+                    self.write_value(v)?;
+                }
+            }
+            Value::Map(map) => {
+                w.write_all(&[MAP])?;
+                write_len(w, map.len())?;
+                for (k, v) in map {
+                    write_bytes(&mut self.writer, k.as_bytes())?;
+                    self.write_value(v)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+///
+/// 流式解码, 每次从 `reader` 读出一个值
+pub struct Decoder<R> {
+    reader: R,
+    limits: Limits,
+    /// 当前值剩余可读的字节数
+    remaining: usize,
+}
+
+impl<R: Read> Decoder<R> {
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            limits: Limits::default(),
+            remaining: 0,
+        }
+    }
+
+    pub fn with_limits(mut self, limits: Limits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    ///
+    /// 读出下一个值, 流在值之间结束时返回 `None`
+    pub fn read(&mut self) -> Result<Option<Value>> {
+        let mut tag = [0u8];
+        loop {
+            match self.reader.read(&mut tag) {
+                Ok(0) => return Ok(None),
+                Ok(_) => break,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e.into()),
+            }
+        }
+        self.remaining = self.limits.max_size;
+        self.take(1)?;
+        self.read_tagged(tag[0], 0).map(Some)
+    }
+
+    ///
+    /// 读出一个值, 流已结束时返回 [`io::ErrorKind::UnexpectedEof`]
+    pub fn read_value(&mut self) -> Result<Value> {
+        self.read()?
+            .ok_or_else(|| io::Error::from(io::ErrorKind::UnexpectedEof).into())
+    }
+
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+
+    fn take(&mut self, len: usize) -> Result<()> {
+        self.remaining = self
+            .remaining
+            .checked_sub(len)
+            .ok_or(CodecError::TooLarge(self.limits.max_size))?;
+        Ok(())
+    }
+
+    fn read_array<const N: usize>(&mut self) -> Result<[u8; N]> {
+        self.take(N)?;
+        let mut buf = [0u8; N];
+        self.reader.read_exact(&mut buf)?;
+        Ok(buf)
+    }
+
+    fn read_len(&mut self) -> Result<usize> {
+        let mut len: u64 = 0;
+        for shift in (0..64).step_by(7) {
+            let [byte] = self.read_array::<1>()?;
+            len |= u64::from(byte & 0x7f) << shift;
+            if byte & 0x80 == 0 {
+                // 超过剩余字节数的长度不可能有效, 不必分配
+                return match usize::try_from(len) {
+                    Ok(len) if len <= self.remaining => Ok(len),
+                    _ => Err(CodecError::TooLarge(self.limits.max_size)),
+                };
+            }
+        }
+        Err(CodecError::Length)
+    }
+
+    ///
+    /// 直接读入最终的 `Arc<[u8]>`, 不经过中间缓冲
+    fn read_shared(&mut self) -> Result<Arc<[u8]>> {
+        let len = self.read_len()?;
+        self.take(len)?;
+        // SAFETY: 全部置零的 u8 是有效的值
+        let mut buf: Arc<[u8]> = unsafe { Arc::new_zeroed_slice(len).assume_init() };
+        let slice = Arc::get_mut(&mut buf).expect("newly created Arc is unique");
+        self.reader.read_exact(slice)?;
+        Ok(buf)
+    }
+
+    fn read_string(&mut self) -> Result<String> {
+        let len = self.read_len()?;
+        self.take(len)?;
+        let mut buf = vec![0u8; len];
+        self.reader.read_exact(&mut buf)?;
+        String::from_utf8(buf).map_err(|_| CodecError::Utf8)
+    }
+
+    fn read_value_at(&mut self, depth: usize) -> Result<Value> {
+        let [tag] = self.read_array::<1>()?;
+        self.read_tagged(tag, depth)
+    }
+
+    fn read_tagged(&mut self, tag: u8, depth: usize) -> Result<Value> {
+        Ok(match tag {
+            NULL => Value::Null,
+            FALSE => Value::Bool(false),
+            TRUE => Value::Bool(true),
+            I8 => i8::from_le_bytes(self.read_array()?).into(),
+            I16 => i16::from_le_bytes(self.read_array()?).into(),
+            I32 => i32::from_le_bytes(self.read_array()?).into(),
+            I64 => i64::from_le_bytes(self.read_array()?).into(),
+            U8 => u8::from_le_bytes(self.read_array()?).into(),
+            U16 => u16::from_le_bytes(self.read_array()?).into(),
+            U32 => u32::from_le_bytes(self.read_array()?).into(),
+            U64 => u64::from_le_bytes(self.read_array()?).into(),
+            F32 => f32::from_le_bytes(self.read_array()?).into(),
+            F64 => f64::from_le_bytes(self.read_array()?).into(),
+            STRING => {
+                let s = self.read_shared()?;
+                Value::String(Arc::from(
+                    std::str::from_utf8(&s).map_err(|_| CodecError::Utf8)?,
+                ))
+            }
+            BYTES => Value::Bytes(self.read_shared()?),
+            ARRAY | MAP if depth >= self.limits.max_depth => {
+                return Err(CodecError::TooDeep(self.limits.max_depth));
+            }
+            ARRAY => {
+                // 每个值至少 1 字节, 长度已经过剩余字节数检查
+                let len = self.read_len()?;
+                let mut vec = Vec::with_capacity(len);
+                for _ in 0..len {
+                    vec.push(self.read_value_at(depth + 1)?);
+                }
+                Value::Array(vec)
+            }
+            MAP => {
+                let len = self.read_len()?;
+                let mut map = Map::with_capacity(len);
+                for _ in 0..len {
+                    let k = self.read_string()?;
+                    map.insert(k, self.read_value_at(depth + 1)?);
+                }
+                Value::Map(map)
+            }
+            tag => return Err(CodecError::Tag(tag)),
+        })
+    }
+}
+
+fn check_depth(value: &Value, limit: usize) -> Result<()> {
+    fn depth(value: &Value) -> usize {
+        match value {
+            Value::Array(vec) => 1 + vec.iter().map(depth).max().unwrap_or(0),
+            Value::Map(map) => 1 + map.values().map(depth).max().unwrap_or(0),
+            _ => 0,
+        }
+    }
+    if depth(value) > limit {
+        return Err(CodecError::TooDeep(limit));
+    }
+    Ok(())
+}
+
+fn len_size(len: usize) -> usize {
+    let bits = usize::BITS - len.leading_zeros();
+    (bits.max(1) as usize).div_ceil(7)
+}
+
+fn write_tagged(w: &mut impl Write, tag: u8, bytes: &[u8]) -> io::Result<()> {
+    w.write_all(&[tag])?;
+    w.write_all(bytes)
+}
+
+fn write_len(w: &mut impl Write, len: usize) -> io::Result<()> {
+    let mut len = len as u64;
+    loop {
+        let byte = (len & 0x7f) as u8;
+        len >>= 7;
+        if len == 0 {
+            return w.write_all(&[byte]);
+        }
+        w.write_all(&[byte | 0x80])?;
+    }
+}
+
+fn write_bytes(w: &mut impl Write, bytes: &[u8]) -> io::Result<()> {
+    write_len(w, bytes.len())?;
+    w.write_all(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_codec() -> Result<()> {
+        let map = Map::from([
+            (String::from("a"), Value::from(-1i8)),
+            (String::from("b"), Value::Bytes(Arc::from(vec![7u8; 300]))),
+        ]);
+        let value = Value::Array(vec![
+            Value::Null,
+            Value::from(true),
+            Value::from(u64::MAX),
+            Value::from(1.5f32),
+            Value::from("文本"),
+            Value::Map(map),
+        ]);
+        let buf = to_vec(&value)?;
+        assert_eq!(buf.len(), encoded_len(&value));
+        let back = from_slice(&buf)?;
+        assert_eq!(back, value);
+        assert!(
+            matches!(&back, Value::Array(vec) if matches!(vec[2], Value::Number(Number::U64(_))))
+        );
+
+        // 流中的多个值
+        let mut encoder = Encoder::new(Vec::new());
+        encoder.write(&value)?;
+        encoder.write(&Value::from(1u16))?;
+        let stream = encoder.into_inner();
+        let mut decoder = Decoder::new(stream.as_slice());
+        assert_eq!(decoder.read()?, Some(value.clone()));
+        assert_eq!(decoder.read()?, Some(Value::from(1u16)));
+        assert!(decoder.read()?.is_none());
+
+        let limits = Limits::default().with_max_depth(1);
+        let mut decoder = Decoder::new(buf.as_slice()).with_limits(limits);
+        assert!(matches!(decoder.read(), Err(CodecError::TooDeep(1))));
+        let limits = Limits::default().with_max_size(100);
+        let mut decoder = Decoder::new(buf.as_slice()).with_limits(limits);
+        assert!(matches!(decoder.read(), Err(CodecError::TooLarge(100))));
+        assert!(
+            Encoder::new(Vec::new())
+                .with_limits(limits)
+                .write(&value)
+                .is_err()
+        );
+
+        // 声明的长度超出数据时不分配
+        assert!(matches!(
+            from_slice(&[BYTES, 0xff, 0xff, 0xff, 0x7f]),
+            Err(CodecError::TooLarge(_))
+        ));
+        assert!(matches!(from_slice(&[0x7f]), Err(CodecError::Tag(0x7f))));
+        assert!(matches!(
+            from_slice(&[NULL, NULL]),
+            Err(CodecError::Trailing(1))
+        ));
+        Ok(())
+    }
+}
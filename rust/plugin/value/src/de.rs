@@ -0,0 +1,402 @@
+use serde::de::{
+    self, Deserialize, DeserializeOwned, DeserializeSeed, Deserializer, EnumAccess, Error,
+    IntoDeserializer, MapAccess, SeqAccess, VariantAccess, Visitor,
+};
+use std::{fmt, sync::Arc};
+
+use crate::{Map, Number, Value, ValueParseError};
+
+///
+/// 从 [`Value`] 还原实现了 `Deserialize` 的类型
+///
+/// 数字可转为范围内的任意数字类型; [`Value::Bytes`] 可还原为 `Vec<u8>` 等序列;
+/// 枚举接受变体名或 `{变体名: 内容}`, 与 [`crate::to_value`] 对应
+pub fn from_value<T: DeserializeOwned>(value: Value) -> Result<T, crate::Error> {
+    T::deserialize(value)
+}
+
+///
+/// 与 [`from_value`] 相同, 失败时返回 [`ValueParseError`]: 期望类型为 `T` 的类型名, 原因为 serde 的错误信息
+pub fn try_from_value<T: DeserializeOwned>(value: Value) -> Result<T, ValueParseError> {
+    let actual = value.type_name();
+    from_value(value).map_err(|e| {
+        ValueParseError::new(std::any::type_name::<T>(), actual).with_message(e.to_string())
+    })
+}
+
+///
+/// 从普通的 json 结构还原, json 中的整数为 [`Number::I64`](超出范围时为 [`Number::U64`]),
+/// 小数为 [`Number::F64`]; 能区分数字类型的格式按原类型还原
+impl<'de> Deserialize<'de> for Value {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(ValueVisitor)
+    }
+}
+
+impl<'de> Deserialize<'de> for Number {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        match Value::deserialize(deserializer)? {
+            Value::Number(n) => Ok(n),
+            other => Err(D::Error::custom(format!("expected number, got {other:?}"))),
+        }
+    }
+}
+
+struct ValueVisitor;
+
+macro_rules! visit_number {
+    ($( $fun:ident => $ty:ty, $variant:ident ),* $(,)?) => {
+        $(
+            fn $fun<E: Error>(self, v: $ty) -> Result<Value, E> {
+                Ok(Value::Number(Number::$variant(v)))
+            }
+        )*
+    };
+}
+
+impl<'de> Visitor<'de> for ValueVisitor {
+    type Value = Value;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "any value")
+    }
+
+    visit_number!(
+        visit_i8 => i8, I8,
+        visit_i16 => i16, I16,
+        visit_i32 => i32, I32,
+        visit_i64 => i64, I64,
+        visit_u8 => u8, U8,
+        visit_u16 => u16, U16,
+        visit_u32 => u32, U32,
+        visit_f32 => f32, F32,
+        visit_f64 => f64, F64,
+    );
+
+    fn visit_u64<E: Error>(self, v: u64) -> Result<Value, E> {
+        // json 中的非负整数都以 u64 给出, 能表示时统一为 i64
+        Ok(Value::Number(match i64::try_from(v) {
+            Ok(v) => Number::I64(v),
+            Err(_) => Number::U64(v),
+        }))
+    }
+
+    fn visit_bool<E: Error>(self, v: bool) -> Result<Value, E> {
+        Ok(Value::Bool(v))
+    }
+
+    fn visit_str<E: Error>(self, v: &str) -> Result<Value, E> {
+        Ok(Value::String(Arc::from(v)))
+    }
+
+    fn visit_bytes<E: Error>(self, v: &[u8]) -> Result<Value, E> {
+        Ok(Value::Bytes(Arc::from(v)))
+    }
+
+    fn visit_unit<E: Error>(self) -> Result<Value, E> {
+        Ok(Value::Null)
+    }
+
+    fn visit_none<E: Error>(self) -> Result<Value, E> {
+        Ok(Value::Null)
+    }
+
+    fn visit_some<D: Deserializer<'de>>(self, deserializer: D) -> Result<Value, D::Error> {
+        Value::deserialize(deserializer)
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Value, A::Error> {
+        let mut vec = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+        while let Some(v) = seq.next_element()? {
+            vec.push(v);
+        }
+        Ok(Value::Array(vec))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut access: A) -> Result<Value, A::Error> {
+        let mut map = Map::with_capacity(access.size_hint().unwrap_or(0));
+        while let Some((k, v)) = access.next_entry::<String, Value>()? {
+            map.insert(k, v);
+        }
+        Ok(Value::Map(map))
+    }
+}
+
+impl<'de> Deserializer<'de> for Value {
+    type Error = crate::Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, crate::Error> {
+        match self {
+            Value::Null => visitor.visit_unit(),
+            Value::Bool(b) => visitor.visit_bool(b),
+            Value::Number(n) => match n {
+                Number::I8(n) => visitor.visit_i8(n),
+                Number::I16(n) => visitor.visit_i16(n),
+                Number::I32(n) => visitor.visit_i32(n),
+                Number::I64(n) => visitor.visit_i64(n),
+                Number::U8(n) => visitor.visit_u8(n),
+                Number::U16(n) => visitor.visit_u16(n),
+                Number::U32(n) => visitor.visit_u32(n),
+                Number::U64(n) => visitor.visit_u64(n),
+                Number::F32(n) => visitor.visit_f32(n),
+                Number::F64(n) => visitor.visit_f64(n),
+            },
+            Value::String(s) => visitor.visit_str(&s),
+            Value::Bytes(b) => visitor.visit_bytes(&b),
+            Value::Array(vec) => visitor.visit_seq(SeqDeserializer(vec.into_iter())),
+            Value::Map(map) => visitor.visit_map(MapDeserializer {
+                iter: map.into_iter(),
+                value: None,
+            }),
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, crate::Error> {
+        match self {
+            Value::Null => visitor.visit_none(),
+            value => visitor.visit_some(value),
+        }
+    }
+
+    /// 二进制数据按 `u8` 序列还原
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, crate::Error> {
+        match self {
+            Value::Bytes(b) => visitor.visit_seq(SeqDeserializer(
+                b.iter()
+                    .map(|b| Value::from(*b))
+                    .collect::<Vec<_>>()
+                    .into_iter(),
+            )),
+            value => value.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(
+        self,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, crate::Error> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, crate::Error> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, crate::Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, crate::Error> {
+        let (variant, value) = match self {
+            Value::String(s) => (s.to_string(), None),
+            Value::Map(map) if map.len() == 1 => {
+                let (k, v) = map.into_iter().next().expect("map has one entry");
+                (k, Some(v))
+            }
+            other => {
+                return Err(crate::Error::new(format!(
+                    "expected enum variant name or map with a single key, got {other:?}"
+                )));
+            }
+        };
+        visitor.visit_enum(EnumDeserializer { variant, value })
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf unit unit_struct map struct identifier ignored_any
+    }
+}
+
+impl IntoDeserializer<'_, crate::Error> for Value {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self {
+        self
+    }
+}
+
+struct SeqDeserializer(std::vec::IntoIter<Value>);
+
+impl<'de> SeqAccess<'de> for SeqDeserializer {
+    type Error = crate::Error;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>, crate::Error> {
+        self.0.next().map(|v| seed.deserialize(v)).transpose()
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.0.len())
+    }
+}
+
+struct MapDeserializer {
+    iter: indexmap::map::IntoIter<String, Value>,
+    value: Option<Value>,
+}
+
+impl<'de> MapAccess<'de> for MapDeserializer {
+    type Error = crate::Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, crate::Error> {
+        match self.iter.next() {
+            Some((k, v)) => {
+                self.value = Some(v);
+                seed.deserialize(Value::String(Arc::from(k))).map(Some)
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(
+        &mut self,
+        seed: V,
+    ) -> Result<V::Value, crate::Error> {
+        let value = self
+            .value
+            .take()
+            .ok_or_else(|| crate::Error::new("next_value called before next_key"))?;
+        seed.deserialize(value)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.iter.len())
+    }
+}
+
+struct EnumDeserializer {
+    variant: String,
+    value: Option<Value>,
+}
+
+impl<'de> EnumAccess<'de> for EnumDeserializer {
+    type Error = crate::Error;
+    type Variant = VariantDeserializer;
+
+    fn variant_seed<V: DeserializeSeed<'de>>(
+        self,
+        seed: V,
+    ) -> Result<(V::Value, VariantDeserializer), crate::Error> {
+        let variant = seed.deserialize(Value::String(Arc::from(self.variant)))?;
+        Ok((variant, VariantDeserializer(self.value)))
+    }
+}
+
+struct VariantDeserializer(Option<Value>);
+
+impl<'de> VariantAccess<'de> for VariantDeserializer {
+    type Error = crate::Error;
+
+    fn unit_variant(self) -> Result<(), crate::Error> {
+        match self.0 {
+            None | Some(Value::Null) => Ok(()),
+            Some(other) => Err(de::Error::invalid_type(unexpected(&other), &"unit variant")),
+        }
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(
+        self,
+        seed: T,
+    ) -> Result<T::Value, crate::Error> {
+        seed.deserialize(self.0.unwrap_or(Value::Null))
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(
+        self,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, crate::Error> {
+        self.0.unwrap_or(Value::Null).deserialize_seq(visitor)
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, crate::Error> {
+        self.0.unwrap_or(Value::Null).deserialize_any(visitor)
+    }
+}
+
+fn unexpected(value: &Value) -> de::Unexpected<'_> {
+    match value {
+        Value::Null => de::Unexpected::Unit,
+        Value::Bool(b) => de::Unexpected::Bool(*b),
+        Value::Number(_) => de::Unexpected::Other("number"),
+        Value::String(s) => de::Unexpected::Str(s),
+        Value::Bytes(b) => de::Unexpected::Bytes(b),
+        Value::Array(_) => de::Unexpected::Seq,
+        Value::Map(_) => de::Unexpected::Map,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::to_value;
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    enum Kind {
+        File,
+        Dir(String),
+        Link { to: String },
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Entry {
+        size: u8,
+        data: Vec<u8>,
+        kinds: Vec<Kind>,
+        parent: Option<String>,
+    }
+
+    #[test]
+    fn test_value_serde() -> Result<(), crate::Error> {
+        let entry = Entry {
+            size: 3,
+            data: vec![1, 2],
+            kinds: vec![
+                Kind::File,
+                Kind::Dir(String::from("d")),
+                Kind::Link {
+                    to: String::from("t"),
+                },
+            ],
+            parent: None,
+        };
+        let value = to_value(&entry)?;
+        let map = value.as_map().unwrap();
+        assert_eq!(map["size"], Value::from(3u8));
+        assert_eq!(map["parent"], Value::Null);
+        assert_eq!(from_value::<Entry>(value)?, entry);
+
+        // 范围内的数字和二进制数据可转为其他类型
+        assert_eq!(from_value::<u8>(Value::from(5i64))?, 5);
+        assert!(from_value::<u8>(Value::from(300i64)).is_err());
+        let bytes = Value::Bytes(Arc::from(&[1u8, 2][..]));
+        assert_eq!(from_value::<Vec<u8>>(bytes)?, vec![1, 2]);
+        Ok(())
+    }
+}
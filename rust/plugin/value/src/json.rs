@@ -0,0 +1,61 @@
+//! 与 `serde_json::Value` 的转换, 需开启 `json` 特性
+use std::sync::Arc;
+
+use crate::{Number, Value};
+
+///
+/// json 中的整数为 [`Number::I64`](超出范围时为 [`Number::U64`]), 小数为 [`Number::F64`]
+impl From<serde_json::Value> for Value {
+    fn from(value: serde_json::Value) -> Self {
+        match value {
+            serde_json::Value::Null => Value::Null,
+            serde_json::Value::Bool(b) => Value::Bool(b),
+            serde_json::Value::Number(n) => Value::Number(if let Some(n) = n.as_i64() {
+                Number::I64(n)
+            } else if let Some(n) = n.as_u64() {
+                Number::U64(n)
+            } else {
+                Number::F64(n.as_f64().unwrap_or(f64::NAN))
+            }),
+            serde_json::Value::String(s) => Value::String(Arc::from(s)),
+            serde_json::Value::Array(vec) => {
+                Value::Array(vec.into_iter().map(Value::from).collect())
+            }
+            serde_json::Value::Object(map) => {
+                Value::Map(map.into_iter().map(|(k, v)| (k, Value::from(v))).collect())
+            }
+        }
+    }
+}
+
+///
+/// 二进制数据转为数字数组, json 不能表示的 NaN 和无穷转为 null
+impl From<Value> for serde_json::Value {
+    fn from(value: Value) -> Self {
+        match value {
+            Value::Null => serde_json::Value::Null,
+            Value::Bool(b) => serde_json::Value::Bool(b),
+            Value::Number(n) => match n {
+                Number::I8(n) => n.into(),
+                Number::I16(n) => n.into(),
+                Number::I32(n) => n.into(),
+                Number::I64(n) => n.into(),
+                Number::U8(n) => n.into(),
+                Number::U16(n) => n.into(),
+                Number::U32(n) => n.into(),
+                Number::U64(n) => n.into(),
+                // f32 先转为十进制文本, 避免 1.1f32 变为 1.100000023841858
+                Number::F32(n) => n.to_string().parse::<f64>().unwrap_or(f64::NAN).into(),
+                Number::F64(n) => n.into(),
+            },
+            Value::String(s) => serde_json::Value::String(s.to_string()),
+            Value::Bytes(b) => b.iter().copied().collect(),
+            Value::Array(vec) => vec.into_iter().map(serde_json::Value::from).collect(),
+            Value::Map(map) => serde_json::Value::Object(
+                map.into_iter()
+                    .map(|(k, v)| (k, serde_json::Value::from(v)))
+                    .collect(),
+            ),
+        }
+    }
+}
@@ -1,5 +1,23 @@
 mod value;
 mod num;
+mod error;
+mod ser;
+mod de;
+#[cfg(feature = "json")]
+mod json;
+pub mod typed;
+pub mod binary;
+mod path;
+mod patch;
+mod schema;
+pub mod text;
 
 pub use value::*;
-pub use num::*;
\ No newline at end of file
+pub use num::*;
+pub use path::{PathError, PathErrorKind, PathQuery};
+pub use patch::{Operation, Patch, PatchError, PatchErrorKind, render_diff};
+pub use schema::{Describe, Field, Schema, Violation, ViolationKind};
+pub use text::TextError;
+pub use error::Error;
+pub use ser::to_value;
+pub use de::{from_value, try_from_value};
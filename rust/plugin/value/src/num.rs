@@ -1,4 +1,8 @@
-#[derive(Clone, PartialEq)]
+///
+/// 数字, 保留原始的数字类型
+///
+/// 比较时按数值比较, 与类型无关: `I8(1) == U64(1)`, `F64(1.0) == I32(1)`; NaN 与任何数都不相等
+#[derive(Clone)]
 pub enum Number {
     I8(i8),
     I16(i16),
@@ -43,24 +47,14 @@ macro_rules! number_conversions {
             // 从原始类型到 Number 的转换
             impl From<$ty> for Number { fn from(val: $ty) -> Self { Number::$variant(val) } }
 
-            // 从 Number 到原始类型的 TryFrom 转换
+            // 从 Number 到原始类型的 TryFrom 转换, 数值不能无损表示时失败
             impl TryFrom<Number> for $ty {
                 type Error = crate::ValueParseError;
                 fn try_from(value: Number) -> Result<Self, Self::Error> {
-                    match value {
-                        Number::$variant(num) => Ok(num),
-                        _ => Err(crate::ValueParseError),
-                    }
-                }
-            }
-
-            // 为 Number 添加访问方法
-            impl Number {
-                pub fn $fun(&self) -> Option<$ty> {
-                    match self {
-                        Number::$variant(num) => Some(*num),
-                        _ => None,
-                    }
+                    value.$fun().ok_or_else(|| {
+                        crate::ValueParseError::new(stringify!($ty), "number")
+                            .with_message(format!("{value} cannot be converted losslessly"))
+                    })
                 }
             }
         )*
@@ -79,3 +73,253 @@ number_conversions!(
     F32=> f32, as_f32,
     F64=> f64, as_f64,
 );
+
+macro_rules! int_accessors {
+    ($( $fun:ident => $ty:ty ),* $(,)?) => {
+        impl Number {
+            $(
+                ///
+                /// 整数且能无损表示时返回, 与原类型无关
+                pub fn $fun(&self) -> Option<$ty> {
+                    self.as_int().and_then(|n| <$ty>::try_from(n).ok())
+                }
+            )*
+        }
+    };
+}
+
+int_accessors!(
+    as_i8 => i8,
+    as_i16 => i16,
+    as_i32 => i32,
+    as_i64 => i64,
+    as_u8 => u8,
+    as_u16 => u16,
+    as_u32 => u32,
+    as_u64 => u64,
+);
+
+/// f64 能精确表示的最大整数
+const F64_EXACT: i128 = 1 << f64::MANTISSA_DIGITS;
+/// f32 能精确表示的最大整数
+const F32_EXACT: i128 = 1 << f32::MANTISSA_DIGITS;
+
+impl Number {
+    pub fn is_integer(&self) -> bool {
+        self.as_int().is_some()
+    }
+
+    pub fn is_float(&self) -> bool {
+        matches!(self, Number::F32(_) | Number::F64(_))
+    }
+
+    ///
+    /// 小数, 或绝对值不超过 2^53 的整数
+    pub fn as_f64(&self) -> Option<f64> {
+        match *self {
+            Number::F32(n) => Some(n as f64),
+            Number::F64(n) => Some(n),
+            _ => self
+                .as_int()
+                .filter(|n| n.abs() <= F64_EXACT)
+                .map(|n| n as f64),
+        }
+    }
+
+    ///
+    /// 能无损转为 f32 的小数, 或绝对值不超过 2^24 的整数
+    pub fn as_f32(&self) -> Option<f32> {
+        match *self {
+            Number::F32(n) => Some(n),
+            Number::F64(n) if n.is_nan() || (n as f32) as f64 == n => Some(n as f32),
+            Number::F64(_) => None,
+            _ => self
+                .as_int()
+                .filter(|n| n.abs() <= F32_EXACT)
+                .map(|n| n as f32),
+        }
+    }
+
+    pub fn checked_add(&self, rhs: &Number) -> Option<Number> {
+        self.checked(rhs, i128::checked_add, |a, b| a + b)
+    }
+
+    pub fn checked_sub(&self, rhs: &Number) -> Option<Number> {
+        self.checked(rhs, i128::checked_sub, |a, b| a - b)
+    }
+
+    pub fn checked_mul(&self, rhs: &Number) -> Option<Number> {
+        self.checked(rhs, i128::checked_mul, |a, b| a * b)
+    }
+
+    ///
+    /// 整数除法向零取整; 除数为 0 时返回 `None`
+    pub fn checked_div(&self, rhs: &Number) -> Option<Number> {
+        self.checked(rhs, i128::checked_div, |a, b| a / b)
+    }
+
+    pub fn checked_rem(&self, rhs: &Number) -> Option<Number> {
+        self.checked(rhs, i128::checked_rem, |a, b| a % b)
+    }
+
+    fn as_int(&self) -> Option<i128> {
+        match *self {
+            Number::I8(n) => Some(n.into()),
+            Number::I16(n) => Some(n.into()),
+            Number::I32(n) => Some(n.into()),
+            Number::I64(n) => Some(n.into()),
+            Number::U8(n) => Some(n.into()),
+            Number::U16(n) => Some(n.into()),
+            Number::U32(n) => Some(n.into()),
+            Number::U64(n) => Some(n.into()),
+            Number::F32(_) | Number::F64(_) => None,
+        }
+    }
+
+    fn as_float(&self) -> f64 {
+        match *self {
+            Number::F32(n) => n as f64,
+            Number::F64(n) => n,
+            _ => self.as_int().unwrap_or_default() as f64,
+        }
+    }
+
+    ///
+    /// 与 `self` 同类型的整数, 超出范围时返回 `None`
+    fn int_like(&self, n: i128) -> Option<Number> {
+        Some(match self {
+            Number::I8(_) => Number::I8(n.try_into().ok()?),
+            Number::I16(_) => Number::I16(n.try_into().ok()?),
+            Number::I32(_) => Number::I32(n.try_into().ok()?),
+            Number::I64(_) => Number::I64(n.try_into().ok()?),
+            Number::U8(_) => Number::U8(n.try_into().ok()?),
+            Number::U16(_) => Number::U16(n.try_into().ok()?),
+            Number::U32(_) => Number::U32(n.try_into().ok()?),
+            Number::U64(_) => Number::U64(n.try_into().ok()?),
+            Number::F32(_) | Number::F64(_) => return None,
+        })
+    }
+
+    ///
+    /// 同类型的整数结果仍为该类型; 不同类型的整数结果为 `I64`, 超出时为 `U64`;
+    /// 有小数时结果为 `F64`, 都是 `F32` 时为 `F32`. 溢出或结果不是有限数时返回 `None`
+    fn checked(
+        &self,
+        rhs: &Number,
+        int: fn(i128, i128) -> Option<i128>,
+        float: fn(f64, f64) -> f64,
+    ) -> Option<Number> {
+        if let (Some(a), Some(b)) = (self.as_int(), rhs.as_int()) {
+            let n = int(a, b)?;
+            return if std::mem::discriminant(self) == std::mem::discriminant(rhs) {
+                self.int_like(n)
+            } else {
+                Number::I64(0)
+                    .int_like(n)
+                    .or_else(|| Number::U64(0).int_like(n))
+            };
+        }
+        let n = float(self.as_float(), rhs.as_float());
+        if !n.is_finite() {
+            return None;
+        }
+        Some(match (self, rhs) {
+            (Number::F32(_), Number::F32(_)) => Number::F32(n as f32),
+            _ => Number::F64(n),
+        })
+    }
+}
+
+impl PartialEq for Number {
+    fn eq(&self, other: &Self) -> bool {
+        self.partial_cmp(other) == Some(std::cmp::Ordering::Equal)
+    }
+}
+
+impl PartialOrd for Number {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        match (self.as_int(), other.as_int()) {
+            (Some(a), Some(b)) => Some(a.cmp(&b)),
+            (Some(a), None) => cmp_int_float(a, other.as_float()),
+            (None, Some(b)) => cmp_int_float(b, self.as_float()).map(|o| o.reverse()),
+            (None, None) => self.as_float().partial_cmp(&other.as_float()),
+        }
+    }
+}
+
+/// 精确比较整数和小数, 不经过可能损失精度的转换
+fn cmp_int_float(i: i128, f: f64) -> Option<std::cmp::Ordering> {
+    use std::cmp::Ordering;
+    const LIMIT: f64 = i128::MAX as f64;
+    if f.is_nan() {
+        return None;
+    }
+    if f >= LIMIT {
+        return Some(Ordering::Less);
+    }
+    if f < -LIMIT {
+        return Some(Ordering::Greater);
+    }
+    let trunc = f.trunc();
+    Some(
+        i.cmp(&(trunc as i128))
+            .then_with(|| 0f64.total_cmp(&(f - trunc))),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_number() {
+        assert_eq!(Number::I32(5).as_i64(), Some(5));
+        assert_eq!(Number::U8(5).as_i8(), Some(5));
+        assert_eq!(Number::I16(-1).as_u64(), None);
+        assert_eq!(Number::U64(u64::MAX).as_i64(), None);
+        assert_eq!(Number::F64(1.0).as_i64(), None);
+        assert_eq!(Number::I64(1 << 53).as_f64(), Some(9007199254740992.0));
+        assert_eq!(Number::I64((1 << 53) + 1).as_f64(), None);
+        assert_eq!(Number::F64(0.5).as_f32(), Some(0.5));
+        assert_eq!(Number::F64(0.1).as_f32(), None);
+        assert_eq!(i64::try_from(Number::U8(7)), Ok(7));
+        assert!(u8::try_from(Number::I32(300)).is_err());
+
+        assert_eq!(Number::I8(1), Number::U64(1));
+        assert_eq!(Number::F64(1.0), Number::I32(1));
+        assert_ne!(Number::F64(f64::NAN), Number::F64(f64::NAN));
+        assert!(Number::I8(-1) < Number::U64(0));
+        assert!(Number::F32(0.5) < Number::U8(1));
+        assert!(Number::I64(i64::MAX) > Number::F64(9.2e18));
+        assert!(Number::U64(u64::MAX) < Number::F64(1e300));
+
+        assert!(matches!(
+            Number::U8(200).checked_add(&Number::U8(50)),
+            Some(Number::U8(250))
+        ));
+        assert_eq!(Number::U8(200).checked_add(&Number::U8(56)), None);
+        assert!(matches!(
+            Number::U8(200).checked_add(&Number::I8(56)),
+            Some(Number::I64(256))
+        ));
+        assert!(
+            Number::I64(-1)
+                .checked_mul(&Number::U64(u64::MAX))
+                .is_none()
+        );
+        assert!(matches!(
+            Number::F32(1.5).checked_mul(&Number::F32(2.0)),
+            Some(Number::F32(3.0))
+        ));
+        assert!(matches!(
+            Number::I32(1).checked_div(&Number::F64(4.0)),
+            Some(Number::F64(0.25))
+        ));
+        assert_eq!(Number::I32(1).checked_div(&Number::I32(0)), None);
+        assert_eq!(Number::F64(1.0).checked_div(&Number::F64(0.0)), None);
+        assert_eq!(
+            Number::I32(7).checked_rem(&Number::I32(4)),
+            Some(Number::I32(3))
+        );
+    }
+}
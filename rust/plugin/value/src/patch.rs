@@ -0,0 +1,388 @@
+//! 合并、比较和补丁
+//!
+//! - [`Value::merge_patch`]: RFC 7386 merge-patch, 补丁中的 `null` 表示删除
+//! - [`Value::diff`]: 比较两个值, 得到 RFC 6902 形式的 [`Patch`]
+//! - [`Value::apply`]: 应用 [`Patch`], 任一操作失败(包括 `test` 不成立)时不做任何修改
+//! - [`render_diff`]: 逐行列出两个值的差异, 用于测试断言, 见 [`crate::assert_value_eq`]
+//!
+//! [`Patch`] 序列化后即为 RFC 6902 的 json 格式:
+//! ```json
+//! [{ "op": "replace", "path": "/files/dev/size", "value": 11 }]
+//! ```
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+use crate::{Map, PathError, Value, path::escape};
+
+///
+/// RFC 6902 的补丁操作, 路径为 JSON Pointer
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "lowercase")]
+pub enum Operation {
+    /// 数组中为插入, `-` 表示追加
+    Add {
+        path: String,
+        value: Value,
+    },
+    Remove {
+        path: String,
+    },
+    /// 路径必须已存在
+    Replace {
+        path: String,
+        value: Value,
+    },
+    Move {
+        from: String,
+        path: String,
+    },
+    Copy {
+        from: String,
+        path: String,
+    },
+    /// 路径上的值必须等于 `value`, 否则整个补丁失败
+    Test {
+        path: String,
+        value: Value,
+    },
+}
+
+///
+/// 按顺序应用的一组操作
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Patch(pub Vec<Operation>);
+
+///
+/// 应用补丁的错误, `index` 为失败的操作在补丁中的序号
+#[derive(Debug, Clone, PartialEq)]
+pub struct PatchError {
+    pub index: usize,
+    pub kind: PatchErrorKind,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum PatchErrorKind {
+    Path(PathError),
+    /// `test` 操作不成立, 值为路径
+    Test(String),
+    /// `move` 的目标在来源之内
+    MoveIntoSelf(String),
+}
+
+impl fmt::Display for PatchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "patch operation {} failed: ", self.index)?;
+        match &self.kind {
+            PatchErrorKind::Path(e) => write!(f, "{e}"),
+            PatchErrorKind::Test(path) => write!(f, "test at {path} does not hold"),
+            PatchErrorKind::MoveIntoSelf(path) => write!(f, "cannot move {path} into itself"),
+        }
+    }
+}
+
+impl std::error::Error for PatchError {}
+
+impl Value {
+    ///
+    /// RFC 7386 merge-patch: 补丁为映射时逐键合并, 值为 `null` 的键被删除; 否则整体替换
+    pub fn merge_patch(&mut self, patch: &Value) {
+        let Value::Map(patch) = patch else {
+            *self = patch.clone();
+            return;
+        };
+        if !matches!(self, Value::Map(_)) {
+            *self = Value::Map(Map::new());
+        }
+        let Value::Map(map) = self else {
+            unreachable!("self is a map");
+        };
+        for (key, value) in patch {
+            if value.is_null() {
+                map.shift_remove(key);
+            } else {
+                map.entry(key.clone()).or_default().merge_patch(value);
+            }
+        }
+    }
+
+    ///
+    /// 得到把 `self` 变为 `to` 的补丁; 数字类型不同也视为不同
+    pub fn diff(&self, to: &Value) -> Patch {
+        let mut ops = Vec::new();
+        diff_into(String::new(), self, to, &mut ops);
+        Patch(ops)
+    }
+
+    ///
+    /// 按顺序应用补丁, 失败时 `self` 保持不变
+    pub fn apply(&mut self, patch: &Patch) -> Result<(), PatchError> {
+        let mut target = self.clone();
+        for (index, op) in patch.0.iter().enumerate() {
+            apply_one(&mut target, op).map_err(|kind| PatchError { index, kind })?;
+        }
+        *self = target;
+        Ok(())
+    }
+}
+
+fn apply_one(target: &mut Value, op: &Operation) -> Result<(), PatchErrorKind> {
+    match op {
+        Operation::Add { path, value } => target.insert(path, value.clone())?,
+        Operation::Remove { path } => {
+            target.remove(path)?;
+        }
+        Operation::Replace { path, value } => {
+            // 原地替换, 不改变键在映射中的位置
+            match target.get_mut(path) {
+                Some(slot) => *slot = value.clone(),
+                None => return Err(missing(target, path)),
+            }
+        }
+        Operation::Move { from, path } => {
+            if path.starts_with(from.as_str()) && path[from.len()..].starts_with('/') {
+                return Err(PatchErrorKind::MoveIntoSelf(from.clone()));
+            }
+            if from != path {
+                let value = target.remove(from)?;
+                target.insert(path, value)?;
+            }
+        }
+        Operation::Copy { from, path } => {
+            let value = target
+                .get(from)
+                .cloned()
+                .ok_or_else(|| missing(target, from))?;
+            target.insert(path, value)?;
+        }
+        Operation::Test { path, value } => {
+            if target.get(path) != Some(value) {
+                return Err(PatchErrorKind::Test(path.clone()));
+            }
+        }
+    }
+    Ok(())
+}
+
+impl From<PathError> for PatchErrorKind {
+    fn from(value: PathError) -> Self {
+        Self::Path(value)
+    }
+}
+
+/// 路径不存在时的错误, 由 [`Value::remove`] 在副本上给出具体的段
+fn missing(target: &Value, path: &str) -> PatchErrorKind {
+    match target.clone().remove(path) {
+        Err(e) => PatchErrorKind::Path(e),
+        Ok(_) => PatchErrorKind::Test(path.to_string()),
+    }
+}
+
+/// 类型和值都相同, 数字的类型不同也视为不同
+fn identical(a: &Value, b: &Value) -> bool {
+    match (a, b) {
+        (Value::Number(x), Value::Number(y)) => {
+            std::mem::discriminant(x) == std::mem::discriminant(y) && x == y
+        }
+        (Value::Array(x), Value::Array(y)) => {
+            x.len() == y.len() && x.iter().zip(y).all(|(a, b)| identical(a, b))
+        }
+        (Value::Map(x), Value::Map(y)) => {
+            x.len() == y.len()
+                && x.iter()
+                    .all(|(k, v)| y.get(k).is_some_and(|w| identical(v, w)))
+        }
+        _ => a == b,
+    }
+}
+
+fn diff_into(path: String, from: &Value, to: &Value, ops: &mut Vec<Operation>) {
+    match (from, to) {
+        (Value::Map(a), Value::Map(b)) => {
+            let keys = a.keys().chain(b.keys().filter(|k| !a.contains_key(*k)));
+            for key in keys {
+                let path = format!("{path}/{}", escape(key));
+                match (a.get(key), b.get(key)) {
+                    (Some(x), Some(y)) => diff_into(path, x, y, ops),
+                    (Some(_), None) => ops.push(Operation::Remove { path }),
+                    (None, Some(y)) => ops.push(Operation::Add {
+                        path,
+                        value: y.clone(),
+                    }),
+                    (None, None) => {}
+                }
+            }
+        }
+        (Value::Array(a), Value::Array(b)) => {
+            for (i, (x, y)) in a.iter().zip(b).enumerate() {
+                diff_into(format!("{path}/{i}"), x, y, ops);
+            }
+            // 从末尾删除, 前面的下标不受影响
+            for i in (b.len()..a.len()).rev() {
+                ops.push(Operation::Remove {
+                    path: format!("{path}/{i}"),
+                });
+            }
+            for (i, y) in b.iter().enumerate().skip(a.len()) {
+                ops.push(Operation::Add {
+                    path: format!("{path}/{i}"),
+                    value: y.clone(),
+                });
+            }
+        }
+        _ if !identical(from, to) => ops.push(Operation::Replace {
+            path,
+            value: to.clone(),
+        }),
+        _ => {}
+    }
+}
+
+///
+/// 逐行列出从 `from` 到 `to` 的差异: `+` 新增, `-` 删除, `~` 修改; 值为 [`crate::text`] 格式, 相同时为空字符串
+pub fn render_diff(from: &Value, to: &Value) -> String {
+    let mut out = String::new();
+    for op in from.diff(to).0 {
+        let line = match op {
+            Operation::Add { path, value } => format!("+ {}: {value}", root(&path)),
+            Operation::Remove { path } => {
+                let old = from.get(&path).map(Value::to_string).unwrap_or_default();
+                format!("- {}: {old}", root(&path))
+            }
+            Operation::Replace { path, value } => {
+                let old = from.get(&path).map(Value::to_string).unwrap_or_default();
+                format!("~ {}: {old} -> {value}", root(&path))
+            }
+            other => format!("{other:?}"),
+        };
+        out.push_str(&line);
+        out.push('\n');
+    }
+    out
+}
+
+fn root(path: &str) -> &str {
+    if path.is_empty() { "/" } else { path }
+}
+
+///
+/// 比较两个 [`Value`], 不相等时 panic 并列出差异
+#[macro_export]
+macro_rules! assert_value_eq {
+    ($left:expr, $right:expr $(,)?) => {
+        match (&$left, &$right) {
+            (left, right) => {
+                if left != right {
+                    panic!(
+                        "values are not equal, diff from left to right:\n{}",
+                        $crate::render_diff(left, right)
+                    );
+                }
+            }
+        }
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn value(json: &str) -> Value {
+        serde_json::from_str(json).unwrap()
+    }
+
+    #[test]
+    fn test_merge_patch() {
+        let mut target = value(r#"{"a": "b", "c": {"d": "e", "f": "g"}}"#);
+        target.merge_patch(&value(r#"{"a": "z", "c": {"f": null}}"#));
+        assert_eq!(target, value(r#"{"a": "z", "c": {"d": "e"}}"#));
+        target.merge_patch(&value(r#"[1]"#));
+        assert_eq!(target, value(r#"[1]"#));
+    }
+
+    #[test]
+    fn test_diff_apply() {
+        let from = value(r#"{"a": 1, "b": [1, 2, 3], "c": {"d": true}}"#);
+        let mut to = value(r#"{"a": 2, "b": [1, 4], "e": null}"#);
+        to.set("/c", Value::Bytes(vec![1u8].into())).unwrap();
+
+        let patch = from.diff(&to);
+        let mut applied = from.clone();
+        applied.apply(&patch).unwrap();
+        assert_eq!(applied, to);
+        // 替换不改变键的顺序, 新增的键在末尾
+        let keys: Vec<_> = applied.as_map().unwrap().keys().collect();
+        assert_eq!(keys, ["a", "b", "c", "e"]);
+        assert!(to.diff(&to).0.is_empty());
+        // 类型不同的数字
+        assert_eq!(Value::from(1u8).diff(&Value::from(1i64)).0.len(), 1);
+
+        let json = serde_json::to_value(&patch).unwrap();
+        assert_eq!(
+            json[0],
+            serde_json::json!({"op": "replace", "path": "/a", "value": 2})
+        );
+        assert_eq!(
+            serde_json::from_value::<Patch>(json).unwrap().0.len(),
+            patch.0.len()
+        );
+
+        // test 不成立时不做任何修改
+        let mut target = from.clone();
+        let patch = Patch(vec![
+            Operation::Remove {
+                path: String::from("/a"),
+            },
+            Operation::Test {
+                path: String::from("/b/0"),
+                value: Value::from(9i64),
+            },
+        ]);
+        let err = target.apply(&patch).unwrap_err();
+        assert_eq!(
+            (err.index, err.kind),
+            (1, PatchErrorKind::Test(String::from("/b/0")))
+        );
+        assert_eq!(target, from);
+
+        let patch = Patch(vec![
+            Operation::Add {
+                path: String::from("/b/0"),
+                value: Value::from(0i64),
+            },
+            Operation::Copy {
+                from: String::from("/c"),
+                path: String::from("/f"),
+            },
+            Operation::Move {
+                from: String::from("/a"),
+                path: String::from("/g"),
+            },
+            Operation::Test {
+                path: String::from("/b"),
+                value: value("[0, 1, 2, 3]"),
+            },
+        ]);
+        target.apply(&patch).unwrap();
+        assert_eq!(target.get("/f/d"), Some(&Value::Bool(true)));
+        assert!(target.get("/a").is_none());
+        let err = target
+            .apply(&Patch(vec![Operation::Move {
+                from: String::from("/c"),
+                path: String::from("/c/x"),
+            }]))
+            .unwrap_err();
+        assert_eq!(err.kind, PatchErrorKind::MoveIntoSelf(String::from("/c")));
+    }
+
+    #[test]
+    fn test_render_diff() {
+        let from = value(r#"{"a": 1, "b": [1, 2]}"#);
+        let to = value(r#"{"a": "x", "b": [1], "c": {"d": null}}"#);
+        assert_eq!(
+            render_diff(&from, &to),
+            "~ /a: 1i64 -> \"x\"\n- /b/1: 2i64\n+ /c: {\"d\": null}\n"
+        );
+        assert_value_eq!(from, from.clone());
+    }
+}
@@ -0,0 +1,720 @@
+//! 按路径访问和修改 [`Value`]
+//!
+//! 路径为 JSON Pointer 形式, 如 `/files/dev/url`, 空字符串表示根; 键中的 `~` 和 `/`
+//! 分别写作 `~0` 和 `~1`, 数组用下标访问, 设置时 `-` 表示追加到末尾
+//!
+//! 查询([`PathQuery`])在路径的基础上支持:
+//! - `*`: 映射的所有值或数组的所有元素
+//! - `**`: 当前节点及其所有后代
+//! - `[条件]`: 跟在段后过滤选中的节点, 如 `[enabled]`(存在)、`[size>=10]`、
+//!   `[/meta/name="a"]`; 比较符为 `= != < <= > >=`, 右侧为数字、字符串、`true`、`false` 或 `null`
+//!
+//! ```ignore
+//! let url = value.get("/files/dev/url");
+//! value.set_create("/files/dev/size", 10u64)?;
+//! let large = value.query("/files/*[size>1024]/url")?;
+//! ```
+use std::{cmp::Ordering, fmt, sync::Arc};
+
+use crate::{Map, Number, Value};
+
+///
+/// 路径错误, 包含出错的段及到该段为止的路径
+#[derive(Debug, Clone, PartialEq)]
+pub struct PathError {
+    /// 到出错的段为止的路径
+    pub path: String,
+    pub segment: String,
+    pub kind: PathErrorKind,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum PathErrorKind {
+    /// 路径或查询的语法错误
+    Syntax(String),
+    /// 映射中没有该键
+    NotFound,
+    /// 不是数组下标, 或下标超出范围
+    Index,
+    /// 该段的上一级不是映射或数组, 值为实际的类型
+    NotContainer(&'static str),
+}
+
+impl fmt::Display for PathError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "path {}: segment `{}` ", self.path, self.segment)?;
+        match &self.kind {
+            PathErrorKind::Syntax(msg) => write!(f, "is invalid: {msg}"),
+            PathErrorKind::NotFound => write!(f, "not found"),
+            PathErrorKind::Index => write!(f, "is not a valid index"),
+            PathErrorKind::NotContainer(kind) => write!(f, "cannot index into {kind}"),
+        }
+    }
+}
+
+impl std::error::Error for PathError {}
+
+impl PathError {
+    fn new(segments: &[String], index: usize, kind: PathErrorKind) -> Self {
+        Self {
+            path: join(&segments[..=index]),
+            segment: segments[index].clone(),
+            kind,
+        }
+    }
+
+    /// `segments` 为未经解码的原始段
+    fn raw(segments: &[String], index: usize, kind: PathErrorKind) -> Self {
+        Self {
+            path: segments[..=index].iter().map(|s| format!("/{s}")).collect(),
+            segment: segments[index].clone(),
+            kind,
+        }
+    }
+}
+
+impl Value {
+    ///
+    /// 按路径取值, 路径无效或不存在时返回 `None`
+    pub fn get(&self, pointer: &str) -> Option<&Value> {
+        parse_pointer(pointer)
+            .ok()?
+            .iter()
+            .try_fold(self, |value, segment| value.child(segment))
+    }
+
+    pub fn get_mut(&mut self, pointer: &str) -> Option<&mut Value> {
+        parse_pointer(pointer)
+            .ok()?
+            .iter()
+            .try_fold(self, |value, segment| value.child_mut(segment))
+    }
+
+    ///
+    /// 设置路径上的值, 返回原来的值; 上一级必须存在
+    pub fn set(
+        &mut self,
+        pointer: &str,
+        value: impl Into<Value>,
+    ) -> Result<Option<Value>, PathError> {
+        self.set_with(pointer, value.into(), false)
+    }
+
+    ///
+    /// 设置路径上的值, 不存在的上级(或为 `null` 的上级)创建为映射
+    pub fn set_create(
+        &mut self,
+        pointer: &str,
+        value: impl Into<Value>,
+    ) -> Result<Option<Value>, PathError> {
+        self.set_with(pointer, value.into(), true)
+    }
+
+    ///
+    /// 移除并返回路径上的值, 不能移除根
+    pub fn remove(&mut self, pointer: &str) -> Result<Value, PathError> {
+        let segments = parse_pointer(pointer)?;
+        let Some((last, parents)) = segments.split_last() else {
+            return Err(PathError {
+                path: String::new(),
+                segment: String::new(),
+                kind: PathErrorKind::Syntax(String::from("cannot remove the root")),
+            });
+        };
+        let index = parents.len();
+        match self.walk_mut(&segments, parents.len(), false)? {
+            Value::Map(map) => map
+                .shift_remove(last)
+                .ok_or_else(|| PathError::new(&segments, index, PathErrorKind::NotFound)),
+            Value::Array(vec) => match parse_index(last) {
+                Some(i) if i < vec.len() => Ok(vec.remove(i)),
+                _ => Err(PathError::new(&segments, index, PathErrorKind::Index)),
+            },
+            other => Err(PathError::new(
+                &segments,
+                index,
+                PathErrorKind::NotContainer(other.type_name()),
+            )),
+        }
+    }
+
+    ///
+    /// 返回路径上的值, 不存在时创建: 上级创建为映射, 最后一级为 `null`;
+    /// 数组中 `-` 或等于长度的下标追加一个 `null`
+    pub fn entry(&mut self, pointer: &str) -> Result<&mut Value, PathError> {
+        let segments = parse_pointer(pointer)?;
+        let parent = self.walk_mut(&segments, segments.len().saturating_sub(1), true)?;
+        let Some(last) = segments.last() else {
+            return Ok(parent);
+        };
+        let index = segments.len() - 1;
+        if parent.is_null() {
+            *parent = Value::Map(Map::new());
+        }
+        match parent {
+            Value::Map(map) => Ok(map.entry(last.clone()).or_default()),
+            Value::Array(vec) => match append_index(last, vec.len()) {
+                Some(i) => {
+                    if i == vec.len() {
+                        vec.push(Value::Null);
+                    }
+                    Ok(&mut vec[i])
+                }
+                None => Err(PathError::new(&segments, index, PathErrorKind::Index)),
+            },
+            other => Err(PathError::new(
+                &segments,
+                index,
+                PathErrorKind::NotContainer(other.type_name()),
+            )),
+        }
+    }
+
+    ///
+    /// 查询所有匹配的节点, 返回各节点的路径和值; 映射按插入顺序遍历
+    pub fn query(&self, query: &str) -> Result<Vec<(String, &Value)>, PathError> {
+        Ok(PathQuery::parse(query)?.select(self))
+    }
+
+    fn child(&self, segment: &str) -> Option<&Value> {
+        match self {
+            Value::Map(map) => map.get(segment),
+            Value::Array(vec) => vec.get(parse_index(segment)?),
+            _ => None,
+        }
+    }
+
+    fn child_mut(&mut self, segment: &str) -> Option<&mut Value> {
+        match self {
+            Value::Map(map) => map.get_mut(segment),
+            Value::Array(vec) => vec.get_mut(parse_index(segment)?),
+            _ => None,
+        }
+    }
+
+    ///
+    /// 沿前 `depth` 段找到节点, `create` 时创建不存在的映射
+    fn walk_mut(
+        &mut self,
+        segments: &[String],
+        depth: usize,
+        create: bool,
+    ) -> Result<&mut Value, PathError> {
+        let mut current = self;
+        for (index, segment) in segments[..depth].iter().enumerate() {
+            if create && current.is_null() {
+                *current = Value::Map(Map::new());
+            }
+            current = match current {
+                Value::Map(map) => {
+                    if create {
+                        map.entry(segment.clone())
+                            .or_insert_with(|| Value::Map(Map::new()))
+                    } else {
+                        map.get_mut(segment).ok_or_else(|| {
+                            PathError::new(segments, index, PathErrorKind::NotFound)
+                        })?
+                    }
+                }
+                Value::Array(vec) => parse_index(segment)
+                    .and_then(|i| vec.get_mut(i))
+                    .ok_or_else(|| PathError::new(segments, index, PathErrorKind::Index))?,
+                other => {
+                    return Err(PathError::new(
+                        segments,
+                        index,
+                        PathErrorKind::NotContainer(other.type_name()),
+                    ));
+                }
+            };
+        }
+        Ok(current)
+    }
+
+    ///
+    /// 与 [`Value::set`] 相同, 但在数组的下标处插入而不是替换, 对应 RFC 6902 的 `add`
+    pub(crate) fn insert(&mut self, pointer: &str, value: Value) -> Result<(), PathError> {
+        let segments = parse_pointer(pointer)?;
+        let Some(last) = segments.last() else {
+            *self = value;
+            return Ok(());
+        };
+        let index = segments.len() - 1;
+        match self.walk_mut(&segments, index, false)? {
+            Value::Map(map) => {
+                map.insert(last.clone(), value);
+                Ok(())
+            }
+            Value::Array(vec) => match append_index(last, vec.len()) {
+                Some(i) => {
+                    vec.insert(i, value);
+                    Ok(())
+                }
+                None => Err(PathError::new(&segments, index, PathErrorKind::Index)),
+            },
+            other => Err(PathError::new(
+                &segments,
+                index,
+                PathErrorKind::NotContainer(other.type_name()),
+            )),
+        }
+    }
+
+    fn set_with(
+        &mut self,
+        pointer: &str,
+        value: Value,
+        create: bool,
+    ) -> Result<Option<Value>, PathError> {
+        let segments = parse_pointer(pointer)?;
+        let Some(last) = segments.last() else {
+            return Ok(Some(std::mem::replace(self, value)));
+        };
+        let index = segments.len() - 1;
+        let parent = self.walk_mut(&segments, index, create)?;
+        if create && parent.is_null() {
+            *parent = Value::Map(Map::new());
+        }
+        match parent {
+            Value::Map(map) => Ok(map.insert(last.clone(), value)),
+            Value::Array(vec) => match append_index(last, vec.len()) {
+                Some(i) if i == vec.len() => {
+                    vec.push(value);
+                    Ok(None)
+                }
+                Some(i) => Ok(Some(std::mem::replace(&mut vec[i], value))),
+                None => Err(PathError::new(&segments, index, PathErrorKind::Index)),
+            },
+            other => Err(PathError::new(
+                &segments,
+                index,
+                PathErrorKind::NotContainer(other.type_name()),
+            )),
+        }
+    }
+}
+
+///
+/// 解析后的查询, 可重复用于多个值
+#[derive(Debug, Clone, PartialEq)]
+pub struct PathQuery {
+    steps: Vec<Step>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Step {
+    selector: Selector,
+    filters: Vec<Filter>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Selector {
+    Key(String),
+    /// `*`
+    Children,
+    /// `**`
+    Descendants,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Filter {
+    /// 相对于被过滤节点的路径
+    path: Vec<String>,
+    test: Option<(Op, Value)>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Op {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl PathQuery {
+    pub fn parse(query: &str) -> Result<Self, PathError> {
+        let raw = split_query(query)?;
+        let mut steps = Vec::with_capacity(raw.len());
+        for index in 0..raw.len() {
+            let error =
+                |msg: &str| PathError::raw(&raw, index, PathErrorKind::Syntax(msg.to_string()));
+            let (name, filters) = match raw[index].find('[') {
+                Some(i) => raw[index].split_at(i),
+                None => (raw[index].as_str(), ""),
+            };
+            let selector = match name {
+                "*" => Selector::Children,
+                "**" => Selector::Descendants,
+                name => Selector::Key(unescape(name).ok_or_else(|| error("invalid escape"))?),
+            };
+            let filters = parse_filters(filters).map_err(|msg| error(&msg))?;
+            steps.push(Step { selector, filters });
+        }
+        Ok(Self { steps })
+    }
+
+    ///
+    /// 返回所有匹配的节点及其路径
+    pub fn select<'a>(&self, value: &'a Value) -> Vec<(String, &'a Value)> {
+        let mut nodes = vec![(String::new(), value)];
+        for step in &self.steps {
+            let mut next = Vec::new();
+            for (path, node) in nodes {
+                match &step.selector {
+                    Selector::Key(key) => {
+                        if let Some(child) = node.child(key) {
+                            next.push((format!("{path}/{}", escape(key)), child));
+                        }
+                    }
+                    Selector::Children => children(&path, node, &mut next),
+                    Selector::Descendants => descendants(path, node, &mut next),
+                }
+            }
+            next.retain(|(_, node)| step.filters.iter().all(|f| f.matches(node)));
+            nodes = next;
+        }
+        nodes
+    }
+}
+
+impl Filter {
+    fn matches(&self, node: &Value) -> bool {
+        let Some(value) = self
+            .path
+            .iter()
+            .try_fold(node, |value, segment| value.child(segment))
+        else {
+            return false;
+        };
+        let Some((op, expected)) = &self.test else {
+            return true;
+        };
+        let ordering = match (value, expected) {
+            (Value::Number(a), Value::Number(b)) => a.partial_cmp(b),
+            (Value::String(a), Value::String(b)) => Some(a.cmp(b)),
+            (a, b) if a == b => Some(Ordering::Equal),
+            _ => None,
+        };
+        match op {
+            Op::Eq => ordering == Some(Ordering::Equal),
+            Op::Ne => ordering != Some(Ordering::Equal),
+            Op::Lt => ordering == Some(Ordering::Less),
+            Op::Le => matches!(ordering, Some(Ordering::Less | Ordering::Equal)),
+            Op::Gt => ordering == Some(Ordering::Greater),
+            Op::Ge => matches!(ordering, Some(Ordering::Greater | Ordering::Equal)),
+        }
+    }
+}
+
+fn children<'a>(path: &str, node: &'a Value, out: &mut Vec<(String, &'a Value)>) {
+    match node {
+        Value::Map(map) => {
+            out.extend(
+                map.iter()
+                    .map(|(k, v)| (format!("{path}/{}", escape(k)), v)),
+            );
+        }
+        Value::Array(vec) => out.extend(
+            vec.iter()
+                .enumerate()
+                .map(|(i, v)| (format!("{path}/{i}"), v)),
+        ),
+        _ => {}
+    }
+}
+
+fn descendants<'a>(path: String, node: &'a Value, out: &mut Vec<(String, &'a Value)>) {
+    let mut direct = Vec::new();
+    children(&path, node, &mut direct);
+    out.push((path, node));
+    for (path, child) in direct {
+        descendants(path, child, out);
+    }
+}
+
+fn parse_pointer(pointer: &str) -> Result<Vec<String>, PathError> {
+    if pointer.is_empty() {
+        return Ok(Vec::new());
+    }
+    let Some(rest) = pointer.strip_prefix('/') else {
+        return Err(PathError {
+            path: pointer.to_string(),
+            segment: pointer.to_string(),
+            kind: PathErrorKind::Syntax(String::from("path must start with `/`")),
+        });
+    };
+    let raw: Vec<String> = rest.split('/').map(String::from).collect();
+    raw.iter()
+        .enumerate()
+        .map(|(index, segment)| {
+            unescape(segment).ok_or_else(|| {
+                PathError::raw(
+                    &raw,
+                    index,
+                    PathErrorKind::Syntax(String::from("invalid escape")),
+                )
+            })
+        })
+        .collect()
+}
+
+///
+/// 按 `/` 分段, 忽略 `[]` 和引号中的 `/`
+fn split_query(query: &str) -> Result<Vec<String>, PathError> {
+    if query.is_empty() {
+        return Ok(Vec::new());
+    }
+    let syntax = |msg: &str| PathError {
+        path: query.to_string(),
+        segment: query.to_string(),
+        kind: PathErrorKind::Syntax(msg.to_string()),
+    };
+    let rest = query
+        .strip_prefix('/')
+        .ok_or_else(|| syntax("query must start with `/`"))?;
+    let mut segments = vec![String::new()];
+    let (mut depth, mut quoted, mut escaped) = (0usize, false, false);
+    for c in rest.chars() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if quoted => escaped = true,
+            '"' if depth > 0 => quoted = !quoted,
+            '[' if !quoted => depth += 1,
+            ']' if !quoted => {
+                depth = depth
+                    .checked_sub(1)
+                    .ok_or_else(|| syntax("unmatched `]`"))?
+            }
+            '/' if depth == 0 => {
+                segments.push(String::new());
+                continue;
+            }
+            _ => {}
+        }
+        segments.last_mut().expect("segments is not empty").push(c);
+    }
+    if depth > 0 || quoted {
+        return Err(syntax("unclosed `[` or string"));
+    }
+    Ok(segments)
+}
+
+fn parse_filters(mut text: &str) -> Result<Vec<Filter>, String> {
+    let mut filters = Vec::new();
+    while !text.is_empty() {
+        let body = text
+            .strip_prefix('[')
+            .ok_or_else(|| format!("unexpected `{text}`"))?;
+        let end = closing(body).ok_or("unclosed `[`")?;
+        filters.push(parse_filter(body[..end].trim())?);
+        text = &body[end + 1..];
+    }
+    Ok(filters)
+}
+
+/// 条件中引号外的第一个 `]`
+fn closing(text: &str) -> Option<usize> {
+    let (mut quoted, mut escaped) = (false, false);
+    for (i, c) in text.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if quoted => escaped = true,
+            '"' => quoted = !quoted,
+            ']' if !quoted => return Some(i),
+            _ => {}
+        }
+    }
+    None
+}
+
+fn parse_filter(text: &str) -> Result<Filter, String> {
+    const OPS: [(&str, Op); 6] = [
+        ("!=", Op::Ne),
+        ("<=", Op::Le),
+        (">=", Op::Ge),
+        ("=", Op::Eq),
+        ("<", Op::Lt),
+        (">", Op::Gt),
+    ];
+    let found = text.find(['=', '!', '<', '>']).map(|i| {
+        let (lhs, rest) = text.split_at(i);
+        let (op, len) = OPS
+            .iter()
+            .find(|(s, _)| rest.starts_with(s))
+            .map(|(s, op)| (*op, s.len()))
+            .ok_or_else(|| format!("invalid operator in `{text}`"))?;
+        Ok::<_, String>((lhs.trim(), Some((op, parse_literal(rest[len..].trim())?))))
+    });
+    let (lhs, test) = match found {
+        Some(result) => result?,
+        None => (text, None),
+    };
+    if lhs.is_empty() {
+        return Err(String::from("empty filter"));
+    }
+    let path = if lhs.starts_with('/') {
+        parse_pointer(lhs).map_err(|e| e.to_string())?
+    } else {
+        vec![unescape(lhs).ok_or("invalid escape")?]
+    };
+    Ok(Filter { path, test })
+}
+
+fn parse_literal(text: &str) -> Result<Value, String> {
+    Ok(match text {
+        "null" => Value::Null,
+        "true" => Value::Bool(true),
+        "false" => Value::Bool(false),
+        _ if text.len() >= 2 && text.starts_with('"') && text.ends_with('"') => {
+            let mut s = String::new();
+            let mut chars = text[1..text.len() - 1].chars();
+            while let Some(c) = chars.next() {
+                s.push(if c == '\\' {
+                    chars.next().ok_or("unterminated escape")?
+                } else {
+                    c
+                });
+            }
+            Value::String(Arc::from(s))
+        }
+        _ => {
+            if let Ok(n) = text.parse::<i64>() {
+                Value::Number(Number::I64(n))
+            } else if let Ok(n) = text.parse::<u64>() {
+                Value::Number(Number::U64(n))
+            } else if let Ok(n) = text.parse::<f64>() {
+                Value::Number(Number::F64(n))
+            } else {
+                return Err(format!("invalid literal `{text}`"));
+            }
+        }
+    })
+}
+
+/// 数组下标, 不允许前导 0
+fn parse_index(segment: &str) -> Option<usize> {
+    if (segment.len() > 1 && segment.starts_with('0')) || segment.starts_with('+') {
+        return None;
+    }
+    segment.parse().ok()
+}
+
+/// 设置时的下标, `-` 表示末尾; 不超过 `len`
+fn append_index(segment: &str, len: usize) -> Option<usize> {
+    match segment {
+        "-" => Some(len),
+        _ => parse_index(segment).filter(|i| *i <= len),
+    }
+}
+
+fn unescape(segment: &str) -> Option<String> {
+    let mut out = String::with_capacity(segment.len());
+    let mut chars = segment.chars();
+    while let Some(c) = chars.next() {
+        out.push(match c {
+            '~' => match chars.next()? {
+                '0' => '~',
+                '1' => '/',
+                _ => return None,
+            },
+            c => c,
+        });
+    }
+    Some(out)
+}
+
+pub(crate) fn escape(key: &str) -> String {
+    key.replace('~', "~0").replace('/', "~1")
+}
+
+fn join(segments: &[String]) -> String {
+    segments.iter().map(|s| format!("/{}", escape(s))).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> Value {
+        let file = |url: &str, size: u64| {
+            Value::from(Map::from([
+                (String::from("url"), Value::from(url)),
+                (String::from("size"), Value::from(size)),
+            ]))
+        };
+        Value::from(Map::from([(
+            String::from("files"),
+            Value::from(Map::from([
+                (String::from("dev"), file("http://dev", 10)),
+                (String::from("a/b"), file("http://ab", 2048)),
+            ])),
+        )]))
+    }
+
+    #[test]
+    fn test_pointer() -> Result<(), PathError> {
+        let mut value = sample();
+        assert_eq!(
+            value.get("/files/dev/url"),
+            Some(&Value::from("http://dev"))
+        );
+        assert_eq!(value.get("/files/a~1b/size"), Some(&Value::from(2048u64)));
+        assert_eq!(value.get(""), Some(&value.clone()));
+        assert!(value.get("/files/x").is_none());
+
+        assert_eq!(
+            value.set("/files/dev/size", 11u8)?,
+            Some(Value::from(10u64))
+        );
+        let err = value.set("/files/x/size", 1u8).unwrap_err();
+        assert_eq!(
+            (err.path.as_str(), err.kind),
+            ("/files/x", PathErrorKind::NotFound)
+        );
+        value.set_create("/files/x/tags", Value::Array(Vec::new()))?;
+        value.set("/files/x/tags/-", "a")?;
+        value.set("/files/x/tags/0", "b")?;
+        assert_eq!(value.get("/files/x/tags/0"), Some(&Value::from("b")));
+        let err = value.set("/files/x/tags/2", "c").unwrap_err();
+        assert_eq!(err.kind, PathErrorKind::Index);
+
+        *value.entry("/meta/count")? = Value::from(1u8);
+        assert_eq!(value.get("/meta/count"), Some(&Value::from(1u8)));
+        let err = value.entry("/meta/count/x").unwrap_err();
+        assert_eq!(err.segment, "x");
+        assert_eq!(err.kind, PathErrorKind::NotContainer("number"));
+
+        assert_eq!(
+            value.remove("/files/x")?.get("/tags/0"),
+            Some(&Value::from("b"))
+        );
+        assert!(value.remove("/files/x").is_err());
+        assert!(value.get("files").is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn test_query() -> Result<(), PathError> {
+        let value = sample();
+        let paths = |q: &str| -> Result<Vec<String>, PathError> {
+            Ok(value.query(q)?.into_iter().map(|(p, _)| p).collect())
+        };
+        assert_eq!(
+            paths("/files/*/url")?,
+            ["/files/dev/url", "/files/a~1b/url"]
+        );
+        assert_eq!(paths("/files/*[size>100]/url")?, ["/files/a~1b/url"]);
+        assert_eq!(paths("/**[url=\"http://dev\"]")?, ["/files/dev"]);
+        assert_eq!(paths("/**/size")?, ["/files/dev/size", "/files/a~1b/size"]);
+        assert_eq!(paths("/files/*[missing]")?.len(), 0);
+
+        let err = value.query("/files/*[size>]").unwrap_err();
+        assert_eq!(err.path, "/files/*[size>]");
+        assert!(matches!(err.kind, PathErrorKind::Syntax(_)));
+        let err = PathQuery::parse("/files/*[size~2]/url").unwrap_err();
+        assert_eq!(err.segment, "*[size~2]");
+        Ok(())
+    }
+}
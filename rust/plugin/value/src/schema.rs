@@ -0,0 +1,552 @@
+//! 描述 [`Value`] 的结构并校验
+//!
+//! ```ignore
+//! let schema = Schema::map()
+//!     .with_field("name", Schema::string().with_pattern("^[a-z]+$")?)
+//!     .with_optional("size", Schema::integer().with_min(0u32));
+//! if let Err(violations) = schema.validate(&input) {
+//!     // 每一项都带有 JSON Pointer 形式的路径
+//! }
+//! ```
+//!
+//! [`Schema::to_json_schema`] 导出为 JSON Schema; 结构体可用 `#[derive(Describe)]` 实现 [`Describe`]
+use indexmap::IndexMap;
+use regex::Regex;
+use std::{
+    cmp::Ordering,
+    collections::{BTreeMap, HashMap},
+    fmt,
+    sync::Arc,
+};
+
+use crate::{Map, Number, Value, path::escape};
+
+///
+/// 值的结构描述
+#[derive(Debug, Clone)]
+pub enum Schema {
+    /// 任意值
+    Any,
+    Null,
+    Bool,
+    /// `integer` 为 true 时只接受整数类型; 范围包含边界
+    Number {
+        integer: bool,
+        min: Option<Number>,
+        max: Option<Number>,
+    },
+    /// 字符串中能找到 `pattern` 的匹配即可, 需要完整匹配时使用 `^...$`
+    String {
+        pattern: Option<Regex>,
+    },
+    Bytes,
+    /// 每一项都符合该描述
+    Array(Box<Schema>),
+    /// `additional` 为 `None` 时不允许 `fields` 以外的键
+    Map {
+        fields: Vec<Field>,
+        additional: Option<Box<Schema>>,
+    },
+    /// 等于其中之一
+    Enum(Vec<Value>),
+    /// 符合其中之一
+    Union(Vec<Schema>),
+}
+
+///
+/// 映射中的一个键
+#[derive(Debug, Clone)]
+pub struct Field {
+    pub name: String,
+    pub schema: Schema,
+    pub required: bool,
+}
+
+impl Schema {
+    pub fn number() -> Self {
+        Schema::Number {
+            integer: false,
+            min: None,
+            max: None,
+        }
+    }
+
+    pub fn integer() -> Self {
+        Schema::Number {
+            integer: true,
+            min: None,
+            max: None,
+        }
+    }
+
+    pub fn string() -> Self {
+        Schema::String { pattern: None }
+    }
+
+    pub fn array(items: Schema) -> Self {
+        Schema::Array(Box::new(items))
+    }
+
+    ///
+    /// 允许其它任意键的映射
+    pub fn map() -> Self {
+        Schema::Map {
+            fields: Vec::new(),
+            additional: Some(Box::new(Schema::Any)),
+        }
+    }
+
+    ///
+    /// 值都符合 `values` 的映射, 如 `HashMap<String, T>`
+    pub fn map_of(values: Schema) -> Self {
+        Schema::Map {
+            fields: Vec::new(),
+            additional: Some(Box::new(values)),
+        }
+    }
+
+    ///
+    /// 最小值, 只作用于 [`Schema::Number`]
+    pub fn with_min(mut self, value: impl Into<Number>) -> Self {
+        if let Schema::Number { min, .. } = &mut self {
+            *min = Some(value.into());
+        }
+        self
+    }
+
+    ///
+    /// 最大值, 只作用于 [`Schema::Number`]
+    pub fn with_max(mut self, value: impl Into<Number>) -> Self {
+        if let Schema::Number { max, .. } = &mut self {
+            *max = Some(value.into());
+        }
+        self
+    }
+
+    ///
+    /// 正则表达式, 只作用于 [`Schema::String`]
+    pub fn with_pattern(mut self, pattern: &str) -> Result<Self, regex::Error> {
+        if let Schema::String { pattern: p } = &mut self {
+            *p = Some(Regex::new(pattern)?);
+        }
+        Ok(self)
+    }
+
+    ///
+    /// 必需的键, 只作用于 [`Schema::Map`]
+    pub fn with_field(self, name: impl Into<String>, schema: Schema) -> Self {
+        self.with_key(name.into(), schema, true)
+    }
+
+    ///
+    /// 可选的键, 只作用于 [`Schema::Map`]
+    pub fn with_optional(self, name: impl Into<String>, schema: Schema) -> Self {
+        self.with_key(name.into(), schema, false)
+    }
+
+    ///
+    /// 不允许声明以外的键, 只作用于 [`Schema::Map`]
+    pub fn deny_additional(mut self) -> Self {
+        if let Schema::Map { additional, .. } = &mut self {
+            *additional = None;
+        }
+        self
+    }
+
+    fn with_key(mut self, name: String, schema: Schema, required: bool) -> Self {
+        if let Schema::Map { fields, .. } = &mut self {
+            fields.retain(|f| f.name != name);
+            fields.push(Field {
+                name,
+                schema,
+                required,
+            });
+        }
+        self
+    }
+
+    ///
+    /// 校验 `value`, 失败时返回所有不符合的地方
+    pub fn validate(&self, value: &Value) -> Result<(), Vec<Violation>> {
+        let mut violations = Vec::new();
+        self.check(String::new(), value, &mut violations);
+        if violations.is_empty() {
+            Ok(())
+        } else {
+            Err(violations)
+        }
+    }
+
+    pub fn is_valid(&self, value: &Value) -> bool {
+        let mut violations = Vec::new();
+        self.check(String::new(), value, &mut violations);
+        violations.is_empty()
+    }
+
+    fn check(&self, path: String, value: &Value, out: &mut Vec<Violation>) {
+        let mut push = |path: String, kind| out.push(Violation { path, kind });
+        match (self, value) {
+            (Schema::Any, _) | (Schema::Null, Value::Null) | (Schema::Bool, Value::Bool(_)) => {}
+            (Schema::Bytes, Value::Bytes(_)) => {}
+            (Schema::Number { integer, min, max }, Value::Number(n)) => {
+                if *integer && !n.is_integer() {
+                    push(
+                        path,
+                        ViolationKind::Type {
+                            expected: self.name(),
+                            actual: "float",
+                        },
+                    );
+                } else if let Some(min) = min
+                    .as_ref()
+                    .filter(|min| n.partial_cmp(min).is_none_or(Ordering::is_lt))
+                {
+                    push(path, ViolationKind::Minimum(min.clone()));
+                } else if let Some(max) = max
+                    .as_ref()
+                    .filter(|max| n.partial_cmp(max).is_none_or(Ordering::is_gt))
+                {
+                    push(path, ViolationKind::Maximum(max.clone()));
+                }
+            }
+            (Schema::String { pattern }, Value::String(s)) => {
+                if let Some(pattern) = pattern.as_ref().filter(|p| !p.is_match(s)) {
+                    push(path, ViolationKind::Pattern(pattern.as_str().to_string()));
+                }
+            }
+            (Schema::Array(items), Value::Array(vec)) => {
+                for (i, item) in vec.iter().enumerate() {
+                    items.check(format!("{path}/{i}"), item, out);
+                }
+            }
+            (Schema::Map { fields, additional }, Value::Map(map)) => {
+                for field in fields {
+                    let path = format!("{path}/{}", escape(&field.name));
+                    match map.get(&field.name) {
+                        Some(value) => field.schema.check(path, value, out),
+                        None if field.required => out.push(Violation {
+                            path,
+                            kind: ViolationKind::Missing,
+                        }),
+                        None => {}
+                    }
+                }
+                let rest = map
+                    .iter()
+                    .filter(|(k, _)| fields.iter().all(|f| &f.name != *k));
+                for (key, value) in rest {
+                    let path = format!("{path}/{}", escape(key));
+                    match additional {
+                        Some(schema) => schema.check(path, value, out),
+                        None => out.push(Violation {
+                            path,
+                            kind: ViolationKind::Unknown,
+                        }),
+                    }
+                }
+            }
+            (Schema::Enum(values), value) => {
+                if !values.contains(value) {
+                    push(path, ViolationKind::Enum);
+                }
+            }
+            (Schema::Union(schemas), value) => {
+                if !schemas.iter().any(|s| s.is_valid(value)) {
+                    let expected = schemas.iter().map(Schema::name).collect();
+                    push(path, ViolationKind::Union(expected));
+                }
+            }
+            (_, value) => push(
+                path,
+                ViolationKind::Type {
+                    expected: self.name(),
+                    actual: value.type_name(),
+                },
+            ),
+        }
+    }
+
+    ///
+    /// 期望的类型名
+    pub fn name(&self) -> &'static str {
+        match self {
+            Schema::Any => "any",
+            Schema::Null => "null",
+            Schema::Bool => "bool",
+            Schema::Number { integer: true, .. } => "integer",
+            Schema::Number { .. } => "number",
+            Schema::String { .. } => "string",
+            Schema::Bytes => "bytes",
+            Schema::Array(_) => "array",
+            Schema::Map { .. } => "map",
+            Schema::Enum(_) => "enum",
+            Schema::Union(_) => "union",
+        }
+    }
+
+    ///
+    /// 导出为 JSON Schema; 二进制数据在 json 中为数字数组, 导出为 0..=255 的整数数组
+    pub fn to_json_schema(&self) -> Value {
+        let mut out = Map::new();
+        let mut set = |key: &str, value: Value| out.insert(key.to_string(), value);
+        match self {
+            Schema::Any => {}
+            Schema::Null => _ = set("type", "null".into()),
+            Schema::Bool => _ = set("type", "boolean".into()),
+            Schema::Number { integer, min, max } => {
+                set("type", if *integer { "integer" } else { "number" }.into());
+                if let Some(min) = min {
+                    set("minimum", min.clone().into());
+                }
+                if let Some(max) = max {
+                    set("maximum", max.clone().into());
+                }
+            }
+            Schema::String { pattern } => {
+                set("type", "string".into());
+                if let Some(pattern) = pattern {
+                    set("pattern", pattern.as_str().into());
+                }
+            }
+            Schema::Bytes => {
+                let byte = Schema::integer().with_min(u8::MIN).with_max(u8::MAX);
+                return Schema::array(byte).to_json_schema();
+            }
+            Schema::Array(items) => {
+                set("type", "array".into());
+                set("items", items.to_json_schema());
+            }
+            Schema::Map { fields, additional } => {
+                set("type", "object".into());
+                let properties: Map = fields
+                    .iter()
+                    .map(|f| (f.name.clone(), f.schema.to_json_schema()))
+                    .collect();
+                let required: Vec<_> = fields
+                    .iter()
+                    .filter(|f| f.required)
+                    .map(|f| f.name.as_str())
+                    .collect();
+                if !properties.is_empty() {
+                    set("properties", properties.into());
+                }
+                if !required.is_empty() {
+                    set("required", required.into());
+                }
+                match additional.as_deref() {
+                    Some(Schema::Any) => {}
+                    Some(schema) => _ = set("additionalProperties", schema.to_json_schema()),
+                    None => _ = set("additionalProperties", false.into()),
+                }
+            }
+            Schema::Enum(values) => _ = set("enum", Value::Array(values.clone())),
+            Schema::Union(schemas) => {
+                let schemas = schemas.iter().map(Schema::to_json_schema).collect();
+                set("anyOf", Value::Array(schemas));
+            }
+        }
+        Value::Map(out)
+    }
+}
+
+///
+/// 一处不符合描述的地方
+#[derive(Debug, Clone, PartialEq)]
+pub struct Violation {
+    /// JSON Pointer 形式的路径, 根为空字符串
+    pub path: String,
+    pub kind: ViolationKind,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ViolationKind {
+    Type {
+        expected: &'static str,
+        actual: &'static str,
+    },
+    Minimum(Number),
+    Maximum(Number),
+    /// 字符串不匹配该正则
+    Pattern(String),
+    /// 缺少必需的键
+    Missing,
+    /// 不允许的键
+    Unknown,
+    /// 不等于任何一个可选值
+    Enum,
+    /// 不符合任何一个描述, 值为各描述的类型名
+    Union(Vec<&'static str>),
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let path = if self.path.is_empty() {
+            "/"
+        } else {
+            &self.path
+        };
+        write!(f, "{path}: ")?;
+        match &self.kind {
+            ViolationKind::Type { expected, actual } => {
+                write!(f, "expected {expected}, found {actual}")
+            }
+            ViolationKind::Minimum(min) => write!(f, "less than {min}"),
+            ViolationKind::Maximum(max) => write!(f, "greater than {max}"),
+            ViolationKind::Pattern(pattern) => write!(f, "does not match {pattern:?}"),
+            ViolationKind::Missing => write!(f, "missing required key"),
+            ViolationKind::Unknown => write!(f, "unknown key"),
+            ViolationKind::Enum => write!(f, "not one of the allowed values"),
+            ViolationKind::Union(expected) => write!(f, "expected one of {}", expected.join(", ")),
+        }
+    }
+}
+
+impl std::error::Error for Violation {}
+
+///
+/// 类型对应的 [`Schema`], 结构体可用 `#[derive(Describe)]` 实现
+pub trait Describe {
+    fn schema() -> Schema;
+
+    ///
+    /// 作为结构体字段时, 缺少该键是否也能转换
+    fn optional() -> bool {
+        false
+    }
+}
+
+macro_rules! describe {
+    ($($ty:ty => $schema:expr),* $(,)?) => {
+        $(
+            impl Describe for $ty {
+                fn schema() -> Schema {
+                    $schema
+                }
+            }
+        )*
+    };
+    (@int $($ty:ty),* $(,)?) => {
+        $(
+            describe!($ty => Schema::integer().with_min(<$ty>::MIN).with_max(<$ty>::MAX));
+        )*
+    };
+}
+
+describe!(@int i8, i16, i32, i64, u8, u16, u32, u64);
+describe!(
+    f32 => Schema::number(),
+    f64 => Schema::number(),
+    Number => Schema::number(),
+    bool => Schema::Bool,
+    String => Schema::string(),
+    Arc<[u8]> => Schema::Bytes,
+);
+
+impl Describe for Value {
+    fn schema() -> Schema {
+        Schema::Any
+    }
+
+    fn optional() -> bool {
+        true
+    }
+}
+
+impl<T: Describe> Describe for Vec<T> {
+    fn schema() -> Schema {
+        Schema::array(T::schema())
+    }
+}
+
+macro_rules! describe_map {
+    ($($map:ident),* $(,)?) => {
+        $(
+            impl<T: Describe> Describe for $map<String, T> {
+                fn schema() -> Schema {
+                    Schema::map_of(T::schema())
+                }
+            }
+        )*
+    };
+}
+
+describe_map!(HashMap, BTreeMap, IndexMap);
+
+impl<T: Describe> Describe for Option<T> {
+    fn schema() -> Schema {
+        Schema::Union(vec![T::schema(), Schema::Null])
+    }
+
+    fn optional() -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_schema() {
+        let schema = Schema::map()
+            .with_field("name", Schema::string().with_pattern("^[a-z]+$").unwrap())
+            .with_field("size", u8::schema())
+            .with_optional("tags", Vec::<String>::schema())
+            .with_optional("kind", Schema::Enum(vec!["a".into(), "b".into()]))
+            .deny_additional();
+        let value: Value =
+            serde_json::from_str(r#"{"name": "abc", "size": 3, "kind": "a"}"#).unwrap();
+        assert!(schema.validate(&value).is_ok());
+
+        let value: Value = serde_json::from_str(
+            r#"{"name": "ABC", "size": 300, "tags": ["x", 1], "kind": "c", "extra": null}"#,
+        )
+        .unwrap();
+        let violations: Vec<_> = schema
+            .validate(&value)
+            .unwrap_err()
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        assert_eq!(
+            violations,
+            [
+                "/name: does not match \"^[a-z]+$\"",
+                "/size: greater than 255u8",
+                "/tags/1: expected string, found number",
+                "/kind: not one of the allowed values",
+                "/extra: unknown key",
+            ]
+        );
+        let value: Value = serde_json::from_str(r#"{"size": 1.5}"#).unwrap();
+        let violations = schema.validate(&value).unwrap_err();
+        assert_eq!(violations[0].kind, ViolationKind::Missing);
+        assert_eq!(
+            violations[1].to_string(),
+            "/size: expected integer, found float"
+        );
+
+        let json = schema.to_json_schema();
+        assert_eq!(
+            json.get("/properties/size/maximum"),
+            Some(&Value::from(255u8))
+        );
+        assert_eq!(
+            json.get("/properties/tags/items/type"),
+            Some(&Value::from("string"))
+        );
+        assert_eq!(
+            json.get("/required"),
+            Some(&Value::from(vec!["name", "size"]))
+        );
+        assert_eq!(json.get("/additionalProperties"), Some(&Value::Bool(false)));
+        assert_eq!(
+            Option::<bool>::schema()
+                .to_json_schema()
+                .as_map()
+                .unwrap()
+                .len(),
+            1
+        );
+    }
+}
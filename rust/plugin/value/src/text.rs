@@ -0,0 +1,469 @@
+//! 保留类型的文本格式
+//!
+//! 与 json 类似, 另外:
+//! - 数字带类型后缀, 与 [`Number`] 的 `Display` 一致: `5i8`, `300u16`, `1.5f32`, `NaNf64`;
+//!   没有后缀时整数为 [`Number::I64`](超出时为 [`Number::U64`]), 小数为 [`Number::F64`]
+//! - 二进制数据: `b"\x00ab"`, 转义与 rust 的字节字符串相同
+//! - 注释: `// ...` 和 `/* ... */`; 数组和映射允许末尾的逗号
+//!
+//! ```text
+//! {
+//!   // 调用参数
+//!   "id": 3u32,
+//!   "data": b"\x89PNG",
+//!   "tags": ["a", "b",],
+//! }
+//! ```
+//!
+//! [`Value`] 的 `Display` 输出该格式(`{:#}` 时换行缩进), 用 [`str::parse`] 读取, 读回的值与原值完全相同
+use std::{fmt, str::FromStr, sync::Arc};
+
+use crate::{Map, Number, Value};
+
+/// 最大嵌套层数
+const MAX_DEPTH: usize = 128;
+
+/// 数字的类型后缀
+const SUFFIXES: [&str; 10] = [
+    "i8", "i16", "i32", "i64", "u8", "u16", "u32", "u64", "f32", "f64",
+];
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let indent = if f.alternate() { Some(0) } else { None };
+        write_value(f, self, indent)
+    }
+}
+
+fn write_value(f: &mut fmt::Formatter<'_>, value: &Value, indent: Option<usize>) -> fmt::Result {
+    match value {
+        Value::Null => f.write_str("null"),
+        Value::Bool(b) => write!(f, "{b}"),
+        Value::Number(n) => write!(f, "{n}"),
+        Value::String(s) => write_str(f, s),
+        Value::Bytes(b) => write!(f, "b\"{}\"", b.escape_ascii()),
+        Value::Array(vec) => write_items(f, "[", "]", vec, indent, |f, item, indent| {
+            write_value(f, item, indent)
+        }),
+        Value::Map(map) => write_items(f, "{", "}", map, indent, |f, (k, v), indent| {
+            write_str(f, k)?;
+            f.write_str(": ")?;
+            write_value(f, v, indent)
+        }),
+    }
+}
+
+/// 单行时以 `, ` 分隔; 缩进时每项一行
+fn write_items<I: IntoIterator>(
+    f: &mut fmt::Formatter<'_>,
+    open: &str,
+    close: &str,
+    items: I,
+    indent: Option<usize>,
+    mut write: impl FnMut(&mut fmt::Formatter<'_>, I::Item, Option<usize>) -> fmt::Result,
+) -> fmt::Result {
+    f.write_str(open)?;
+    let mut empty = true;
+    for (i, item) in items.into_iter().enumerate() {
+        empty = false;
+        match indent {
+            Some(n) => {
+                f.write_str(if i == 0 { "\n" } else { ",\n" })?;
+                write!(f, "{:1$}", "", (n + 1) * 2)?;
+                write(f, item, Some(n + 1))?;
+            }
+            None => {
+                if i > 0 {
+                    f.write_str(", ")?;
+                }
+                write(f, item, None)?;
+            }
+        }
+    }
+    if let (Some(n), false) = (indent, empty) {
+        write!(f, "\n{:1$}", "", n * 2)?;
+    }
+    f.write_str(close)
+}
+
+fn write_str(f: &mut fmt::Formatter<'_>, s: &str) -> fmt::Result {
+    f.write_str("\"")?;
+    for c in s.chars() {
+        match c {
+            '"' => f.write_str("\\\"")?,
+            '\\' => f.write_str("\\\\")?,
+            '\n' => f.write_str("\\n")?,
+            '\r' => f.write_str("\\r")?,
+            '\t' => f.write_str("\\t")?,
+            c if c.is_control() => write!(f, "\\u{{{:x}}}", c as u32)?,
+            c => write!(f, "{c}")?,
+        }
+    }
+    f.write_str("\"")
+}
+
+///
+/// 解析文本格式的错误, 行和列从 1 开始
+#[derive(Debug, Clone, PartialEq)]
+pub struct TextError {
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+impl fmt::Display for TextError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} at line {}, column {}",
+            self.message, self.line, self.column
+        )
+    }
+}
+
+impl std::error::Error for TextError {}
+
+impl FromStr for Value {
+    type Err = TextError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parser = Parser { src: s, pos: 0 };
+        let value = parser.value(0)?;
+        parser.skip()?;
+        if parser.pos < s.len() {
+            return Err(parser.error("trailing characters"));
+        }
+        Ok(value)
+    }
+}
+
+struct Parser<'a> {
+    src: &'a str,
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn value(&mut self, depth: usize) -> Result<Value, TextError> {
+        self.skip()?;
+        match self.peek() {
+            Some(b'"') => Ok(Value::String(Arc::from(self.string()?))),
+            Some(b'b') if self.src[self.pos..].starts_with("b\"") => self.bytes(),
+            Some(b'[' | b'{') if depth >= MAX_DEPTH => Err(self.error("too deeply nested")),
+            Some(b'[') => self.array(depth + 1),
+            Some(b'{') => self.map(depth + 1),
+            Some(_) => self.token(),
+            None => Err(self.error("unexpected end of input")),
+        }
+    }
+
+    fn array(&mut self, depth: usize) -> Result<Value, TextError> {
+        self.pos += 1;
+        let mut vec = Vec::new();
+        while !self.close(b']')? {
+            vec.push(self.value(depth)?);
+            self.separator(b']')?;
+        }
+        Ok(Value::Array(vec))
+    }
+
+    fn map(&mut self, depth: usize) -> Result<Value, TextError> {
+        self.pos += 1;
+        let mut map = Map::new();
+        while !self.close(b'}')? {
+            let start = self.pos;
+            if self.peek() != Some(b'"') {
+                return Err(self.error("expected string key"));
+            }
+            let key = self.string()?;
+            self.skip()?;
+            if self.peek() != Some(b':') {
+                return Err(self.error("expected ':'"));
+            }
+            self.pos += 1;
+            let value = self.value(depth)?;
+            if map.contains_key(&key) {
+                self.pos = start;
+                return Err(self.error(format!("duplicate key {key:?}")));
+            }
+            map.insert(key, value);
+            self.separator(b'}')?;
+        }
+        Ok(Value::Map(map))
+    }
+
+    /// 跳过空白, 遇到 `close` 时消耗并返回 true
+    fn close(&mut self, close: u8) -> Result<bool, TextError> {
+        self.skip()?;
+        let closed = self.peek() == Some(close);
+        if closed {
+            self.pos += 1;
+        }
+        Ok(closed)
+    }
+
+    /// 项之后必须是 `,` 或 `close`, 只消耗 `,`
+    fn separator(&mut self, close: u8) -> Result<(), TextError> {
+        self.skip()?;
+        match self.peek() {
+            Some(b',') => {
+                self.pos += 1;
+                Ok(())
+            }
+            Some(c) if c == close => Ok(()),
+            _ => Err(self.error(format!("expected ',' or '{}'", close as char))),
+        }
+    }
+
+    fn string(&mut self) -> Result<String, TextError> {
+        self.pos += 1;
+        let mut out = String::new();
+        loop {
+            let c = self.next_char()?;
+            match c {
+                '"' => return Ok(out),
+                '\\' => {
+                    let c = match self.next_char()? {
+                        'n' => '\n',
+                        'r' => '\r',
+                        't' => '\t',
+                        '0' => '\0',
+                        c @ ('\\' | '"' | '\'') => c,
+                        'u' => self.unicode()?,
+                        other => return Err(self.back(other).error("invalid escape")),
+                    };
+                    out.push(c);
+                }
+                c => out.push(c),
+            }
+        }
+    }
+
+    /// `\u{..}` 中 `u` 之后的部分
+    fn unicode(&mut self) -> Result<char, TextError> {
+        let rest = &self.src[self.pos..];
+        let hex = rest
+            .strip_prefix('{')
+            .and_then(|rest| rest.split_once('}'))
+            .map(|(hex, _)| hex)
+            .ok_or_else(|| self.error("expected '{' in unicode escape"))?;
+        let c = u32::from_str_radix(hex, 16)
+            .ok()
+            .and_then(char::from_u32)
+            .ok_or_else(|| self.error("invalid unicode escape"))?;
+        self.pos += hex.len() + 2;
+        Ok(c)
+    }
+
+    fn bytes(&mut self) -> Result<Value, TextError> {
+        self.pos += 2;
+        let mut out = Vec::new();
+        loop {
+            let c = self.next_char()?;
+            if !c.is_ascii() {
+                return Err(self.back(c).error("non-ASCII character in byte string"));
+            }
+            match c {
+                '"' => return Ok(Value::Bytes(Arc::from(out))),
+                '\\' => {
+                    let b = match self.next_char()? {
+                        'n' => b'\n',
+                        'r' => b'\r',
+                        't' => b'\t',
+                        '0' => b'\0',
+                        c @ ('\\' | '"' | '\'') => c as u8,
+                        'x' => {
+                            let hex = self.src.get(self.pos..self.pos + 2).unwrap_or_default();
+                            let b = u8::from_str_radix(hex, 16)
+                                .map_err(|_| self.error("invalid \\x escape"))?;
+                            self.pos += 2;
+                            b
+                        }
+                        c => return Err(self.back(c).error("invalid escape")),
+                    };
+                    out.push(b);
+                }
+                c => out.push(c as u8),
+            }
+        }
+    }
+
+    /// `null`, `true`, `false` 或数字
+    fn token(&mut self) -> Result<Value, TextError> {
+        let start = self.pos;
+        let len = self.src[start..]
+            .find(|c: char| !(c.is_ascii_alphanumeric() || matches!(c, '.' | '+' | '-' | '_')))
+            .unwrap_or(self.src.len() - start);
+        let token = &self.src[start..start + len];
+        let value = match token {
+            "" => return Err(self.error("unexpected character")),
+            "null" => Value::Null,
+            "true" => Value::Bool(true),
+            "false" => Value::Bool(false),
+            _ => Value::Number(number(token).ok_or_else(|| self.error("invalid number"))?),
+        };
+        self.pos += len;
+        Ok(value)
+    }
+
+    /// 跳过空白和注释
+    fn skip(&mut self) -> Result<(), TextError> {
+        loop {
+            let rest = &self.src[self.pos..];
+            let trimmed = rest.trim_start();
+            self.pos += rest.len() - trimmed.len();
+            if trimmed.starts_with("//") {
+                self.pos += trimmed.find('\n').unwrap_or(trimmed.len());
+            } else if trimmed.starts_with("/*") {
+                let end = trimmed
+                    .find("*/")
+                    .ok_or_else(|| self.error("unterminated comment"))?;
+                self.pos += end + 2;
+            } else {
+                return Ok(());
+            }
+        }
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.src.as_bytes().get(self.pos).copied()
+    }
+
+    fn next_char(&mut self) -> Result<char, TextError> {
+        let c = self.src[self.pos..]
+            .chars()
+            .next()
+            .ok_or_else(|| self.error("unexpected end of input"))?;
+        self.pos += c.len_utf8();
+        Ok(c)
+    }
+
+    /// 回到字符 `c` 之前, 使错误指向该字符
+    fn back(&mut self, c: char) -> &Self {
+        self.pos -= c.len_utf8();
+        self
+    }
+
+    fn error(&self, message: impl Into<String>) -> TextError {
+        let before = &self.src[..self.pos];
+        let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+        TextError {
+            line: before.matches('\n').count() + 1,
+            column: before[line_start..].chars().count() + 1,
+            message: message.into(),
+        }
+    }
+}
+
+fn number(token: &str) -> Option<Number> {
+    let (body, suffix) = SUFFIXES
+        .iter()
+        .find_map(|s| Some((token.strip_suffix(s)?, *s)))
+        .unwrap_or((token, ""));
+    let number = match suffix {
+        "i8" => Number::I8(body.parse().ok()?),
+        "i16" => Number::I16(body.parse().ok()?),
+        "i32" => Number::I32(body.parse().ok()?),
+        "i64" => Number::I64(body.parse().ok()?),
+        "u8" => Number::U8(body.parse().ok()?),
+        "u16" => Number::U16(body.parse().ok()?),
+        "u32" => Number::U32(body.parse().ok()?),
+        "u64" => Number::U64(body.parse().ok()?),
+        "f32" => Number::F32(body.parse().ok()?),
+        "f64" => Number::F64(body.parse().ok()?),
+        // 没有后缀时只接受十进制的字面量
+        _ if !body
+            .bytes()
+            .all(|b| b.is_ascii_digit() || b"+-.eE".contains(&b)) =>
+        {
+            return None;
+        }
+        _ => match body.parse() {
+            Ok(n) => Number::I64(n),
+            Err(_) => match body.parse() {
+                Ok(n) => Number::U64(n),
+                Err(_) => Number::F64(body.parse().ok()?),
+            },
+        },
+    };
+    Some(number)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_text() {
+        let value = Value::Map(Map::from([
+            (String::from("z"), Value::from(5i8)),
+            (String::from("a\"\n"), Value::from("tab\there \u{1b} 中")),
+            (
+                String::from("bytes"),
+                Value::Bytes(Arc::from(&b"\x00a\"\\\xff"[..])),
+            ),
+            (
+                String::from("nums"),
+                Value::from(vec![
+                    Value::from(u64::MAX),
+                    Value::from(-0.0f64),
+                    Value::from(0.1f32),
+                    Value::from(f64::NEG_INFINITY),
+                    Value::from(1e300f64),
+                ]),
+            ),
+            (String::from("empty"), Value::Array(Vec::new())),
+            (
+                String::from("null"),
+                Value::Map(Map::from([(String::new(), Value::Null)])),
+            ),
+        ]));
+        for text in [value.to_string(), format!("{value:#}")] {
+            let parsed: Value = text.parse().unwrap();
+            assert_eq!(parsed.to_string(), value.to_string());
+            assert_eq!(format!("{parsed:#}"), format!("{value:#}"));
+        }
+        assert!(value.to_string().starts_with(
+            r#"{"z": 5i8, "a\"\n": "tab\there \u{1b} 中", "bytes": b"\x00a\"\\\xff""#
+        ));
+
+        let nan: Value = "NaNf32".parse().unwrap();
+        assert!(matches!(nan, Value::Number(Number::F32(n)) if n.is_nan()));
+
+        let text = r#"
+            // 注释
+            { "a": [1, 2.5, 18446744073709551615, true, /* 空 */ null,], "b": 300u16, }
+        "#;
+        let value: Value = text.parse().unwrap();
+        assert!(matches!(
+            value.get("/a/0"),
+            Some(Value::Number(Number::I64(1)))
+        ));
+        assert!(matches!(
+            value.get("/a/1"),
+            Some(Value::Number(Number::F64(_)))
+        ));
+        assert!(matches!(
+            value.get("/a/2"),
+            Some(Value::Number(Number::U64(u64::MAX)))
+        ));
+        assert!(matches!(
+            value.get("/b"),
+            Some(Value::Number(Number::U16(300)))
+        ));
+
+        let err = "{\n  \"a\": 300u8\n}".parse::<Value>().unwrap_err();
+        assert_eq!(err.to_string(), "invalid number at line 2, column 8");
+        assert_eq!(
+            "[1 2]".parse::<Value>().unwrap_err().message,
+            "expected ',' or ']'"
+        );
+        assert_eq!(
+            "{\"a\": 1, \"a\": 2}".parse::<Value>().unwrap_err().column,
+            10
+        );
+        assert!("b\"中\"".parse::<Value>().is_err());
+        assert_eq!("\"\\q\"".parse::<Value>().unwrap_err().column, 3);
+        assert!("[".repeat(MAX_DEPTH + 1).parse::<Value>().is_err());
+        assert!("1 2".parse::<Value>().is_err());
+    }
+}
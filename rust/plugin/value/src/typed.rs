@@ -0,0 +1,272 @@
+//! 保留类型的序列化
+//!
+//! 默认的序列化在 json 中会丢失数字类型、二进制数据和映射中键的顺序; 该模式中 json 对象都是类型标记:
+//! - 数字: `{"i8": 1}`, `{"u64": 2}`, `{"f32": 1.5}` 等
+//! - 二进制数据: `{"bytes": [1, 2]}`
+//! - 映射: `{"map": [["k", v], ...]}`, 按顺序保存键值对
+//!
+//! 其余值与默认模式相同. 使用 [`Typed`] 包装, 或在字段上使用 `#[serde(with = "value::typed")]`
+use serde::{
+    de::{self, Deserialize, Deserializer, MapAccess, SeqAccess, Visitor},
+    ser::{Serialize, SerializeMap, SerializeSeq, Serializer},
+};
+use std::{fmt, sync::Arc};
+
+use crate::{Map, Number, Value};
+
+const BYTES: &str = "bytes";
+const MAP: &str = "map";
+
+///
+/// 以保留类型的模式序列化/反序列化 `T`(`Value` 或 `&Value`)
+#[derive(Debug, Clone, PartialEq)]
+pub struct Typed<T>(pub T);
+
+pub fn serialize<S: Serializer>(value: &Value, serializer: S) -> Result<S::Ok, S::Error> {
+    Typed(value).serialize(serializer)
+}
+
+pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Value, D::Error> {
+    Typed::<Value>::deserialize(deserializer).map(|t| t.0)
+}
+
+impl Serialize for Typed<&Value> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self.0 {
+            Value::Number(n) => {
+                let mut m = serializer.serialize_map(Some(1))?;
+                m.serialize_entry(tag(n), n)?;
+                m.end()
+            }
+            Value::Bytes(b) => {
+                let mut m = serializer.serialize_map(Some(1))?;
+                m.serialize_entry(BYTES, &RawBytes(b))?;
+                m.end()
+            }
+            Value::Array(vec) => {
+                let mut seq = serializer.serialize_seq(Some(vec.len()))?;
+                for v in vec {
+                    seq.serialize_element(&Typed(v))?;
+                }
+                seq.end()
+            }
+            Value::Map(map) => {
+                let mut m = serializer.serialize_map(Some(1))?;
+                m.serialize_entry(MAP, &Entries(map))?;
+                m.end()
+            }
+            other => other.serialize(serializer),
+        }
+    }
+}
+
+impl Serialize for Typed<Value> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        Typed(&self.0).serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Typed<Value> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(TypedVisitor).map(Typed)
+    }
+}
+
+fn tag(n: &Number) -> &'static str {
+    match n {
+        Number::I8(_) => "i8",
+        Number::I16(_) => "i16",
+        Number::I32(_) => "i32",
+        Number::I64(_) => "i64",
+        Number::U8(_) => "u8",
+        Number::U16(_) => "u16",
+        Number::U32(_) => "u32",
+        Number::U64(_) => "u64",
+        Number::F32(_) => "f32",
+        Number::F64(_) => "f64",
+    }
+}
+
+struct RawBytes<'a>(&'a [u8]);
+
+impl Serialize for RawBytes<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_bytes(self.0)
+    }
+}
+
+struct Entries<'a>(&'a Map);
+
+impl Serialize for Entries<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut seq = serializer.serialize_seq(Some(self.0.len()))?;
+        for (k, v) in self.0 {
+            seq.serialize_element(&(k, Typed(v)))?;
+        }
+        seq.end()
+    }
+}
+
+/// 二进制数据, 接受 bytes 或 u8 数组
+struct ByteBuf(Vec<u8>);
+
+impl<'de> Deserialize<'de> for ByteBuf {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct BytesVisitor;
+
+        impl<'de> Visitor<'de> for BytesVisitor {
+            type Value = ByteBuf;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                write!(f, "bytes")
+            }
+
+            fn visit_bytes<E: de::Error>(self, v: &[u8]) -> Result<ByteBuf, E> {
+                Ok(ByteBuf(v.to_vec()))
+            }
+
+            fn visit_byte_buf<E: de::Error>(self, v: Vec<u8>) -> Result<ByteBuf, E> {
+                Ok(ByteBuf(v))
+            }
+
+            fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<ByteBuf, A::Error> {
+                let mut vec = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+                while let Some(b) = seq.next_element::<u8>()? {
+                    vec.push(b);
+                }
+                Ok(ByteBuf(vec))
+            }
+        }
+
+        deserializer.deserialize_bytes(BytesVisitor)
+    }
+}
+
+struct TypedVisitor;
+
+impl<'de> Visitor<'de> for TypedVisitor {
+    type Value = Value;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "typed value")
+    }
+
+    fn visit_bool<E: de::Error>(self, v: bool) -> Result<Value, E> {
+        Ok(Value::Bool(v))
+    }
+
+    // 未标记的数字按默认模式处理
+    fn visit_i64<E: de::Error>(self, v: i64) -> Result<Value, E> {
+        Ok(Value::Number(Number::I64(v)))
+    }
+
+    fn visit_u64<E: de::Error>(self, v: u64) -> Result<Value, E> {
+        Ok(Value::Number(Number::U64(v)))
+    }
+
+    fn visit_f64<E: de::Error>(self, v: f64) -> Result<Value, E> {
+        Ok(Value::Number(Number::F64(v)))
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<Value, E> {
+        Ok(Value::String(Arc::from(v)))
+    }
+
+    fn visit_bytes<E: de::Error>(self, v: &[u8]) -> Result<Value, E> {
+        Ok(Value::Bytes(Arc::from(v)))
+    }
+
+    fn visit_unit<E: de::Error>(self) -> Result<Value, E> {
+        Ok(Value::Null)
+    }
+
+    fn visit_none<E: de::Error>(self) -> Result<Value, E> {
+        Ok(Value::Null)
+    }
+
+    fn visit_some<D: Deserializer<'de>>(self, deserializer: D) -> Result<Value, D::Error> {
+        deserialize(deserializer)
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Value, A::Error> {
+        let mut vec = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+        while let Some(Typed(v)) = seq.next_element()? {
+            vec.push(v);
+        }
+        Ok(Value::Array(vec))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut access: A) -> Result<Value, A::Error> {
+        let tag: String = access
+            .next_key()?
+            .ok_or_else(|| de::Error::custom("empty object is not a typed value"))?;
+        let value = match tag.as_str() {
+            "i8" => Number::I8(access.next_value()?).into(),
+            "i16" => Number::I16(access.next_value()?).into(),
+            "i32" => Number::I32(access.next_value()?).into(),
+            "i64" => Number::I64(access.next_value()?).into(),
+            "u8" => Number::U8(access.next_value()?).into(),
+            "u16" => Number::U16(access.next_value()?).into(),
+            "u32" => Number::U32(access.next_value()?).into(),
+            "u64" => Number::U64(access.next_value()?).into(),
+            "f32" => Number::F32(access.next_value()?).into(),
+            "f64" => Number::F64(access.next_value()?).into(),
+            BYTES => Value::Bytes(Arc::from(access.next_value::<ByteBuf>()?.0)),
+            MAP => {
+                let entries: Vec<(String, Typed<Value>)> = access.next_value()?;
+                Value::Map(entries.into_iter().map(|(k, v)| (k, v.0)).collect())
+            }
+            other => return Err(de::Error::unknown_variant(other, TAGS)),
+        };
+        if access.next_key::<de::IgnoredAny>()?.is_some() {
+            return Err(de::Error::custom(format!(
+                "typed value {tag} has more than one key"
+            )));
+        }
+        Ok(value)
+    }
+}
+
+const TAGS: &[&str] = &[
+    "i8", "i16", "i32", "i64", "u8", "u16", "u32", "u64", "f32", "f64", BYTES, MAP,
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_roundtrip() {
+        let mut map = Map::new();
+        map.insert(String::from("a"), Value::from(1u8));
+        map.insert(String::from("b"), Value::Bytes(Arc::from(&[1u8, 2][..])));
+        let value = Value::Array(vec![
+            Value::Null,
+            Value::from(true),
+            Value::from(-3i16),
+            Value::from(u64::MAX),
+            Value::from(1.5f32),
+            Value::from(String::from("s")),
+            Value::Map(map),
+        ]);
+
+        // 默认模式为普通 json
+        let plain = serde_json::to_value(&value).unwrap();
+        assert_eq!(plain[2], serde_json::json!(-3));
+        assert_eq!(plain[6]["b"], serde_json::json!([1, 2]));
+        let back: Value = serde_json::from_value(plain).unwrap();
+        assert!(
+            matches!(&back, Value::Array(vec) if matches!(vec[2], Value::Number(Number::I64(-3))))
+        );
+
+        let json = serde_json::to_string(&Typed(&value)).unwrap();
+        let Typed(back) = serde_json::from_str::<Typed<Value>>(&json).unwrap();
+        assert_eq!(back, value);
+        // 数字比较时不区分类型, 单独检查类型
+        assert!(
+            matches!(&back, Value::Array(vec) if matches!(vec[2], Value::Number(Number::I16(-3)))
+            && matches!(vec[4], Value::Number(Number::F32(_))))
+        );
+        assert!(serde_json::from_str::<Typed<Value>>(r#"{"x": 1}"#).is_err());
+    }
+}
@@ -1,13 +1,25 @@
 use crate::Number;
-use std::{collections::HashMap, convert::Infallible, sync::Arc};
+use indexmap::IndexMap;
+use std::{
+    collections::{BTreeMap, HashMap},
+    convert::Infallible,
+    sync::Arc,
+};
+
+///
+/// [`Value::Map`] 的映射, 按插入顺序迭代和序列化; 比较时与顺序无关
+///
+/// 删除键时使用 `shift_remove` 保持其余键的顺序
+pub type Map = IndexMap<String, Value>;
 
 /// 动态值类型，可表示 rust 的各种数据类型。
 ///
 /// 该枚举支持空值、布尔、数字、字符串、二进制数据、数组和映射。
 /// 字符串和二进制数据使用 `Arc` 实现共享所有权，克隆廉价。
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Default)]
 pub enum Value {
     /// 空值
+    #[default]
     Null,
     /// 布尔值。
     Bool(bool),
@@ -19,8 +31,102 @@ pub enum Value {
     Bytes(Arc<[u8]>),
     /// 值数组。
     Array(Vec<Value>),
-    /// 键值对映射，键为字符串。
-    Map(HashMap<String, Value>),
+    /// 键值对映射，键为字符串, 保留插入顺序。
+    Map(Map),
+}
+
+impl Value {
+    ///
+    /// 类型名, 用于错误信息
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Value::Null => "null",
+            Value::Bool(_) => "bool",
+            Value::Number(_) => "number",
+            Value::String(_) => "string",
+            Value::Bytes(_) => "bytes",
+            Value::Array(_) => "array",
+            Value::Map(_) => "map",
+        }
+    }
+
+    pub fn is_null(&self) -> bool {
+        matches!(self, Value::Null)
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            Value::Bool(b) => Some(*b),
+            _ => None,
+        }
+    }
+
+    pub fn as_number(&self) -> Option<&Number> {
+        match self {
+            Value::Number(n) => Some(n),
+            _ => None,
+        }
+    }
+
+    pub fn as_i64(&self) -> Option<i64> {
+        self.as_number().and_then(Number::as_i64)
+    }
+
+    pub fn as_u64(&self) -> Option<u64> {
+        self.as_number().and_then(Number::as_u64)
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        self.as_number().and_then(Number::as_f64)
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_bytes(&self) -> Option<&[u8]> {
+        match self {
+            Value::Bytes(b) => Some(b),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&Vec<Value>> {
+        match self {
+            Value::Array(vec) => Some(vec),
+            _ => None,
+        }
+    }
+
+    pub fn as_array_mut(&mut self) -> Option<&mut Vec<Value>> {
+        match self {
+            Value::Array(vec) => Some(vec),
+            _ => None,
+        }
+    }
+
+    pub fn as_map(&self) -> Option<&Map> {
+        match self {
+            Value::Map(map) => Some(map),
+            _ => None,
+        }
+    }
+
+    pub fn as_map_mut(&mut self) -> Option<&mut Map> {
+        match self {
+            Value::Map(map) => Some(map),
+            _ => None,
+        }
+    }
+}
+
+impl From<&str> for Value {
+    fn from(value: &str) -> Self {
+        Value::String(Arc::from(value))
+    }
 }
 
 #[macro_export]
@@ -47,7 +153,7 @@ macro_rules! value_conversions {
             fn try_from(value: Value) -> Result<Self, Self::Error> {
                 match value {
                     Value::$enum(val) => Ok($to(val)),
-                    _ => Err(ValueParseError),
+                    other => Err(ValueParseError::new(stringify!($ty), other.type_name())),
                 }
             }
         }
@@ -62,8 +168,8 @@ macro_rules! value_conversions {
             type Error = ValueParseError;
             fn try_from(value: Value) -> Result<Self, Self::Error> {
                 match value {
-                    Value::Number($crate::Number::$enum(val)) => Ok($to(val)),
-                    _ => Err(ValueParseError),
+                    Value::Number(val) => <$ty>::try_from(val).map($to),
+                    other => Err(ValueParseError::new(stringify!($ty), other.type_name())),
                 }
             }
         }
@@ -98,32 +204,48 @@ impl<T: TryFrom<Value, Error = ValueParseError>> TryFrom<Value> for Vec<T> {
     type Error = ValueParseError;
     fn try_from(value: Value) -> Result<Self, Self::Error> {
         match value {
-            Value::Array(vec) => vec.into_iter().map(T::try_from).collect(),
-            _ => Err(ValueParseError),
+            Value::Array(vec) => vec
+                .into_iter()
+                .enumerate()
+                .map(|(i, v)| T::try_from(v).map_err(|e| e.at_index(i)))
+                .collect(),
+            other => Err(ValueParseError::new("array", other.type_name())),
         }
     }
 }
 
-impl<T: Into<Value>> From<HashMap<String, T>> for Value {
-    fn from(map: HashMap<String, T>) -> Self {
-        Value::Map(map.into_iter().map(|(k, v)| (k, v.into())).collect())
-    }
-}
+macro_rules! map_conversions {
+    ($($map:ident),* $(,)?) => {
+        $(
+            impl<T: Into<Value>> From<$map<String, T>> for Value {
+                fn from(map: $map<String, T>) -> Self {
+                    Value::Map(map.into_iter().map(|(k, v)| (k, v.into())).collect())
+                }
+            }
 
-impl<T: TryFrom<Value, Error = ValueParseError>> TryFrom<Value> for HashMap<String, T> {
-    type Error = ValueParseError;
+            impl<T: TryFrom<Value, Error = ValueParseError>> TryFrom<Value> for $map<String, T> {
+                type Error = ValueParseError;
 
-    fn try_from(value: Value) -> Result<Self, Self::Error> {
-        match value {
-            Value::Map(map) => map
-                .into_iter()
-                .map(|(k, v)| Ok((k, T::try_from(v)?)))
-                .collect(),
-            _ => Err(ValueParseError),
-        }
-    }
+                fn try_from(value: Value) -> Result<Self, Self::Error> {
+                    match value {
+                        Value::Map(map) => map
+                            .into_iter()
+                            .map(|(k, v)| match T::try_from(v) {
+                                Ok(v) => Ok((k, v)),
+                                Err(e) => Err(e.at(&k)),
+                            })
+                            .collect(),
+                        other => Err(ValueParseError::new("map", other.type_name())),
+                    }
+                }
+            }
+        )*
+    };
 }
 
+// HashMap 转为 Value 时的键顺序不确定, BTreeMap 按键排序
+map_conversions!(HashMap, BTreeMap, IndexMap);
+
 impl<T: Into<Value>> From<Option<T>> for Value {
     fn from(value: Option<T>) -> Self {
         match value {
@@ -144,14 +266,59 @@ impl<T: TryFrom<Value, Error = ValueParseError>> TryFrom<Value> for Option<T> {
     }
 }
 
+///
+/// 转换失败的位置和原因
 #[derive(Debug, Clone, PartialEq)]
-pub struct ValueParseError;
+pub struct ValueParseError {
+    /// JSON Pointer 形式的路径, 根为空字符串
+    pub path: String,
+    /// 期望的类型
+    pub expected: &'static str,
+    /// 实际的类型, 见 [`Value::type_name`]
+    pub actual: &'static str,
+    pub message: Option<String>,
+}
+
+impl ValueParseError {
+    pub fn new(expected: &'static str, actual: &'static str) -> Self {
+        Self {
+            path: String::new(),
+            expected,
+            actual,
+            message: None,
+        }
+    }
+
+    pub fn with_message(mut self, message: impl Into<String>) -> Self {
+        self.message = Some(message.into());
+        self
+    }
+
+    ///
+    /// 错误发生在映射的键 `key` 之下, 由内向外逐层添加
+    pub fn at(mut self, key: &str) -> Self {
+        self.path = format!("/{}{}", crate::path::escape(key), self.path);
+        self
+    }
+
+    ///
+    /// 错误发生在数组的第 `index` 项之下
+    pub fn at_index(mut self, index: usize) -> Self {
+        self.path = format!("/{index}{}", self.path);
+        self
+    }
+}
 
 impl std::error::Error for ValueParseError {}
 
 impl std::fmt::Display for ValueParseError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "value parse error")
+        let path = if self.path.is_empty() { "/" } else { &self.path };
+        write!(f, "{path}: expected {}, found {}", self.expected, self.actual)?;
+        match &self.message {
+            Some(message) => write!(f, " ({message})"),
+            None => Ok(()),
+        }
     }
 }
 
@@ -161,7 +328,39 @@ impl std::fmt::Display for ValueParseError {
 /// 因此需要添加此自动转换;
 /// 
 impl From<Infallible> for ValueParseError {
-    fn from(_value: Infallible) -> Self {
-        Self
+    fn from(value: Infallible) -> Self {
+        match value {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_error() {
+        let value = Value::from(vec![Value::from(1u8), Value::from(300i64)]);
+        let e = Vec::<u8>::try_from(value).unwrap_err();
+        assert_eq!((e.path.as_str(), e.expected, e.actual), ("/1", "u8", "number"));
+        assert_eq!(e.to_string(), "/1: expected u8, found number (300i64 cannot be converted losslessly)");
+
+        let value = Value::from(HashMap::from([("a/b".to_string(), Value::from("x"))]));
+        let e = HashMap::<String, Vec<bool>>::try_from(value).unwrap_err();
+        assert_eq!(e.to_string(), "/a~1b: expected array, found string");
+        assert!(bool::try_from(Value::Null).is_err());
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_map_order() {
+        let value: Value = serde_json::from_str(r#"{"b": 1, "a": 2, "c": 3}"#).unwrap();
+        let map = value.as_map().unwrap();
+        assert_eq!(map.keys().collect::<Vec<_>>(), ["b", "a", "c"]);
+        assert_eq!(serde_json::to_string(&value).unwrap(), r#"{"b":1,"a":2,"c":3}"#);
+
+        let sorted = Value::from(BTreeMap::<String, Value>::from_iter(map.clone()));
+        assert_eq!(sorted.as_map().unwrap().keys().collect::<Vec<_>>(), ["a", "b", "c"]);
+        assert_eq!(sorted, value);
+        let map = HashMap::<String, i64>::try_from(value).unwrap();
+        assert_eq!(map["c"], 3);
+    }
+}
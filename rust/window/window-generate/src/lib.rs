@@ -96,6 +96,8 @@ declare global {
   interface Window {
     bridge: {
       send<T>(command: string, payload: any | undefined): Promise<T>;
+      publish(topic: string, payload: any, retain?: boolean): Promise<number>;
+      subscribe<T = any>(pattern: string, callback: (payload: T, topic: string) => void): Promise<() => Promise<void>>;
     };
   }
 }
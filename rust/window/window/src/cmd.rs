@@ -1,4 +1,7 @@
-use crate::{IpcReqWithId, IpcResponse, Message, script::bridge_handler_call};
+use crate::{
+    BusEvent, IpcReqWithId, IpcResponse, Message,
+    script::{bridge_event_call, bridge_handler_call},
+};
 use dashmap::DashMap;
 use libcommon::{ErrMapperExt, newerr, prelude::Result};
 use std::pin::Pin;
@@ -58,3 +61,13 @@ pub fn resp2web(webview: &wry::WebView, msg: &IpcResponse) -> Result<()> {
     webview.evaluate_script(&bridge_handler_call(&json_str))?;
     Ok(())
 }
+
+///
+/// 推送订阅消息给前端
+///
+/// script要与注入的代码对应 [`crate::script::setup_script`]
+pub fn event2web(webview: &wry::WebView, event: &BusEvent) -> Result<()> {
+    let json_str = serde_json::to_string(event).map_err(|e| newerr!(e))?;
+    webview.evaluate_script(&bridge_event_call(&json_str))?;
+    Ok(())
+}
@@ -12,6 +12,8 @@ pub enum UserEvent {
     IpcHandle(IpcReqWithId),
     /// 收到IPC消息回复, 转发给Loop回复
     RespHandle(WindowId, IpcResponse),
+    /// 窗口订阅的消息, 转发给Loop推送给前端
+    BusHandle(WindowId, BusEvent),
 }
 
 unsafe impl Send for UserEvent {}
@@ -60,6 +62,72 @@ impl IpcReqWithId {
     }
 }
 
+/// 推送给前端订阅者的消息, `key` 为前端订阅时生成的标识
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct BusEvent {
+    pub key: u64,
+    pub topic: String,
+    pub payload: Message,
+}
+
+/// 前端通过 `bridge.publish` / `bridge.subscribe` 发送的总线命令
+#[derive(Debug)]
+pub enum BusCommand {
+    Publish {
+        topic: String,
+        payload: Message,
+        retain: bool,
+    },
+    Subscribe {
+        key: u64,
+        pattern: String,
+    },
+    Unsubscribe {
+        key: u64,
+    },
+}
+
+#[derive(Deserialize)]
+struct PublishParam {
+    topic: String,
+    #[serde(default)]
+    payload: Message,
+    #[serde(default)]
+    retain: bool,
+}
+
+#[derive(Deserialize)]
+struct SubscribeParam {
+    key: u64,
+    #[serde(default)]
+    pattern: String,
+}
+
+impl BusCommand {
+    ///
+    /// 不是总线命令时返回 `None`
+    pub fn parse(req: &IpcRequest) -> Option<Result<Self, serde_json::Error>> {
+        let payload = req.payload.clone().unwrap_or_default();
+        let cmd = match req.command.as_str() {
+            "Publish" => serde_json::from_value(payload).map(|p: PublishParam| Self::Publish {
+                topic: p.topic,
+                payload: p.payload,
+                retain: p.retain,
+            }),
+            "Subscribe" => {
+                serde_json::from_value(payload).map(|p: SubscribeParam| Self::Subscribe {
+                    key: p.key,
+                    pattern: p.pattern,
+                })
+            }
+            "Unsubscribe" => serde_json::from_value(payload)
+                .map(|p: SubscribeParam| Self::Unsubscribe { key: p.key }),
+            _ => return None,
+        };
+        Some(cmd)
+    }
+}
+
 #[derive(Debug)]
 pub enum SysWindowEvent {
     DragStart,
@@ -9,6 +9,9 @@ pub const BRIDGE_PUBLIC: &str = "bridge";
 /// 后端回调前端响应处理函数的方法名（挂载在内部桥接对象上）
 pub const BRIDGE_HANDLER_METHOD: &str = "_handleResponse";
 
+/// 后端推送订阅消息的方法名（挂载在内部桥接对象上）
+pub const BRIDGE_EVENT_METHOD: &str = "_handleEvent";
+
 pub const ERROR_PARAM_NAME: &str = "error";
 
 /// 完整的后端调用表达式，用于 evaluate_script
@@ -17,6 +20,12 @@ pub fn bridge_handler_call(response_json: &str) -> String {
     format!("window.{}.{}({});", BRIDGE_INTERNAL, BRIDGE_HANDLER_METHOD, response_json)
 }
 
+/// 推送订阅消息的调用表达式，用于 evaluate_script
+/// 格式：window.__bridge._handleEvent(event)
+pub fn bridge_event_call(event_json: &str) -> String {
+    format!("window.{}.{}({});", BRIDGE_INTERNAL, BRIDGE_EVENT_METHOD, event_json)
+}
+
 pub(crate) fn setup_script() -> String {
     format!(r#"
 /**
@@ -34,6 +43,11 @@ pub(crate) fn setup_script() -> String {
  * 
  * 同时处理窗口系统命令（拖动、关闭、最小化），这些命令也通过 {public}.send 发送，
  * 但无需等待响应。
+ *
+ * 消息总线：{public}.publish(topic, payload, retain) 发布消息；
+ * {public}.subscribe(pattern, callback) 订阅主题，返回取消订阅的函数，
+ * 后端通过 window.{internal}.{event} 推送消息：
+ *   {{ key: number, topic: string, payload: any }}
  */
 
 (function() {{
@@ -41,6 +55,8 @@ pub(crate) fn setup_script() -> String {
   const BRIDGE = {{
     _nextId: 1,                 // 自增请求 ID
     _callbacks: new Map(),       // 存储等待中的 Promise 回调 {{ resolve, reject }}
+    _nextKey: 1,                 // 自增订阅标识
+    _subscriptions: new Map(),   // 订阅标识 -> 回调
 
     /**
      * 处理后端返回的响应
@@ -61,6 +77,49 @@ pub(crate) fn setup_script() -> String {
       }}
     }},
 
+    /**
+     * 处理后端推送的订阅消息
+     * @param {{Object}} event - 包含 key、topic 和 payload
+     */
+    {event}: function(event) {{
+      const callback = this._subscriptions.get(event.key);
+      if (callback) callback(event.payload, event.topic);
+    }},
+
+    /**
+     * 发布消息
+     * @param {{string}} topic - 主题，不能包含通配符
+     * @param {{any}} payload - 消息内容
+     * @param {{boolean}} retain - 是否保留为该主题的最新消息
+     * @returns {{Promise<number>}} 收到消息的订阅数
+     */
+    publish: function(topic, payload, retain) {{
+      return this.send('Publish', {{ topic, payload, retain: !!retain }});
+    }},
+
+    /**
+     * 订阅主题，+ 匹配一个层级，# 匹配之后的所有层级
+     * @param {{string}} pattern - 订阅的主题
+     * @param {{Function}} callback - (payload, topic) => void
+     * @returns {{Promise<Function>}} 取消订阅的函数
+     */
+    subscribe: function(pattern, callback) {{
+      const key = this._nextKey++;
+      // 先注册回调，订阅时补发的保留消息可能先于响应到达
+      this._subscriptions.set(key, callback);
+      const unsubscribe = () => {{
+        this._subscriptions.delete(key);
+        return this.send('Unsubscribe', {{ key }});
+      }};
+      return this.send('Subscribe', {{ key, pattern }}).then(
+        () => unsubscribe,
+        (e) => {{
+          this._subscriptions.delete(key);
+          throw e;
+        }}
+      );
+    }},
+
     /**
      * 发送命令到后端
      * @param {{string}} command - 命令名称
@@ -81,7 +140,9 @@ pub(crate) fn setup_script() -> String {
   // 将内部对象挂载到全局（用于后端回调），同时暴露简化版的公共 API
   window.{internal} = BRIDGE;
   window.{public} = {{
-    send: window.{internal}.send.bind(window.{internal})
+    send: window.{internal}.send.bind(window.{internal}),
+    publish: window.{internal}.publish.bind(window.{internal}),
+    subscribe: window.{internal}.subscribe.bind(window.{internal})
   }};
 
   // ----- 2. 窗口控制功能（拖动、关闭、最小化）-----
@@ -133,6 +194,7 @@ pub(crate) fn setup_script() -> String {
         internal = BRIDGE_INTERNAL,
         public = BRIDGE_PUBLIC,
         handler = BRIDGE_HANDLER_METHOD,
+        event = BRIDGE_EVENT_METHOD,
         error = ERROR_PARAM_NAME
     )
 }
\ No newline at end of file
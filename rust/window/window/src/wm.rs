@@ -1,8 +1,10 @@
 use crate::{
-    IpcReqWithId, IpcRequest, IpcResponse, Message, SysWindowEvent, UserEvent,
-    cmd::{CommandHander, Error, resp2web},
+    BusCommand, BusEvent, IpcReqWithId, IpcRequest, IpcResponse, Message, SysWindowEvent,
+    UserEvent,
+    cmd::{CommandHander, Error, event2web, resp2web},
     script::setup_script,
 };
+use bus::{Bus, Source, SubscriptionId};
 use dashmap::DashMap;
 use libcommon::prelude::*;
 use std::{pin::Pin, sync::Arc};
@@ -19,6 +21,9 @@ pub struct WindowManager<H> {
     windows: DashMap<WindowId, Window>,
     handlers: Arc<CommandHander<H>>,
     state: WindowState<H>,
+    bus: Option<Arc<Bus>>,
+    /// 窗口和前端订阅标识对应的总线订阅
+    subscriptions: DashMap<(WindowId, u64), SubscriptionId>,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
@@ -45,9 +50,18 @@ impl<H: Send + Sync + 'static> WindowManager<H> {
             windows: DashMap::new(),
             handlers: Arc::new(CommandHander::new()),
             state: WindowState(state),
+            bus: None,
+            subscriptions: DashMap::new(),
         }
     }
 
+    ///
+    /// 设置消息总线, 前端可通过 `bridge.publish` / `bridge.subscribe` 发布和订阅消息
+    pub fn with_bus(mut self, bus: Arc<Bus>) -> Self {
+        self.bus = Some(bus);
+        self
+    }
+
     pub fn create_window(&self, title: &str, url: &str) -> Result<WindowId> {
         let window = Window::create_default(title, url, &self.event)?;
         let id = window.id;
@@ -120,6 +134,10 @@ impl<H: Send + Sync + 'static> WindowManager<H> {
                                     }
                                 }
                                 SysWindowEvent::Close => {
+                                    if let Some(bus) = &self.bus {
+                                        bus.unsubscribe_all(&Source::Window(id.to_string()));
+                                    }
+                                    self.subscriptions.retain(|(window, _), _| window != id);
                                     if let Some(remove) = self.windows.remove(id) {
                                         drop(remove);
                                         debug!("close window({id:?})");
@@ -136,6 +154,26 @@ impl<H: Send + Sync + 'static> WindowManager<H> {
                                     }
                                 }
                             }
+                        } else if let Some(cmd) = BusCommand::parse(&msg.req) {
+                            let result = cmd.map_err(|e| e.to_string()).and_then(|cmd| {
+                                bus_command(
+                                    self.bus.as_ref(),
+                                    &self.subscriptions,
+                                    *id,
+                                    cmd,
+                                    &proxy,
+                                )
+                            });
+                            let payload = match result {
+                                Ok(payload) => payload,
+                                std::result::Result::Err(e) => serde_json::json!({"error": e}),
+                            };
+                            let resp = IpcResponse::from(msg.req.id, payload);
+                            if let Some(win) = self.windows.get(id)
+                                && resp2web(&win.webview, &resp).is_err()
+                            {
+                                warn!("failed to send response to webview({id:?})");
+                            }
                         } else {
                             let handlers = self.handlers.clone();
                             let proxy = proxy.clone();
@@ -162,6 +200,14 @@ impl<H: Send + Sync + 'static> WindowManager<H> {
                         }
                         warn!("failed to send response to webview({id:?})");
                     }
+                    UserEvent::BusHandle(id, event) => {
+                        if let Some(win) = self.windows.get(&id)
+                            && event2web(&win.webview, &event).is_ok()
+                        {
+                            return;
+                        }
+                        warn!("failed to send bus event to webview({id:?})");
+                    }
                 },
                 _ => {}
             }
@@ -169,6 +215,59 @@ impl<H: Send + Sync + 'static> WindowManager<H> {
     }
 }
 
+///
+/// 处理前端的总线命令, 订阅的消息通过事件循环推送给窗口
+fn bus_command(
+    bus: Option<&Arc<Bus>>,
+    subscriptions: &DashMap<(WindowId, u64), SubscriptionId>,
+    window: WindowId,
+    cmd: BusCommand,
+    proxy: &Arc<EventLoopProxy<UserEvent>>,
+) -> std::result::Result<Message, String> {
+    let bus = bus.ok_or("no bus configured")?;
+    let source = Source::Window(window.to_string());
+    match cmd {
+        BusCommand::Publish {
+            topic,
+            payload,
+            retain,
+        } => {
+            let delivered = bus
+                .publish(source, &topic, payload, retain)
+                .map_err(|e| e.to_string())?;
+            Ok(delivered.into())
+        }
+        BusCommand::Subscribe { key, pattern } => {
+            let proxy = proxy.clone();
+            let id = bus
+                .subscribe(source, &pattern, move |_, message| {
+                    let event = BusEvent {
+                        key,
+                        topic: message.topic.clone(),
+                        payload: message.payload.clone(),
+                    };
+                    if proxy
+                        .send_event(UserEvent::BusHandle(window, event))
+                        .is_err()
+                    {
+                        warn!("failed to send event, the event loop has been destroyed");
+                    }
+                })
+                .map_err(|e| e.to_string())?;
+            if let Some(old) = subscriptions.insert((window, key), id) {
+                bus.unsubscribe(old);
+            }
+            Ok(Message::Null)
+        }
+        BusCommand::Unsubscribe { key } => {
+            if let Some((_, id)) = subscriptions.remove(&(window, key)) {
+                bus.unsubscribe(id);
+            }
+            Ok(Message::Null)
+        }
+    }
+}
+
 struct Window {
     id: WindowId,
     window: TaoWindow,
@@ -9,6 +9,7 @@ export interface ScanResult {
     loaded: string[];
     failds: ScanFailItem[];
     ignores: string[];
+    disabled: string[];
 }
 
 export interface Plugin {
@@ -16,6 +17,70 @@ export interface Plugin {
     name: string;
     version: string;
     url: string;
+    enabled: boolean;
+    state: PluginState;
+}
+
+export type PluginState = "Registered" | "Loaded" | "IdleUnloaded" | "Disabled";
+
+export interface StorePlugin {
+    name: string;
+    description: string;
+    versions: string[];
+    latest: string | null;
+    id: string | null;
+    installed: string | null;
+    has_update: boolean;
+}
+
+export interface PluginMetrics {
+    id: string;
+    name: string;
+    method: string;
+    calls: number;
+    errors: number;
+    in_flight: number;
+    p50: number | null;
+    p90: number | null;
+    p99: number | null;
+}
+
+export interface Recording {
+    recording: boolean;
+    path: string | null;
+}
+
+export interface BatchParam {
+    calls: BatchCall[];
+    mode: BatchMode;
+}
+
+export type BatchMode = "AllOrNothing" | "ContinueOnError";
+
+export interface BatchCall {
+    id: string;
+    method: string;
+    params: any;
+}
+
+export interface BatchItem {
+    ok: boolean;
+    result: any | null;
+    error: string | null;
+}
+
+export interface PluginJob {
+    id: string;
+    plugin: string;
+    name: string;
+    method: string;
+    schedule: string;
+    paused: boolean;
+    running: boolean;
+    next_run: number | null;
+    last_run: number | null;
+    last_duration: number | null;
+    last_result: BatchItem | null;
 }
 
 export interface ScanFailItem {
@@ -29,6 +94,8 @@ declare global {
   interface Window {
     bridge: {
       send<T>(command: string, payload: any | undefined): Promise<T>;
+      publish(topic: string, payload: any, retain?: boolean): Promise<number>;
+      subscribe<T = any>(pattern: string, callback: (payload: T, topic: string) => void): Promise<() => Promise<void>>;
     };
   }
 }
@@ -45,10 +112,90 @@ export const commands = {
 	 * * 调用插件
 	 */
 	call: (args: { id: string }): Promise<string> => window.bridge.send<string>('call', args),
+	/**
+	 * 
+	 * * 并发调用多个插件方法, 结果按顺序返回
+	 */
+	call_batch: (args: { p: BatchParam }): Promise<BatchItem[]> => window.bridge.send<BatchItem[]>('call_batch', args),
 	/**
 	 * 
 	 * * 扫描指定位置的插件
 	 */
 	scan_plugins: (args: { p: ScanParam }): Promise<ScanResult> => window.bridge.send<ScanResult>('scan_plugins', args),
+	/**
+	 * 
+	 * * 从安装包安装插件并加载
+	 */
+	install_plugin: (args: { path: string }): Promise<Plugin> => window.bridge.send<Plugin>('install_plugin', args),
+	/**
+	 * 
+	 * * 卸载并删除通过安装包安装的插件
+	 */
+	uninstall_plugin: (args: { id: string }): Promise<Plugin> => window.bridge.send<Plugin>('uninstall_plugin', args),
+	/**
+	 * 
+	 * * 使用新的安装包更新插件
+	 */
+	update_plugin: (args: { id: string, path: string }): Promise<Plugin> => window.bridge.send<Plugin>('update_plugin', args),
+	/**
+	 * 
+	 * * 列出插件仓库中的插件及其安装状态
+	 */
+	store_plugins: (args: { index: string }): Promise<StorePlugin[]> => window.bridge.send<StorePlugin[]>('store_plugins', args),
+	/**
+	 * 
+	 * * 从插件仓库安装插件, 未指定版本时安装最高版本
+	 */
+	store_install: (args: { index: string, name: string, version: string | null }): Promise<Plugin> => window.bridge.send<Plugin>('store_install', args),
+	/**
+	 * 
+	 * * 将已安装的插件更新到插件仓库中的最高版本
+	 */
+	store_update: (args: { index: string, name: string }): Promise<Plugin> => window.bridge.send<Plugin>('store_update', args),
+	/**
+	 * 
+	 * * 返回各插件方法的调用指标
+	 */
+	plugin_metrics: (): Promise<PluginMetrics[]> => window.bridge.send<PluginMetrics[]>('plugin_metrics', undefined),
+	/**
+	 * 
+	 * * 开始录制插件调用, 未指定文件时自动生成
+	 */
+	start_recording: (args: { path: string | null }): Promise<Recording> => window.bridge.send<Recording>('start_recording', args),
+	/**
+	 * 
+	 * * 停止录制插件调用, 返回录制的文件
+	 */
+	stop_recording: (): Promise<Recording> => window.bridge.send<Recording>('stop_recording', undefined),
+	/**
+	 * 
+	 * * 返回插件调用的录制状态
+	 */
+	recording_state: (): Promise<Recording> => window.bridge.send<Recording>('recording_state', undefined),
+	/**
+	 * 
+	 * * 清除插件方法的缓存结果, 未指定插件时清除全部, 返回清除的数量
+	 */
+	clear_cache: (args: { id: string | null }): Promise<number> => window.bridge.send<number>('clear_cache', args),
+	/**
+	 * 
+	 * * 启用并加载插件, 重启后保持启用
+	 */
+	enable_plugin: (args: { id: string }): Promise<Plugin> => window.bridge.send<Plugin>('enable_plugin', args),
+	/**
+	 * 
+	 * * 禁用并卸载插件, 插件仍会被列出, 重启后保持禁用
+	 */
+	disable_plugin: (args: { id: string }): Promise<Plugin> => window.bridge.send<Plugin>('disable_plugin', args),
+	/**
+	 * 
+	 * * 返回插件声明的定时任务及其执行状态
+	 */
+	list_jobs: (): Promise<PluginJob[]> => window.bridge.send<PluginJob[]>('list_jobs', undefined),
+	/**
+	 * 
+	 * * 立即执行插件的定时任务
+	 */
+	run_job: (args: { id: string, name: string }): Promise<BatchItem> => window.bridge.send<BatchItem>('run_job', args),
 };
 
@@ -0,0 +1,54 @@
+import { commands } from "../generate/bridge";
+import type { BatchMode } from "../generate/bridge";
+
+// 一次插件调用, T 为该方法的返回类型
+export interface PluginCall<T = unknown> {
+  id: string;
+  method: string;
+  params?: unknown;
+  // 仅用于推断返回类型
+  readonly _result?: T;
+}
+
+export type BatchOutcome<T> =
+  | { ok: true; result: T }
+  | { ok: false; error: string };
+
+type Outcomes<C extends readonly PluginCall<any>[]> = {
+  [K in keyof C]: C[K] extends PluginCall<infer T> ? BatchOutcome<T> : never;
+};
+
+type Results<C extends readonly PluginCall<any>[]> = {
+  [K in keyof C]: C[K] extends PluginCall<infer T> ? T : never;
+};
+
+export function pluginCall<T>(id: string, method: string, params?: unknown): PluginCall<T> {
+  return { id, method, params };
+}
+
+// 并发调用, 各项独立返回结果或错误, 顺序与 calls 相同
+export async function callBatch<const C extends readonly PluginCall<any>[]>(
+  calls: C,
+): Promise<Outcomes<C>> {
+  const items = await send(calls, "ContinueOnError");
+  return items.map((item) =>
+    item.ok ? { ok: true, result: item.result } : { ok: false, error: item.error ?? "" },
+  ) as Outcomes<C>;
+}
+
+// 并发调用, 任一项失败时抛出该错误
+export async function callAll<const C extends readonly PluginCall<any>[]>(
+  calls: C,
+): Promise<Results<C>> {
+  const items = await send(calls, "AllOrNothing");
+  return items.map((item) => item.result) as Results<C>;
+}
+
+function send(calls: readonly PluginCall<any>[], mode: BatchMode) {
+  return commands.call_batch({
+    p: {
+      calls: calls.map(({ id, method, params }) => ({ id, method, params: params ?? null })),
+      mode,
+    },
+  });
+}